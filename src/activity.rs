@@ -1,4 +1,5 @@
-use crate::windows::{self, AppCategory, ManagedWindow, categorize_process};
+use crate::backend::WindowBackend;
+use crate::windows::{AppCategory, ManagedWindow, categorize_process};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{mpsc, Arc, Mutex};
@@ -53,6 +54,8 @@ pub struct ActivityTracker {
     last_save: Instant,
     last_decay: Instant,
     decay_half_life_days: f64,
+    /// Whether the database is saved to the activity file.
+    persist: bool,
 }
 
 fn now_ts() -> f64 {
@@ -64,7 +67,7 @@ fn now_ts() -> f64 {
 
 impl ActivityTracker {
     /// Start the activity tracker with a background focus poller thread.
-    pub fn new(decay_half_life_days: f64) -> Self {
+    pub fn new(decay_half_life_days: f64, backend: Arc<dyn WindowBackend>) -> Self {
         let (tx, rx) = mpsc::channel();
 
         // Load existing DB
@@ -75,7 +78,7 @@ impl ActivityTracker {
         std::thread::Builder::new()
             .name("activity-poller".into())
             .spawn(move || {
-                focus_poller(tx);
+                focus_poller(tx, backend);
             })
             .expect("failed to spawn activity poller thread");
 
@@ -87,6 +90,23 @@ impl ActivityTracker {
             last_save: Instant::now(),
             last_decay: Instant::now(),
             decay_half_life_days,
+            persist: true,
+        }
+    }
+
+    /// Tracker over `db` that doesn't poll focus or touch the activity file.
+    #[cfg(test)]
+    pub fn from_db(db: ActivityDb, decay_half_life_days: f64) -> Self {
+        let (_, rx) = mpsc::channel();
+        Self {
+            rx,
+            session: HashMap::new(),
+            db: Arc::new(Mutex::new(db)),
+            current_focus: None,
+            last_save: Instant::now(),
+            last_decay: Instant::now(),
+            decay_half_life_days,
+            persist: false,
         }
     }
 
@@ -201,6 +221,9 @@ impl ActivityTracker {
 
     /// Save activity DB to disk.
     pub fn save(&self) {
+        if !self.persist {
+            return;
+        }
         if let Some(path) = crate::config::activity_path() {
             if let Some(parent) = path.parent() {
                 let _ = std::fs::create_dir_all(parent);
@@ -287,14 +310,14 @@ impl Drop for ActivityTracker {
     }
 }
 
-/// Background thread: polls GetForegroundWindow every 1s, sends FocusEvent on change.
-fn focus_poller(tx: mpsc::Sender<FocusEvent>) {
+/// Background thread: polls the foreground window every 1s, sends FocusEvent on change.
+fn focus_poller(tx: mpsc::Sender<FocusEvent>, backend: Arc<dyn WindowBackend>) {
     let mut last_hwnd: Option<isize> = None;
 
     loop {
         std::thread::sleep(Duration::from_secs(1));
 
-        let hwnd = match windows::get_foreground_window() {
+        let hwnd = match backend.foreground_window() {
             Some(h) => h,
             None => continue,
         };
@@ -305,12 +328,12 @@ fn focus_poller(tx: mpsc::Sender<FocusEvent>) {
         }
         last_hwnd = Some(hwnd);
 
-        let process_name = match windows::get_process_name_for_hwnd(hwnd) {
+        let process_name = match backend.process_name(hwnd) {
             Some(n) => n,
             None => continue,
         };
 
-        let title = windows::get_window_title(hwnd);
+        let title = backend.window_title(hwnd);
 
         let event = FocusEvent {
             process_name,
@@ -1,21 +1,43 @@
 use crate::activity::ActivityTracker;
 use crate::arrange;
+#[cfg(windows)]
+use crate::backend::win32;
+use crate::backend::{self, WindowBackend};
 use crate::config::{self, Config};
 use crate::gui;
-use crate::layout::{LayoutPreset, builtin_presets};
+use crate::history;
+use crate::launcher;
+use crate::session;
+use crate::layout::{
+    CellSpan, GridShape, LayoutPreset, RelRect, builtin_presets, normalize_weights, sanitize_merges,
+};
 use crate::theme::THEMES;
+use crate::watch::{WatchTarget, Watcher};
+#[cfg(windows)]
 use crate::tray;
-use crate::windows::{ManagedWindow, TargetFilter, find_windows};
+use crate::windows::ManagedWindow;
 use raw_window_handle::HasWindowHandle;
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use windows::Win32::Foundation::HWND;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DividerAxis {
     Col,
     Row,
+    /// Boundary between the main area and the side stack of a main/side preset.
+    Main,
+    /// Boundary between two weighted slots of a column, row or main/side preset.
+    Slot,
+}
+
+/// In-progress mouse interaction in the freeform layout editor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FreeDrag {
+    /// Drawing a new rect from this corner (fractions of the work area).
+    Draw(f32, f32),
+    Move(usize),
+    Resize(usize),
 }
 
 pub struct UpdateInfo {
@@ -24,12 +46,17 @@ pub struct UpdateInfo {
 }
 
 pub struct PsmApp {
+    #[cfg(windows)]
     _tray_icon: Option<tray::TrayIcon>, // must stay alive on main thread
     pub gui_visible: bool,
     pub app_hwnd: isize,
+    pub backend: Arc<dyn WindowBackend>,
     pub managed_windows: Vec<ManagedWindow>,
     pub config: Config,
-    pub presets: Vec<(String, LayoutPreset)>,
+    /// The config as last saved, shared with the tray thread so hotkeys and
+    /// tray entries arrange with current settings.
+    live_config: Arc<Mutex<Config>>,
+    pub presets: Vec<(String, LayoutPreset, Option<GridShape>)>,
     pub selected_preset: usize,
     last_refresh: Instant,
     pub custom_cols: u32,
@@ -43,9 +70,19 @@ pub struct PsmApp {
     pub col_weights: Vec<f32>,
     pub row_weights: Vec<f32>,
     pub dragging_divider: Option<(DividerAxis, usize)>,
+    pub merged_cells: Vec<CellSpan>,
+    /// Cell (col, row) where a shift-drag merge started in the preview.
+    pub merge_drag_start: Option<(u32, u32)>,
+    pub use_free: bool,
+    pub free_rects: Vec<RelRect>,
+    pub free_drag: Option<FreeDrag>,
     pub activity: ActivityTracker,
+    watcher: Watcher,
     pub save_grid_name: String,
     pub show_save_dialog: bool,
+    pub session_name: String,
+    /// Process whose Pin button was clicked, waiting for a preview cell.
+    pub pinning: Option<String>,
 }
 
 impl PsmApp {
@@ -53,47 +90,39 @@ impl PsmApp {
         let app_hwnd = cc
             .window_handle()
             .ok()
-            .and_then(|wh| {
-                if let raw_window_handle::RawWindowHandle::Win32(h) = wh.as_raw() {
-                    Some(h.hwnd.get() as isize)
-                } else {
-                    None
-                }
+            .and_then(|wh| match wh.as_raw() {
+                raw_window_handle::RawWindowHandle::Win32(h) => Some(h.hwnd.get()),
+                raw_window_handle::RawWindowHandle::Xlib(h) => Some(h.window as isize),
+                raw_window_handle::RawWindowHandle::Xcb(h) => Some(h.window.get() as isize),
+                _ => None,
             })
             .unwrap_or(0);
 
-        // Create tray icon (stays on main thread) and extract menu IDs for bg thread.
-        let (tray_icon, tray_menu_ids) = match tray::create_tray(&config) {
-            Some((icon, ids)) => (Some(icon), Some(ids)),
+        let backend = backend::native();
+        let live_config = Arc::new(Mutex::new(config.clone()));
+
+        // Create tray icon (stays on main thread) and spawn the tray event
+        // thread, which runs independently of eframe's render loop. eframe
+        // skips update() for hidden windows, so tray events must be polled there.
+        #[cfg(windows)]
+        let tray_icon = match tray::create_tray(&config) {
+            Some((icon, menu_ids)) => {
+                let ctx = cc.egui_ctx.clone();
+                let hwnd = app_hwnd;
+                let live = Arc::clone(&live_config);
+                let backend = Arc::clone(&backend);
+                std::thread::spawn(move || {
+                    tray_event_loop(menu_ids, ctx, hwnd, live, backend);
+                });
+                Some(icon)
+            }
             None => {
                 log::warn!("Failed to create system tray icon");
-                (None, None)
+                None
             }
         };
 
-        // Spawn tray event thread — runs independently of eframe's render loop.
-        // eframe skips update() for hidden windows, so tray events must be polled here.
-        if let Some(menu_ids) = tray_menu_ids {
-            let ctx = cc.egui_ctx.clone();
-            let hwnd = app_hwnd;
-            let cfg = config.clone();
-            std::thread::spawn(move || {
-                tray_event_loop(menu_ids, ctx, hwnd, &cfg);
-            });
-        }
-
-        let mut presets = builtin_presets();
-        for layout_def in &config.layout {
-            if let Some(preset) = layout_def.to_preset() {
-                presets.push((layout_def.name.clone(), preset));
-            }
-        }
-        for sg in &config.saved_grid {
-            presets.push((
-                sg.name.clone(),
-                LayoutPreset::Grid { cols: sg.cols, rows: sg.rows },
-            ));
-        }
+        let presets = collect_presets(&config);
 
         let theme_index = config.defaults.theme.min(THEMES.len() - 1);
         let selected_preset = config.defaults.selected_preset;
@@ -112,6 +141,10 @@ impl PsmApp {
             vec![1.0 / custom_rows as f32; custom_rows as usize]
         };
 
+        let use_free = config.defaults.use_free && !use_custom;
+        let free_rects = config.defaults.free_rects.iter().map(|r| r.clamped()).collect();
+        let merged_cells = sanitize_merges(custom_cols, custom_rows, &config.defaults.merged_cells);
+
         let update_info: Arc<Mutex<Option<UpdateInfo>>> = Arc::new(Mutex::new(None));
 
         // Spawn background update checker
@@ -122,14 +155,20 @@ impl PsmApp {
             });
         }
 
-        let activity = ActivityTracker::new(config.defaults.decay_half_life_days);
+        let activity =
+            ActivityTracker::new(config.defaults.decay_half_life_days, Arc::clone(&backend));
+
+        let watcher = Watcher::new(Arc::clone(&backend));
 
         let mut app = Self {
+            #[cfg(windows)]
             _tray_icon: tray_icon,
             gui_visible: true,
             app_hwnd,
+            backend,
             managed_windows: Vec::new(),
             config,
+            live_config,
             presets,
             selected_preset,
             last_refresh: Instant::now(),
@@ -144,26 +183,58 @@ impl PsmApp {
             col_weights,
             row_weights,
             dragging_divider: None,
+            merged_cells,
+            merge_drag_start: None,
+            use_free,
+            free_rects,
+            free_drag: None,
             activity,
+            watcher,
             save_grid_name: String::new(),
             show_save_dialog: false,
+            session_name: String::new(),
+            pinning: None,
         };
 
         app.refresh_windows();
         app
     }
 
+    /// Write the config to disk and hand it to the tray thread.
+    pub fn save_config(&self) {
+        config::save(&self.config);
+        if let Ok(mut live) = self.live_config.lock() {
+            *live = self.config.clone();
+        }
+    }
+
+    /// Name of the selected layout in the picker; custom grids and the
+    /// freeform editor have none.
+    pub fn layout_name(&self) -> Option<String> {
+        if self.use_custom || self.use_free {
+            return None;
+        }
+        self.presets.get(self.selected_preset).map(|(name, _, _)| name.clone())
+    }
+
     pub fn active_preset(&self) -> LayoutPreset {
         if self.use_custom {
             LayoutPreset::Grid {
                 cols: self.custom_cols,
                 rows: self.custom_rows,
             }
+        } else if self.use_free {
+            LayoutPreset::Free(self.free_rects.clone())
         } else {
-            self.presets
+            let preset = self
+                .presets
                 .get(self.selected_preset)
-                .map(|(_, p)| p.clone())
-                .unwrap_or(LayoutPreset::Grid { cols: 2, rows: 2 })
+                .map(|(_, p, _)| p.clone())
+                .unwrap_or(LayoutPreset::Grid { cols: 2, rows: 2 });
+            match self.config.defaults.main_ratio {
+                Some(ratio) => preset.with_main_ratio(ratio),
+                None => preset,
+            }
         }
     }
 
@@ -183,27 +254,89 @@ impl PsmApp {
             && self.row_weights.iter().all(|w| (w - eq_row).abs() < 0.001)
     }
 
+    /// Weights and merges for the custom grid, if they differ from a plain grid,
+    /// or the slot weights of the selected preset.
+    pub fn grid_shape(&self) -> Option<GridShape> {
+        if self.use_custom {
+            if !self.weights_are_uniform() || !self.merged_cells.is_empty() {
+                Some(GridShape {
+                    col_weights: self.col_weights.clone(),
+                    row_weights: self.row_weights.clone(),
+                    merges: self.merged_cells.clone(),
+                    weights: Vec::new(),
+                    spacing: None,
+                })
+            } else {
+                None
+            }
+        } else if self.use_free {
+            None
+        } else {
+            let entry = self.presets.get(self.selected_preset).and_then(|(_, _, s)| s.clone());
+            selected_shape(entry, &self.active_preset(), &self.config.defaults.slot_weights)
+        }
+    }
+
+    /// Slot weights of the selected preset (even if none are set), or empty
+    /// if the preset doesn't take weights.
+    pub fn preset_weights(&self) -> Vec<f32> {
+        let n = self.active_preset().weight_count();
+        match self.grid_shape() {
+            Some(shape) if shape.weights.len() == n => shape.weights,
+            _ => vec![1.0 / n as f32; n],
+        }
+    }
+
+    /// Number of slots in the active layout, after merging grid cells.
+    pub fn slot_count(&self) -> usize {
+        if self.use_custom {
+            crate::layout::grid_slot_cells(self.custom_cols, self.custom_rows, &self.merged_cells).len()
+        } else {
+            match (self.active_preset(), self.grid_shape()) {
+                (LayoutPreset::Grid { cols, rows }, Some(shape)) => {
+                    crate::layout::grid_slot_cells(cols, rows, &shape.merges).len()
+                }
+                (preset, _) => preset.sized_for(self.managed_windows.len()).slot_count(),
+            }
+        }
+    }
+
+    /// Replace the custom grid's merged regions. Slot indices shift when
+    /// merges change, so disabled cells are reset.
+    pub fn set_merged_cells(&mut self, merges: Vec<CellSpan>) {
+        self.merged_cells = sanitize_merges(self.custom_cols, self.custom_rows, &merges);
+        self.disabled_cells.clear();
+        self.config.defaults.merged_cells = self.merged_cells.clone();
+        self.save_config();
+    }
+
     pub fn apply_current_layout(&self) {
         let preset = self.active_preset();
-        let filter = TargetFilter::from_str(&self.config.defaults.target);
-        let weights = if self.use_custom && !self.weights_are_uniform() {
-            Some((self.col_weights.as_slice(), self.row_weights.as_slice()))
-        } else {
-            None
-        };
+        let filter = self.config.target_filter();
+        let grid_shape = self.grid_shape();
         let extra_exclude = self.config.categories.excluded_lower();
+        let layout_name = self.layout_name();
+        let slot_names = self.config.slot_names(layout_name.as_deref(), Some(&preset));
         let result = arrange::arrange_masked(
-            &preset,
-            &filter,
-            &self.config.defaults.monitor,
-            self.config.defaults.gap,
-            &self.disabled_cells,
-            weights,
-            self.app_hwnd,
-            &extra_exclude,
-            self.config.defaults.smart_sort,
-            Some(&self.activity),
-            &self.config.pin,
+            self.backend.as_ref(),
+            &arrange::ArrangeRequest {
+                preset: &preset,
+                grid_shape: grid_shape.as_ref(),
+                filter: &filter,
+                monitor_spec: &self.config.defaults.monitor,
+                spacing: &self.config.defaults.spacing(),
+                disabled: &self.disabled_cells,
+                app_hwnd: self.app_hwnd,
+                extra_exclude: &extra_exclude,
+                smart_sort: self.config.defaults.smart_sort,
+                activity: Some(&self.activity),
+                pin_rules: &self.config.pin,
+                layout_name: layout_name.as_deref(),
+                slot_names: &slot_names,
+                placements: &[],
+                size_rules: &self.config.size_constraint,
+                persist_slots: self.config.defaults.persist_slot_memory,
+            },
         );
         log::info!(
             "Arranged {} windows ({} skipped, {} errors)",
@@ -211,15 +344,56 @@ impl PsmApp {
             result.skipped,
             result.errors.len()
         );
+        result.log_pins();
         for err in &result.errors {
             log::warn!("  {}", err);
         }
     }
 
+    /// Put windows back where they were before the last arrangement.
+    pub fn undo_arrangement(&mut self) {
+        if let Some(restored) = history::undo(self.backend.as_ref()) {
+            log::info!("Undo: restored {} windows", restored);
+            self.refresh_windows();
+        }
+    }
+
+    pub fn redo_arrangement(&mut self) {
+        if let Some(restored) = history::redo(self.backend.as_ref()) {
+            log::info!("Redo: moved {} windows", restored);
+            self.refresh_windows();
+        }
+    }
+
+    /// The layout watch mode keeps live, if it's on.
+    fn watch_target(&self) -> Option<WatchTarget> {
+        if !self.config.defaults.watch {
+            return None;
+        }
+        Some(WatchTarget {
+            preset: self.active_preset(),
+            grid_shape: self.grid_shape(),
+            filter: self.config.target_filter(),
+            monitor_spec: self.config.defaults.monitor.clone(),
+            spacing: self.config.defaults.spacing(),
+            disabled: self.disabled_cells.clone(),
+            app_hwnd: self.app_hwnd,
+            extra_exclude: self.config.categories.excluded_lower(),
+            pin_rules: self.config.pin.clone(),
+            slot_names: self.config.slot_names(self.layout_name().as_deref(), Some(&self.active_preset())),
+            layout_name: self.layout_name(),
+            size_rules: self.config.size_constraint.clone(),
+            persist_slots: self.config.defaults.persist_slot_memory,
+            close_policy: self.config.defaults.watch_close_policy,
+        })
+    }
+
     pub fn refresh_windows(&mut self) {
-        let filter = TargetFilter::from_str(&self.config.defaults.target);
+        let filter = self.config.target_filter();
         let extra_exclude = self.config.categories.excluded_lower();
-        self.managed_windows = find_windows(&filter, self.app_hwnd, &extra_exclude);
+        self.managed_windows =
+            self.backend
+                .enumerate_windows(&filter, self.app_hwnd, &extra_exclude);
         self.last_refresh = Instant::now();
     }
 
@@ -228,7 +402,7 @@ impl PsmApp {
             self.theme_index = index;
             self.theme_dirty = true;
             self.config.defaults.theme = index;
-            config::save(&self.config);
+            self.save_config();
         }
     }
 
@@ -244,7 +418,42 @@ impl PsmApp {
         }
     }
 
+    /// Pin a process to the preview cell at `index` of the selected layout,
+    /// by the cell's name if it has one. Pin slots count enabled cells only,
+    /// so a disabled cell can't be picked.
+    pub fn pin_to_cell(&mut self, process: String, index: usize) -> bool {
+        if self.disabled_cells.contains(&index) {
+            return false;
+        }
+        let layout = self.layout_name();
+        let preset = self.active_preset();
+        let name = self
+            .config
+            .slot_names(layout.as_deref(), Some(&preset))
+            .get(index)
+            .cloned()
+            .filter(|n| !n.is_empty());
+        let slot = (0..index).filter(|i| !self.disabled_cells.contains(i)).count();
+        self.config.pin.push(config::PinRule {
+            process: Some(process),
+            title_contains: None,
+            rule: None,
+            slot,
+            slots: name.map(|n| config::SlotList::List(vec![config::SlotRef::Name(n)])),
+            // A custom grid has no name, so it's scoped by its preset string
+            layout: layout.or_else(|| preset.spec()),
+            priority: 0,
+            max_windows: None,
+        });
+        self.save_config();
+        true
+    }
+
     pub fn load_saved_grid(&mut self, grid: &config::SavedGrid) {
+        if grid.preset.is_some() {
+            self.load_saved_preset(&grid.name);
+            return;
+        }
         self.use_custom = true;
         self.custom_cols = grid.cols;
         self.custom_rows = grid.rows;
@@ -259,65 +468,243 @@ impl PsmApp {
             vec![1.0 / grid.rows as f32; grid.rows as usize]
         };
         self.disabled_cells = grid.disabled_cells.iter().copied().collect();
+        self.merged_cells = sanitize_merges(grid.cols, grid.rows, &grid.merged);
 
         self.config.defaults.use_custom = true;
         self.config.defaults.custom_cols = grid.cols;
         self.config.defaults.custom_rows = grid.rows;
         self.config.defaults.col_weights = self.col_weights.clone();
         self.config.defaults.row_weights = self.row_weights.clone();
-        config::save(&self.config);
+        self.config.defaults.merged_cells = self.merged_cells.clone();
+        self.save_config();
+    }
+
+    /// Select a saved preset from the layout picker, dropping any unsaved
+    /// ratio or weight tweaks.
+    fn load_saved_preset(&mut self, name: &str) {
+        let Some(index) = self.presets.iter().position(|(n, _, _)| n == name) else {
+            return;
+        };
+        self.use_custom = false;
+        self.use_free = false;
+        self.selected_preset = index;
+        self.disabled_cells.clear();
+
+        self.config.defaults.use_custom = false;
+        self.config.defaults.use_free = false;
+        self.config.defaults.selected_preset = index;
+        self.config.defaults.main_ratio = None;
+        self.config.defaults.slot_weights.clear();
+        self.save_config();
     }
 
     pub fn save_current_as_grid(&mut self, name: String) {
-        let grid = config::SavedGrid {
-            name: name.clone(),
-            cols: self.custom_cols,
-            rows: self.custom_rows,
-            col_weights: self.col_weights.clone(),
-            row_weights: self.row_weights.clone(),
-            disabled_cells: self.disabled_cells.iter().copied().collect(),
+        let mut grid = if self.use_custom {
+            config::SavedGrid {
+                name: name.clone(),
+                cols: self.custom_cols,
+                rows: self.custom_rows,
+                col_weights: self.col_weights.clone(),
+                row_weights: self.row_weights.clone(),
+                disabled_cells: self.disabled_cells.iter().copied().collect(),
+                merged: self.merged_cells.clone(),
+                preset: None,
+                weights: Vec::new(),
+                slot_names: Vec::new(),
+            }
+        } else {
+            let preset = self.active_preset();
+            let Some(spec) = preset.spec() else {
+                log::warn!("Layout '{}' can't be saved as a preset", preset.display_name());
+                return;
+            };
+            config::SavedGrid {
+                name: name.clone(),
+                cols: 0,
+                rows: 0,
+                col_weights: Vec::new(),
+                row_weights: Vec::new(),
+                disabled_cells: self.disabled_cells.iter().copied().collect(),
+                merged: Vec::new(),
+                preset: Some(spec),
+                weights: self.preset_weights(),
+                slot_names: Vec::new(),
+            }
         };
+        let is_preset = grid.preset.is_some();
         // Upsert: replace existing with same name
         if let Some(existing) = self.config.saved_grid.iter_mut().find(|g| g.name == name) {
+            // Slot names are only set in the config file, so keep them
+            grid.slot_names = std::mem::take(&mut existing.slot_names);
             *existing = grid;
         } else {
             self.config.saved_grid.push(grid);
         }
-        config::save(&self.config);
+        self.save_config();
         self.rebuild_presets();
+        if is_preset {
+            self.load_saved_preset(&name);
+        }
     }
 
     pub fn delete_saved_grid(&mut self, name: &str) {
         self.config.saved_grid.retain(|g| g.name != name);
-        config::save(&self.config);
+        self.save_config();
         self.rebuild_presets();
     }
 
-    pub fn rebuild_presets(&mut self) {
-        let mut presets = builtin_presets();
-        for layout_def in &self.config.layout {
-            if let Some(preset) = layout_def.to_preset() {
-                presets.push((layout_def.name.clone(), preset));
-            }
+    /// Persist the freeform editor's rectangles after an edit.
+    pub fn save_free_rects(&mut self) {
+        self.config.defaults.free_rects = self.free_rects.clone();
+        self.save_config();
+    }
+
+    pub fn load_free_layout(&mut self, layout: &config::FreeLayout) {
+        self.use_custom = false;
+        self.use_free = true;
+        self.free_rects = layout.rects.iter().map(|r| r.clamped()).collect();
+        self.disabled_cells.clear();
+
+        self.config.defaults.use_custom = false;
+        self.config.defaults.use_free = true;
+        self.config.defaults.free_rects = self.free_rects.clone();
+        self.save_config();
+    }
+
+    pub fn save_current_as_free_layout(&mut self, name: String) {
+        let layout = config::FreeLayout {
+            name: name.clone(),
+            rects: self.free_rects.clone(),
+        };
+        // Upsert: replace existing with same name
+        if let Some(existing) = self.config.free_layout.iter_mut().find(|l| l.name == name) {
+            *existing = layout;
+        } else {
+            self.config.free_layout.push(layout);
         }
-        for sg in &self.config.saved_grid {
-            presets.push((
-                sg.name.clone(),
-                LayoutPreset::Grid { cols: sg.cols, rows: sg.rows },
-            ));
+        self.save_config();
+        self.rebuild_presets();
+    }
+
+    pub fn delete_free_layout(&mut self, name: &str) {
+        self.config.free_layout.retain(|l| l.name != name);
+        self.save_config();
+        self.rebuild_presets();
+    }
+
+    /// Save where every managed window is now as a named session.
+    pub fn save_session(&mut self, name: String) {
+        let filter = self.config.target_filter();
+        let extra_exclude = self.config.categories.excluded_lower();
+        let session = session::capture(
+            self.backend.as_ref(),
+            &name,
+            &filter,
+            self.app_hwnd,
+            &extra_exclude,
+        );
+        log::info!("Saved session '{}' with {} windows", name, session.window.len());
+        // Upsert: replace existing with same name
+        if let Some(existing) = self.config.session.iter_mut().find(|s| s.name == name) {
+            *existing = session;
+        } else {
+            self.config.session.push(session);
         }
-        self.presets = presets;
+        self.save_config();
+    }
+
+    pub fn restore_session(&mut self, session: &config::Session) {
+        let filter = self.config.target_filter();
+        let extra_exclude = self.config.categories.excluded_lower();
+        let result = session::restore(
+            self.backend.as_ref(),
+            session,
+            &filter,
+            self.app_hwnd,
+            &extra_exclude,
+        );
+        log::info!(
+            "Restored session '{}': {} windows ({} not open)",
+            session.name,
+            result.restored,
+            result.missing
+        );
+        self.refresh_windows();
+    }
+
+    /// Start a profile's missing apps and arrange them, in the background
+    /// since it waits for their windows.
+    pub fn launch_profile(&self, profile: &config::LaunchProfile) {
+        let backend = Arc::clone(&self.backend);
+        let profile = profile.clone();
+        let config = self.config.clone();
+        let hwnd = self.app_hwnd;
+        std::thread::spawn(move || {
+            let result = launcher::run_profile(backend.as_ref(), &profile, &config, hwnd);
+            launcher::report(&profile.name, &result);
+        });
+    }
+
+    pub fn delete_session(&mut self, name: &str) {
+        self.config.session.retain(|s| s.name != name);
+        self.save_config();
+    }
+
+    pub fn rebuild_presets(&mut self) {
+        self.presets = collect_presets(&self.config);
         if self.selected_preset >= self.presets.len() {
             self.selected_preset = 0;
         }
     }
 
+    #[cfg(windows)]
     fn hide_window(&mut self) {
-        crate::windows::hide_app_window(self.app_hwnd);
+        win32::hide_app_window(self.app_hwnd);
         self.gui_visible = false;
     }
 }
 
+/// Built-in presets followed by config layouts, saved grids and free layouts,
+/// in the order shown in the layout picker, each with its saved shape.
+fn collect_presets(config: &Config) -> Vec<(String, LayoutPreset, Option<GridShape>)> {
+    let mut presets: Vec<_> = builtin_presets()
+        .into_iter()
+        .map(|(name, preset)| (name, preset, None))
+        .collect();
+    for layout_def in &config.layout {
+        if let Some(preset) = layout_def.to_preset() {
+            let shape = layout_def.to_shape(&preset, &config.defaults);
+            presets.push((layout_def.name.clone(), preset, shape));
+        }
+    }
+    for sg in &config.saved_grid {
+        if let Some(preset) = sg.to_preset() {
+            let shape = sg.to_shape(&preset);
+            presets.push((sg.name.clone(), preset, Some(shape)));
+        }
+    }
+    for fl in &config.free_layout {
+        presets.push((fl.name.clone(), LayoutPreset::Free(fl.rects.clone()), None));
+    }
+    presets
+}
+
+/// Shape for a picker entry: its saved shape, with the slot weights replaced
+/// by `slot_weights` when those fit the preset.
+fn selected_shape(
+    entry: Option<GridShape>,
+    preset: &LayoutPreset,
+    slot_weights: &[f32],
+) -> Option<GridShape> {
+    let weights = normalize_weights(slot_weights, preset.weight_count());
+    if weights.is_empty() {
+        return entry;
+    }
+    let mut shape = entry.unwrap_or_default();
+    shape.weights = weights;
+    Some(shape)
+}
+
 impl eframe::App for PsmApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if self.theme_dirty {
@@ -326,19 +713,16 @@ impl eframe::App for PsmApp {
         }
 
         // Intercept close button → hide to tray instead of closing
-        let close_requested = ctx.input(|i| i.viewport().close_requested());
-        if close_requested {
-            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
-            self.hide_window();
-        }
+        #[cfg(windows)]
+        {
+            let close_requested = ctx.input(|i| i.viewport().close_requested());
+            if close_requested {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                self.hide_window();
+            }
 
-        // Check if the tray thread restored us
-        if !self.gui_visible {
-            let visible = unsafe {
-                use windows::Win32::UI::WindowsAndMessaging::IsWindowVisible;
-                IsWindowVisible(HWND(self.app_hwnd as *mut _)).as_bool()
-            };
-            if visible {
+            // Check if the tray thread restored us
+            if !self.gui_visible && win32::is_app_window_visible(self.app_hwnd) {
                 self.gui_visible = true;
                 self.refresh_windows();
             }
@@ -347,6 +731,9 @@ impl eframe::App for PsmApp {
         // Update activity tracker (drains focus events)
         self.activity.update();
 
+        // Hand the current layout to watch mode
+        self.watcher.set_target(self.watch_target());
+
         if self.gui_visible && self.last_refresh.elapsed().as_secs() >= 3 {
             self.refresh_windows();
         }
@@ -359,8 +746,52 @@ impl eframe::App for PsmApp {
     }
 }
 
+/// The layout the global hotkey applies: the one selected in the GUI, as
+/// saved in `config`.
+#[cfg(windows)]
+fn hotkey_layout(config: &Config) -> (Option<String>, LayoutPreset, Option<GridShape>) {
+    if config.defaults.use_custom {
+        let preset = LayoutPreset::Grid {
+            cols: config.defaults.custom_cols,
+            rows: config.defaults.custom_rows,
+        };
+        let shape = GridShape {
+            col_weights: config.defaults.col_weights.clone(),
+            row_weights: config.defaults.row_weights.clone(),
+            merges: config.defaults.merged_cells.clone(),
+            weights: Vec::new(),
+            spacing: None,
+        };
+        (None, preset, Some(shape))
+    } else if config.defaults.use_free {
+        (None, LayoutPreset::Free(config.defaults.free_rects.clone()), None)
+    } else {
+        let mut presets = collect_presets(config);
+        let index = config.defaults.selected_preset;
+        let (name, preset, entry) = if index < presets.len() {
+            let (name, preset, shape) = presets.swap_remove(index);
+            (Some(name), preset, shape)
+        } else {
+            (None, LayoutPreset::Grid { cols: 2, rows: 2 }, None)
+        };
+        let preset = match config.defaults.main_ratio {
+            Some(ratio) => preset.with_main_ratio(ratio),
+            None => preset,
+        };
+        let shape = selected_shape(entry, &preset, &config.defaults.slot_weights);
+        (name, preset, shape)
+    }
+}
+
 /// Background thread that polls tray events independently of eframe's render loop.
-fn tray_event_loop(menu_ids: tray::TrayMenuIds, ctx: egui::Context, hwnd: isize, config: &Config) {
+#[cfg(windows)]
+fn tray_event_loop(
+    menu_ids: tray::TrayMenuIds,
+    ctx: egui::Context,
+    hwnd: isize,
+    live_config: Arc<Mutex<Config>>,
+    backend_arc: Arc<dyn WindowBackend>,
+) {
     use crate::tray::TrayAction;
     use windows::Win32::UI::Input::KeyboardAndMouse::{
         RegisterHotKey, UnregisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT,
@@ -368,41 +799,29 @@ fn tray_event_loop(menu_ids: tray::TrayMenuIds, ctx: egui::Context, hwnd: isize,
     use windows::Win32::UI::WindowsAndMessaging::{MSG, PeekMessageW, PM_REMOVE, WM_HOTKEY};
 
     const HOTKEY_ID: i32 = 1;
-    // 0x47 = 'G'
-    let hotkey_ok = unsafe {
-        RegisterHotKey(None, HOTKEY_ID, MOD_CONTROL | MOD_ALT | MOD_NOREPEAT, 0x47)
-    };
-    if hotkey_ok.is_ok() {
-        log::info!("Registered global hotkey Ctrl+Alt+G");
-    } else {
-        log::warn!("Failed to register global hotkey Ctrl+Alt+G (already in use?)");
+    const UNDO_HOTKEY_ID: i32 = 2;
+    const REDO_HOTKEY_ID: i32 = 3;
+    // 0x47 = 'G', 0x5A = 'Z', 0x59 = 'Y'
+    for (id, key, name) in [
+        (HOTKEY_ID, 0x47, "Ctrl+Alt+G"),
+        (UNDO_HOTKEY_ID, 0x5A, "Ctrl+Alt+Z"),
+        (REDO_HOTKEY_ID, 0x59, "Ctrl+Alt+Y"),
+    ] {
+        let hotkey_ok = unsafe {
+            RegisterHotKey(None, id, MOD_CONTROL | MOD_ALT | MOD_NOREPEAT, key)
+        };
+        if hotkey_ok.is_ok() {
+            log::info!("Registered global hotkey {}", name);
+        } else {
+            log::warn!("Failed to register global hotkey {} (already in use?)", name);
+        }
     }
 
-    // Resolve the active preset from config (snapshot at startup)
-    let active_preset = if config.defaults.use_custom {
-        LayoutPreset::Grid {
-            cols: config.defaults.custom_cols,
-            rows: config.defaults.custom_rows,
-        }
-    } else {
-        let mut presets = builtin_presets();
-        for ld in &config.layout {
-            if let Some(p) = ld.to_preset() {
-                presets.push((ld.name.clone(), p));
-            }
-        }
-        presets
-            .get(config.defaults.selected_preset)
-            .map(|(_, p)| p.clone())
-            .unwrap_or(LayoutPreset::Grid { cols: 2, rows: 2 })
-    };
-    let hotkey_weights = if config.defaults.use_custom
-        && config.defaults.col_weights.len() == config.defaults.custom_cols as usize
-        && config.defaults.row_weights.len() == config.defaults.custom_rows as usize
-    {
-        Some((config.defaults.col_weights.clone(), config.defaults.row_weights.clone()))
-    } else {
-        None
+    let backend = backend_arc.as_ref();
+    // The config as the GUI last saved it
+    let current_config = || match live_config.lock() {
+        Ok(config) => config.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
     };
 
     loop {
@@ -412,57 +831,84 @@ fn tray_event_loop(menu_ids: tray::TrayMenuIds, ctx: egui::Context, hwnd: isize,
         unsafe {
             let mut msg = MSG::default();
             while PeekMessageW(&mut msg, None, WM_HOTKEY, WM_HOTKEY, PM_REMOVE).as_bool() {
-                if msg.wParam.0 as i32 == HOTKEY_ID {
-                    log::info!("Hotkey Ctrl+Alt+G pressed — applying layout");
-                    let filter = TargetFilter::from_str(&config.defaults.target);
-                    let disabled = HashSet::new();
-                    let extra_exclude = config.categories.excluded_lower();
-                    let w = hotkey_weights.as_ref().map(|(c, r)| (c.as_slice(), r.as_slice()));
-                    let result = arrange::arrange_masked(
-                        &active_preset,
-                        &filter,
-                        &config.defaults.monitor,
-                        config.defaults.gap,
-                        &disabled,
-                        w,
-                        hwnd,
-                        &extra_exclude,
-                        false,
-                        None,
-                        &config.pin,
-                    );
-                    log::info!("Hotkey: arranged {} windows", result.arranged);
+                match msg.wParam.0 as i32 {
+                    HOTKEY_ID => {
+                        log::info!("Hotkey Ctrl+Alt+G pressed — applying layout");
+                        let config = current_config();
+                        let (name, preset, shape) = hotkey_layout(&config);
+                        let result = arrange::arrange_with_config(
+                            backend,
+                            name.as_deref(),
+                            &preset,
+                            shape.as_ref(),
+                            &config,
+                            hwnd,
+                        );
+                        log::info!("Hotkey: arranged {} windows", result.arranged);
+                        result.log_pins();
+                    }
+                    UNDO_HOTKEY_ID => {
+                        if let Some(restored) = history::undo(backend) {
+                            log::info!("Hotkey: undo restored {} windows", restored);
+                        }
+                    }
+                    REDO_HOTKEY_ID => {
+                        if let Some(restored) = history::redo(backend) {
+                            log::info!("Hotkey: redo moved {} windows", restored);
+                        }
+                    }
+                    _ => {}
                 }
             }
         }
 
         match menu_ids.poll() {
             TrayAction::ShowGui => {
-                crate::windows::show_app_window(hwnd);
+                win32::show_app_window(hwnd);
                 ctx.request_repaint();
             }
-            TrayAction::ApplyLayout(_name, preset, weights) => {
-                let filter = TargetFilter::from_str(&config.defaults.target);
-                let disabled = HashSet::new();
-                let extra_exclude = config.categories.excluded_lower();
-                let w = weights.as_ref().map(|(c, r)| (c.as_slice(), r.as_slice()));
-                let result = arrange::arrange_masked(
+            TrayAction::ApplyLayout(name, preset, grid_shape) => {
+                let result = arrange::arrange_with_config(
+                    backend,
+                    Some(&name),
                     &preset,
-                    &filter,
-                    &config.defaults.monitor,
-                    config.defaults.gap,
-                    &disabled,
-                    w,
+                    grid_shape.as_ref(),
+                    &current_config(),
                     hwnd,
-                    &extra_exclude,
-                    false,
-                    None,
-                    &config.pin,
                 );
                 log::info!("Tray: arranged {} windows", result.arranged);
+                result.log_pins();
+            }
+            TrayAction::RestoreSession(session) => {
+                let config = current_config();
+                let filter = config.target_filter();
+                let extra_exclude = config.categories.excluded_lower();
+                let result = session::restore(backend, &session, &filter, hwnd, &extra_exclude);
+                log::info!("Tray: restored {} windows of '{}'", result.restored, session.name);
+            }
+            TrayAction::LaunchProfile(profile) => {
+                // Waiting for windows would stall the tray
+                let backend = Arc::clone(&backend_arc);
+                let config = current_config();
+                std::thread::spawn(move || {
+                    let result = launcher::run_profile(backend.as_ref(), &profile, &config, hwnd);
+                    launcher::report(&profile.name, &result);
+                });
+            }
+            TrayAction::Undo => {
+                if let Some(restored) = history::undo(backend) {
+                    log::info!("Tray: undo restored {} windows", restored);
+                }
+            }
+            TrayAction::Redo => {
+                if let Some(restored) = history::redo(backend) {
+                    log::info!("Tray: redo moved {} windows", restored);
+                }
             }
             TrayAction::Quit => {
-                unsafe { let _ = UnregisterHotKey(None, HOTKEY_ID); }
+                for id in [HOTKEY_ID, UNDO_HOTKEY_ID, REDO_HOTKEY_ID] {
+                    unsafe { let _ = UnregisterHotKey(None, id); }
+                }
                 std::process::exit(0);
             }
             TrayAction::None => {}
@@ -1,185 +1,761 @@
 use crate::activity::ActivityTracker;
-use crate::config::PinRule;
-use crate::layout::{LayoutPreset, compute_weighted_grid};
-use crate::monitor::{enumerate_monitors, resolve_monitor};
-use crate::windows::{ManagedWindow, TargetFilter, find_windows};
+use crate::backend::WindowBackend;
+#[cfg(windows)]
+use crate::config::Config;
+use crate::config::{PinRule, SizeConstraint};
+use crate::assign::{Candidate, Reason, assign};
+use crate::constraints::{self, limits_for, rebalance_shape};
+use crate::history;
+use crate::layout::{GridShape, LayoutPreset, Slot, Spacing, compute_shaped_slots};
+use crate::monitor::{Rect, resolve_monitor};
+use crate::pinning::{self, Pin};
+use crate::slot_memory::{self, SlotEntry};
+use crate::windows::{ManagedWindow, TargetFilter};
+use serde::Serialize;
 use std::collections::HashSet;
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::{SWP_NOACTIVATE, SWP_NOZORDER, SetWindowPos};
+use std::fmt;
 
 #[derive(Debug)]
 pub struct ArrangeResult {
     pub arranged: usize,
     pub skipped: usize,
+    /// Windows moved into their slot by a pin rule.
+    pub pinned: Vec<FiredPin>,
     pub errors: Vec<String>,
 }
 
-pub fn arrange_masked(
-    preset: &LayoutPreset,
-    filter: &TargetFilter,
-    monitor_spec: &str,
-    gap: i32,
-    disabled: &HashSet<usize>,
-    weights: Option<(&[f32], &[f32])>,
-    app_hwnd: isize,
-    extra_exclude: &[String],
-    smart_sort: bool,
-    activity: Option<&ActivityTracker>,
-    pin_rules: &[PinRule],
-) -> ArrangeResult {
-    let monitors = enumerate_monitors();
+impl ArrangeResult {
+    /// Log which pin rules fired.
+    pub fn log_pins(&self) {
+        for pin in &self.pinned {
+            log::info!("  {}", pin);
+        }
+    }
+}
+
+/// A pin rule that put a window in its slot.
+#[derive(Debug, Clone)]
+pub struct FiredPin {
+    /// Index into the config's pin list.
+    pub rule: usize,
+    pub title: String,
+    pub process_name: String,
+    /// Slot index in the layout (disabled cells included).
+    pub slot: usize,
+}
+
+impl fmt::Display for FiredPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pin rule #{} put '{}' ({}) in slot {}",
+            self.rule + 1,
+            self.title,
+            self.process_name,
+            self.slot + 1
+        )
+    }
+}
+
+/// Why a window was given its slot.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MoveReason {
+    /// Given a fixed slot by the caller, e.g. an app of a launch profile.
+    Placed,
+    /// Matched a pin rule (index into the config's pin list).
+    Pinned { rule: usize },
+    /// Kept the slot it had the last time this layout was applied.
+    Remembered,
+    /// Placed by the assignment engine. `rank` is the window's position in
+    /// priority order among the windows it placed, `score` its activity
+    /// score with smart sort.
+    Assigned { rank: usize, score: Option<f64> },
+}
+
+impl fmt::Display for MoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Placed => write!(f, "placed"),
+            Self::Pinned { rule } => write!(f, "pin rule #{}", rule + 1),
+            Self::Remembered => write!(f, "kept from last arrange"),
+            Self::Assigned { rank, score: Some(score) } => {
+                write!(f, "rank {} (score {:.2})", rank + 1, score)
+            }
+            Self::Assigned { rank, score: None } => write!(f, "rank {}", rank + 1),
+        }
+    }
+}
+
+/// A window and where it will be moved.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedMove {
+    pub hwnd: isize,
+    pub title: String,
+    pub process_name: String,
+    /// Slot index in the layout (disabled cells included).
+    pub slot: usize,
+    /// Final window rect: the slot, shrunk to the window's maximum size.
+    pub target: Slot,
+    pub reason: MoveReason,
+}
+
+/// A window left out because there were not enough slots.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedWindow {
+    pub hwnd: isize,
+    pub title: String,
+    pub process_name: String,
+}
+
+/// Everything `arrange_masked` would do, without doing it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArrangePlan {
+    /// Slot memory key of the layout (preset, slot count, monitor).
+    pub layout: String,
+    pub moves: Vec<PlannedMove>,
+    pub skipped: Vec<SkippedWindow>,
+    pub errors: Vec<String>,
+}
+
+impl ArrangePlan {
+    fn failed(error: &str) -> Self {
+        Self {
+            layout: String::new(),
+            moves: Vec::new(),
+            skipped: Vec::new(),
+            errors: vec![error.into()],
+        }
+    }
+}
+
+/// Everything an arrangement depends on besides the desktop itself.
+#[derive(Clone, Copy)]
+pub struct ArrangeRequest<'a> {
+    pub preset: &'a LayoutPreset,
+    pub grid_shape: Option<&'a GridShape>,
+    pub filter: &'a TargetFilter,
+    pub monitor_spec: &'a str,
+    pub spacing: &'a Spacing,
+    pub disabled: &'a HashSet<usize>,
+    pub app_hwnd: isize,
+    pub extra_exclude: &'a [String],
+    pub smart_sort: bool,
+    pub activity: Option<&'a ActivityTracker>,
+    pub pin_rules: &'a [PinRule],
+    /// Name of the layout being applied, for pin rules scoped to a layout.
+    pub layout_name: Option<&'a str>,
+    /// Names of the layout's slots (disabled cells included).
+    pub slot_names: &'a [String],
+    /// Windows that must go to a slot, as (hwnd, slot index with disabled
+    /// cells included). These win over pin rules.
+    pub placements: &'a [(isize, usize)],
+    pub size_rules: &'a [SizeConstraint],
+    pub persist_slots: bool,
+}
+
+/// Plan and carry out an arrangement, saving where the windows were so it
+/// can be undone.
+pub fn arrange_masked(backend: &dyn WindowBackend, request: &ArrangeRequest) -> ArrangeResult {
+    let plan = plan_arrangement(backend, request);
+    let hwnds: Vec<isize> = plan.moves.iter().map(|mv| mv.hwnd).collect();
+    history::record(backend, &hwnds);
+    execute_plan(backend, &plan, request.persist_slots)
+}
+
+/// The request's slot memory key and its enabled slots, each with its index
+/// in the full slot list. BSP layouts are sized for the windows there are
+/// now, so their key changes with the window count.
+pub fn enabled_slots(
+    backend: &dyn WindowBackend,
+    request: &ArrangeRequest,
+) -> Option<(String, Vec<(usize, Slot)>)> {
+    let monitors = backend.monitors();
     if monitors.is_empty() {
-        return ArrangeResult {
-            arranged: 0,
-            skipped: 0,
-            errors: vec!["No monitors detected".into()],
-        };
+        return None;
     }
+    let preset = match request.preset {
+        LayoutPreset::Bsp(_) => {
+            let windows = backend.enumerate_windows(
+                request.filter,
+                request.app_hwnd,
+                request.extra_exclude,
+            );
+            request.preset.sized_for(slots_for(windows.len(), request.disabled))
+        }
+        preset => preset.clone(),
+    };
+    let monitor = resolve_monitor(&monitors, request.monitor_spec);
+    let all_slots =
+        compute_shaped_slots(&preset, &monitor.work_area, request.spacing, request.grid_shape);
+    let key = slot_memory::layout_key(&preset, all_slots.len(), request.monitor_spec);
+    let enabled = all_slots
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !request.disabled.contains(i))
+        .collect();
+    Some((key, enabled))
+}
+
+/// Slots a layout needs so `windows` windows fit around the disabled ones.
+fn slots_for(windows: usize, disabled: &HashSet<usize>) -> usize {
+    let mut n = windows;
+    while (0..n).filter(|i| !disabled.contains(i)).count() < windows {
+        n += 1;
+    }
+    n
+}
+
+/// Decide where every window goes without moving anything.
+pub fn plan_arrangement(backend: &dyn WindowBackend, request: &ArrangeRequest) -> ArrangePlan {
+    let ArrangeRequest {
+        preset,
+        grid_shape,
+        filter,
+        monitor_spec,
+        spacing,
+        disabled,
+        app_hwnd,
+        extra_exclude,
+        smart_sort,
+        activity,
+        pin_rules,
+        layout_name,
+        slot_names,
+        placements,
+        size_rules,
+        persist_slots,
+    } = *request;
+    let monitors = backend.monitors();
+    if monitors.is_empty() {
+        return ArrangePlan::failed("No monitors detected");
+    }
+
+    let found = backend.enumerate_windows(filter, app_hwnd, extra_exclude);
+    let sized = preset.sized_for(slots_for(found.len(), disabled));
+    let preset = &sized;
 
     let monitor = resolve_monitor(&monitors, monitor_spec);
-    let all_slots = if let (Some((col_w, row_w)), LayoutPreset::Grid { cols, rows }) = (weights, preset) {
-        compute_weighted_grid(*cols, *rows, &monitor.work_area, gap, col_w, row_w)
-    } else {
-        preset.compute_slots(&monitor.work_area, gap)
-    };
+    let mut all_slots = compute_shaped_slots(preset, &monitor.work_area, spacing, grid_shape);
 
     // Only use enabled slots
-    let slots: Vec<_> = all_slots
-        .into_iter()
+    let enabled: Vec<usize> = (0..all_slots.len()).filter(|i| !disabled.contains(i)).collect();
+    let mut slots: Vec<Slot> = enabled.iter().map(|&i| all_slots[i].clone()).collect();
+
+    // Sort by activity score descending with smart sort
+    let scores: Vec<Option<f64>> = match activity.filter(|_| smart_sort) {
+        Some(tracker) => tracker.score_windows(&found).into_iter().map(Some).collect(),
+        None => vec![None; found.len()],
+    };
+    let mut windows: Vec<(ManagedWindow, Option<f64>)> = found.into_iter().zip(scores).collect();
+    windows.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let layout_key = slot_memory::layout_key(preset, all_slots.len(), monitor_spec);
+    let previous = slot_memory::recall(&layout_key, persist_slots);
+    let placed: Vec<Option<usize>> = windows
+        .iter()
+        .map(|(win, _)| {
+            placements
+                .iter()
+                .find(|(hwnd, _)| *hwnd == win.hwnd)
+                .and_then(|&(_, slot)| enabled.iter().position(|&i| i == slot))
+        })
+        .collect();
+
+    // Pins come after the caller's placements and before everything else
+    let mut taken = vec![false; slots.len()];
+    for &slot in placed.iter().flatten() {
+        taken[slot] = true;
+    }
+    let unplaced: Vec<&ManagedWindow> = windows
+        .iter()
+        .zip(&placed)
+        .filter(|(_, p)| p.is_none())
+        .map(|((win, _), _)| win)
+        .collect();
+    let enabled_names: Vec<String> = enabled
+        .iter()
+        .map(|&i| slot_names.get(i).cloned().unwrap_or_default())
+        .collect();
+    let mut resolved =
+        pinning::resolve(pin_rules, layout_name, preset, &enabled_names, &unplaced, &mut taken)
+            .into_iter();
+    let pins: Vec<Option<Pin>> = placed
+        .iter()
+        .map(|p| if p.is_some() { None } else { resolved.next().flatten() })
+        .collect();
+    let candidates: Vec<Candidate> = windows
+        .iter()
         .enumerate()
-        .filter(|(i, _)| !disabled.contains(i))
-        .map(|(_, slot)| slot)
+        .map(|(i, (win, _))| Candidate {
+            rect: (!win.is_minimized).then_some(win.rect),
+            limits: limits_for(win, size_rules),
+            pin: placed[i].or(pins[i].map(|pin| pin.slot)),
+            previous: previous
+                .iter()
+                .find(|e| e.hwnd == win.hwnd && e.process.eq_ignore_ascii_case(&win.process_name))
+                .and_then(|e| enabled.iter().position(|&i| i == e.slot)),
+        })
+        .collect();
+    let placement = assign(&candidates, &slots);
+
+    // Grow slots for windows with a minimum size if the layout's weights
+    // allow it
+    let mut slot_mins = vec![(0, 0); all_slots.len()];
+    for (slot_idx, placed) in placement.iter().enumerate() {
+        if let Some(p) = placed {
+            let lim = &candidates[p.window].limits;
+            slot_mins[enabled[slot_idx]] = (lim.min_w, lim.min_h);
+        }
+    }
+    if let Some((preset, shape)) = rebalance_shape(
+        preset,
+        grid_shape,
+        &monitor.work_area,
+        spacing,
+        &all_slots,
+        &slot_mins,
+    ) {
+        all_slots = compute_shaped_slots(&preset, &monitor.work_area, spacing, Some(&shape));
+        slots = enabled.iter().map(|&i| all_slots[i].clone()).collect();
+    }
+
+    // Priority order of the windows the assignment engine placed, for ranks
+    let mut optimized: Vec<usize> = placement
+        .iter()
+        .flatten()
+        .filter(|p| p.reason == Reason::Optimized)
+        .map(|p| p.window)
+        .collect();
+    optimized.sort_unstable();
+
+    let mut moves = Vec::new();
+    let mut arranged = vec![false; windows.len()];
+    for (slot_idx, (slot, p)) in slots.iter().zip(&placement).enumerate() {
+        let Some(p) = p else { continue };
+        let (win, score) = &windows[p.window];
+        arranged[p.window] = true;
+        let reason = match p.reason {
+            Reason::Pinned if placed[p.window].is_some() => MoveReason::Placed,
+            Reason::Pinned => MoveReason::Pinned {
+                rule: pins[p.window].map_or(0, |pin| pin.rule),
+            },
+            Reason::Previous => MoveReason::Remembered,
+            Reason::Optimized => MoveReason::Assigned {
+                rank: optimized.binary_search(&p.window).unwrap_or(0),
+                score: *score,
+            },
+        };
+        moves.push(PlannedMove {
+            hwnd: win.hwnd,
+            title: win.title.clone(),
+            process_name: win.process_name.clone(),
+            slot: enabled[slot_idx],
+            target: candidates[p.window].limits.fit_into(slot),
+            reason,
+        });
+    }
+
+    let skipped = windows
+        .iter()
+        .zip(&arranged)
+        .filter(|(_, arranged)| !**arranged)
+        .map(|((win, _), _)| SkippedWindow {
+            hwnd: win.hwnd,
+            title: win.title.clone(),
+            process_name: win.process_name.clone(),
+        })
         .collect();
 
-    let mut windows = find_windows(filter, app_hwnd, extra_exclude);
+    ArrangePlan {
+        layout: layout_key,
+        moves,
+        skipped,
+        errors: Vec::new(),
+    }
+}
 
-    if smart_sort && !pin_rules.is_empty() {
-        // Pin resolution: assign pinned windows to their slots first
-        let mut pinned: Vec<(usize, ManagedWindow)> = Vec::new(); // (slot_index, window)
-        let mut unpinned: Vec<ManagedWindow> = Vec::new();
+/// Move the windows as planned and remember where they went.
+pub fn execute_plan(
+    backend: &dyn WindowBackend,
+    plan: &ArrangePlan,
+    persist_slots: bool,
+) -> ArrangeResult {
+    let mut arranged = 0;
+    let mut pinned = Vec::new();
+    let mut errors = plan.errors.clone();
+    let mut remembered = Vec::new();
+
+    for mv in &plan.moves {
+        let target = &mv.target;
+        let result = backend.move_window(
+            mv.hwnd,
+            &Rect {
+                x: target.x,
+                y: target.y,
+                w: target.w,
+                h: target.h,
+            },
+        );
 
-        for win in windows.drain(..) {
-            let mut matched_slot = None;
-            for rule in pin_rules {
-                if rule.matches(&win.process_name, &win.title) && rule.slot < slots.len() {
-                    matched_slot = Some(rule.slot);
-                    break;
+        match result {
+            Ok(()) => {
+                arranged += 1;
+                if let MoveReason::Pinned { rule } = mv.reason {
+                    pinned.push(FiredPin {
+                        rule,
+                        title: mv.title.clone(),
+                        process_name: mv.process_name.clone(),
+                        slot: mv.slot,
+                    });
+                }
+                remembered.push(SlotEntry {
+                    hwnd: mv.hwnd,
+                    process: mv.process_name.clone(),
+                    slot: mv.slot,
+                });
+                if backend.moves_are_synchronous() {
+                    if let Some(actual) = backend.window_rect(mv.hwnd) {
+                        constraints::learn(&mv.process_name, target, &actual);
+                    }
                 }
             }
-            if let Some(slot_idx) = matched_slot {
-                pinned.push((slot_idx, win));
-            } else {
-                unpinned.push(win);
-            }
+            Err(e) => errors.push(format!("Failed to position '{}': {}", mv.title, e)),
         }
+    }
 
-        // Score and sort unpinned windows
-        if smart_sort {
-            if let Some(tracker) = activity {
-                let scores = tracker.score_windows(&unpinned);
-                // Sort by score descending
-                let mut scored: Vec<_> = unpinned.into_iter().zip(scores).collect();
-                scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-                unpinned = scored.into_iter().map(|(w, _)| w).collect();
-            }
-        }
+    if !plan.layout.is_empty() {
+        slot_memory::remember(&plan.layout, remembered, persist_slots);
+    }
 
-        // Build final ordered list: pinned get their exact slots, unpinned fill remaining
-        let used_slots: HashSet<usize> = pinned.iter().map(|(s, _)| *s).collect();
-        let mut final_windows: Vec<Option<ManagedWindow>> = vec![None; slots.len()];
+    ArrangeResult {
+        arranged,
+        skipped: plan.skipped.len(),
+        pinned,
+        errors,
+    }
+}
 
-        // Place pinned windows
-        for (slot_idx, win) in pinned {
-            if slot_idx < final_windows.len() {
-                final_windows[slot_idx] = Some(win);
-            }
-        }
+/// Arrange with the settings from `config` alone: no disabled cells, smart
+/// sort or activity scores. Used by the global hotkey and the tray menu.
+/// `layout_name` is the layout's name in the picker, if it has one.
+#[cfg(windows)]
+pub fn arrange_with_config(
+    backend: &dyn WindowBackend,
+    layout_name: Option<&str>,
+    preset: &LayoutPreset,
+    grid_shape: Option<&GridShape>,
+    config: &Config,
+    app_hwnd: isize,
+) -> ArrangeResult {
+    let filter = config.target_filter();
+    let extra_exclude = config.categories.excluded_lower();
+    arrange_masked(
+        backend,
+        &ArrangeRequest {
+            preset,
+            grid_shape,
+            filter: &filter,
+            monitor_spec: &config.defaults.monitor,
+            spacing: &config.defaults.spacing(),
+            disabled: &HashSet::new(),
+            app_hwnd,
+            extra_exclude: &extra_exclude,
+            smart_sort: false,
+            activity: None,
+            pin_rules: &config.pin,
+            layout_name,
+            slot_names: &config.slot_names(layout_name, Some(preset)),
+            placements: &[],
+            size_rules: &config.size_constraint,
+            persist_slots: config.defaults.persist_slot_memory,
+        },
+    )
+}
 
-        // Fill remaining slots with unpinned windows in score order
-        let mut unpinned_iter = unpinned.into_iter();
-        for i in 0..slots.len() {
-            if final_windows[i].is_none() && !used_slots.contains(&i) {
-                if let Some(win) = unpinned_iter.next() {
-                    final_windows[i] = Some(win);
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::{ActivityDb, AppRecord};
+    use crate::backend::fake::{FakeBackend, Scenario};
+    use crate::config::Config;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    // Slot memory and learned sizes are shared by the whole process, so
+    // tests that execute a plan each use a preset of their own.
+
+    const DESKTOP: &str = r#"
+        [[monitor]]
+        x = 0
+        y = 0
+        w = 1200
+        h = 800
+        primary = true
+
+        [[window]]
+        hwnd = 1
+        process = "pwsh.exe"
+        title = "build"
+        x = 0
+        y = 0
+        w = 600
+        h = 400
+
+        [[window]]
+        hwnd = 2
+        process = "code.exe"
+        title = "main.rs"
+        x = 600
+        y = 0
+        w = 600
+        h = 800
+
+        [[window]]
+        hwnd = 3
+        process = "slack.exe"
+        title = "general"
+        x = 0
+        y = 400
+        w = 600
+        h = 400
+    "#;
+
+    fn desktop() -> FakeBackend {
+        FakeBackend::new(toml::from_str::<Scenario>(DESKTOP).unwrap())
+    }
+
+    fn config(extra: &str) -> Config {
+        toml::from_str(&format!("[defaults]\ntarget = \"all\"\n{}", extra)).unwrap()
+    }
+
+    fn plan_on(
+        backend: &FakeBackend,
+        layout: &str,
+        config: &Config,
+        activity: Option<&ActivityTracker>,
+    ) -> ArrangePlan {
+        let preset = LayoutPreset::parse(layout).unwrap();
+        let filter = config.target_filter();
+        plan_arrangement(
+            backend,
+            &ArrangeRequest {
+                preset: &preset,
+                grid_shape: None,
+                filter: &filter,
+                monitor_spec: &config.defaults.monitor,
+                spacing: &Spacing::default(),
+                disabled: &HashSet::new(),
+                app_hwnd: 0,
+                extra_exclude: &[],
+                smart_sort: activity.is_some(),
+                activity,
+                pin_rules: &config.pin,
+                layout_name: Some(layout),
+                slot_names: &config.slot_names(Some(layout), Some(&preset)),
+                placements: &[],
+                size_rules: &config.size_constraint,
+                persist_slots: false,
+            },
+        )
+    }
+
+    fn slot_of(plan: &ArrangePlan, hwnd: isize) -> Option<usize> {
+        plan.moves.iter().find(|mv| mv.hwnd == hwnd).map(|mv| mv.slot)
+    }
+
+    #[test]
+    fn plan_fills_slots_and_execute_moves_windows() {
+        let fake = desktop();
+        let plan = plan_on(&fake, "columns:3", &config(""), None);
+        assert!(plan.errors.is_empty());
+        assert!(plan.skipped.is_empty());
+        let mut slots: Vec<usize> = plan.moves.iter().map(|mv| mv.slot).collect();
+        slots.sort();
+        assert_eq!(slots, vec![0, 1, 2]);
+        // The editor is already on the right, so it stays there
+        assert_eq!(slot_of(&plan, 2), Some(2));
+
+        let result = execute_plan(&fake, &plan, false);
+        assert_eq!(result.arranged, 3);
+        assert!(result.errors.is_empty());
+        for mv in &plan.moves {
+            let win = fake.windows().into_iter().find(|w| w.hwnd == mv.hwnd).unwrap();
+            let t = &mv.target;
+            assert_eq!((win.x, win.y, win.w, win.h), (t.x, t.y, t.w, t.h));
+            assert_eq!(t.w, 400);
         }
+    }
 
-        // Position all assigned windows
-        let mut arranged = 0;
-        let mut errors = Vec::new();
-
-        for (i, maybe_win) in final_windows.iter().enumerate() {
-            if let Some(win) = maybe_win {
-                let slot = &slots[i];
-                let result = unsafe {
-                    SetWindowPos(
-                        HWND(win.hwnd as *mut _),
-                        None,
-                        slot.x, slot.y, slot.w, slot.h,
-                        SWP_NOZORDER | SWP_NOACTIVATE,
-                    )
-                };
-                match result {
-                    Ok(()) => arranged += 1,
-                    Err(e) => errors.push(format!("Failed to position '{}': {}", win.title, e)),
-                }
+    #[test]
+    fn windows_beyond_the_slots_are_skipped() {
+        let fake = desktop();
+        let plan = plan_on(&fake, "left-right", &config(""), None);
+        assert_eq!(plan.moves.len(), 2);
+        assert_eq!(plan.skipped.len(), 1);
+
+        let skipped = plan.skipped[0].hwnd;
+        let before = fake.windows().into_iter().find(|w| w.hwnd == skipped).unwrap();
+        let result = execute_plan(&fake, &plan, false);
+        assert_eq!((result.arranged, result.skipped), (2, 1));
+        let after = fake.windows().into_iter().find(|w| w.hwnd == skipped).unwrap();
+        assert_eq!(
+            (before.x, before.y, before.w, before.h),
+            (after.x, after.y, after.w, after.h)
+        );
+    }
+
+    #[test]
+    fn bsp_gives_every_window_a_slot() {
+        let fake = desktop();
+        for hwnd in 4..=6 {
+            let window = format!(
+                "hwnd = {}\nprocess = \"xterm\"\nx = 0\ny = 0\nw = 300\nh = 300",
+                hwnd
+            );
+            fake.open(toml::from_str(&window).unwrap());
+        }
+        let plan = plan_on(&fake, "bsp", &config(""), None);
+        assert!(plan.skipped.is_empty());
+        assert_eq!(plan.moves.len(), 6);
+        let area: i32 = plan.moves.iter().map(|mv| mv.target.w * mv.target.h).sum();
+        assert_eq!(area, 1200 * 800);
+        for (i, a) in plan.moves.iter().enumerate() {
+            for b in &plan.moves[i + 1..] {
+                let (a, b) = (&a.target, &b.target);
+                let apart = a.x + a.w <= b.x || b.x + b.w <= a.x || a.y + a.h <= b.y
+                    || b.y + b.h <= a.y;
+                assert!(apart, "{a:?} overlaps {b:?}");
             }
         }
 
-        let remaining: usize = unpinned_iter.count();
-        return ArrangeResult {
-            arranged,
-            skipped: remaining,
-            errors,
-        };
-    } else if smart_sort {
-        // Smart sort without pins
-        if let Some(tracker) = activity {
-            let scores = tracker.score_windows(&windows);
-            let mut scored: Vec<_> = windows.into_iter().zip(scores).collect();
-            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            windows = scored.into_iter().map(|(w, _)| w).collect();
-        }
+        // A cap leaves the rest of the windows out, and fewer windows than
+        // the cap leave no empty slots
+        let plan = plan_on(&fake, "bsp:4", &config(""), None);
+        assert_eq!((plan.moves.len(), plan.skipped.len()), (4, 2));
+        let fake = desktop();
+        let plan = plan_on(&fake, "bsp:5", &config(""), None);
+        assert_eq!(plan.moves.len(), 3);
+        let area: i32 = plan.moves.iter().map(|mv| mv.target.w * mv.target.h).sum();
+        assert_eq!(area, 1200 * 800);
     }
 
-    // Standard arrangement (no pins, or no smart sort)
-    let mut arranged = 0;
-    let mut errors = Vec::new();
+    #[test]
+    fn pins_take_their_slots() {
+        let fake = desktop();
+        let config = config(
+            r#"
+            [[pin]]
+            process = "slack.exe"
+            slot = 0
 
-    for (i, win) in windows.iter().enumerate() {
-        if i >= slots.len() {
-            break;
-        }
-        let slot = &slots[i];
-
-        let result = unsafe {
-            SetWindowPos(
-                HWND(win.hwnd as *mut _),
-                None,
-                slot.x,
-                slot.y,
-                slot.w,
-                slot.h,
-                SWP_NOZORDER | SWP_NOACTIVATE,
-            )
+            [[pin]]
+            match = { title = "^bui" }
+            slots = "2-3"
+            "#,
+        );
+        let plan = plan_on(&fake, "rows:4", &config, None);
+        assert_eq!(slot_of(&plan, 3), Some(0));
+        assert_eq!(slot_of(&plan, 1), Some(2));
+        let reasons: Vec<String> = plan.moves.iter().map(|mv| mv.reason.to_string()).collect();
+        assert!(reasons.contains(&"pin rule #1".to_string()), "{reasons:?}");
+        assert!(reasons.contains(&"pin rule #2".to_string()), "{reasons:?}");
+
+        let result = execute_plan(&fake, &plan, false);
+        let mut fired: Vec<(usize, usize)> =
+            result.pinned.iter().map(|p| (p.rule, p.slot)).collect();
+        fired.sort();
+        assert_eq!(fired, vec![(0, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn pin_scopes_match_any_spelling_of_the_layout() {
+        let fake = desktop();
+        let config = config(
+            r#"
+            [[pin]]
+            process = "slack.exe"
+            slot = 3
+            layout = "2x2 Grid"
+
+            [[pin]]
+            process = "code.exe"
+            slot = 2
+            layout = "3x3"
+            "#,
+        );
+        let plan = plan_on(&fake, "2x2", &config, None);
+        let reason = |hwnd| {
+            plan.moves.iter().find(|mv| mv.hwnd == hwnd).map(|mv| mv.reason.to_string())
         };
+        assert_eq!(slot_of(&plan, 3), Some(3));
+        assert_eq!(reason(3).as_deref(), Some("pin rule #1"));
+        assert_ne!(reason(2).as_deref(), Some("pin rule #2"));
 
-        match result {
-            Ok(()) => arranged += 1,
-            Err(e) => errors.push(format!("Failed to position '{}': {}", win.title, e)),
-        }
+        // Custom grids have no name, only their shape
+        let grid = LayoutPreset::Grid { cols: 2, rows: 2 };
+        assert!(config.pin[0].applies_to(None, &grid));
+        assert!(!config.pin[1].applies_to(None, &grid));
+        assert!(!config.pin[0].applies_to(Some("work"), &LayoutPreset::LeftRight));
     }
 
-    let skipped = if windows.len() > slots.len() {
-        windows.len() - slots.len()
-    } else {
-        0
-    };
+    #[test]
+    fn pin_priority_decides_shared_slots() {
+        let fake = desktop();
+        let config = config(
+            r#"
+            [[pin]]
+            process = "slack.exe"
+            slot = 1
 
-    ArrangeResult {
-        arranged,
-        skipped,
-        errors,
+            [[pin]]
+            process = "code.exe"
+            slot = 1
+            priority = 5
+
+            [[pin]]
+            match = { category = "terminal" }
+            slots = [0, 2]
+            max_windows = 0
+            "#,
+        );
+        let plan = plan_on(&fake, "bsp:3", &config, None);
+        assert_eq!(slot_of(&plan, 2), Some(1));
+        let slack = plan.moves.iter().find(|mv| mv.hwnd == 3).unwrap();
+        assert!(matches!(slack.reason, MoveReason::Assigned { .. }));
+        // max_windows = 0 leaves the terminal to the assignment engine
+        let pwsh = plan.moves.iter().find(|mv| mv.hwnd == 1).unwrap();
+        assert!(matches!(pwsh.reason, MoveReason::Assigned { .. }));
+    }
+
+    #[test]
+    fn smart_sort_orders_by_activity() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
+        let record = |focus: f64, switches: u64, ago_hours: f64| AppRecord {
+            total_focus_secs: focus,
+            total_switches: switches,
+            last_focus_ts: now - ago_hours * 3600.0,
+            category: String::new(),
+            last_title: String::new(),
+        };
+        let mut db = ActivityDb::default();
+        db.apps.insert("slack.exe".into(), record(5000.0, 400, 0.1));
+        db.apps.insert("pwsh.exe".into(), record(600.0, 20, 6.0));
+        let tracker = ActivityTracker::from_db(db, 7.0);
+
+        let fake = desktop();
+        let plan = plan_on(&fake, "top-bottom", &config(""), Some(&tracker));
+        // The unused editor is the one left without a slot
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].process_name, "code.exe");
+        let rank = |hwnd| {
+            plan.moves.iter().find_map(|mv| match mv.reason {
+                MoveReason::Assigned { rank, score } if mv.hwnd == hwnd => Some((rank, score)),
+                _ => None,
+            })
+        };
+        let (slack_rank, slack_score) = rank(3).unwrap();
+        let (pwsh_rank, pwsh_score) = rank(1).unwrap();
+        assert_eq!((slack_rank, pwsh_rank), (0, 1));
+        assert!(slack_score > pwsh_score);
     }
 }
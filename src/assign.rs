@@ -0,0 +1,331 @@
+use crate::constraints::SizeLimits;
+use crate::layout::Slot;
+use crate::monitor::Rect;
+
+/// Movement cost for minimized windows, whose rect says nothing about where
+/// they will reappear.
+const MINIMIZED_MOVE_COST: f64 = 0.5;
+/// How much a top-priority window prefers the largest slot over moving.
+const SIZE_WEIGHT: f64 = 1.0;
+
+/// What the assignment engine needs to know about a window.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// Current position, or `None` if minimized.
+    pub rect: Option<Rect>,
+    pub limits: SizeLimits,
+    /// Slot the window is pinned to, if any.
+    pub pin: Option<usize>,
+    /// Slot the window was in the last time this layout was applied.
+    pub previous: Option<usize>,
+}
+
+/// Why a window ended up in its slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Pinned,
+    /// Back in the slot it had last time.
+    Previous,
+    /// Placed by cost minimization.
+    Optimized,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// Index into the candidate list.
+    pub window: usize,
+    pub reason: Reason,
+}
+
+/// Decide which window goes in which slot. `windows` are in priority order
+/// (highest score first) and the result holds a placement per slot.
+///
+/// Pinned windows get their slot (the first pin to claim a slot wins; later
+/// ones compete like unpinned windows). Windows then go back to the slot they
+/// had last time if it is still free. The highest-priority remaining windows
+/// fill the other slots, arranged to minimize the total distance
+/// they move while keeping higher-priority windows in larger slots and
+/// windows with a minimum size in slots that fit them. Re-applying the same
+/// layout therefore leaves windows where they are.
+pub fn assign(windows: &[Candidate], slots: &[Slot]) -> Vec<Option<Placement>> {
+    let place = |window, reason| Some(Placement { window, reason });
+    let mut placement: Vec<Option<Placement>> = vec![None; slots.len()];
+    let mut free_windows = Vec::new();
+    for (i, win) in windows.iter().enumerate() {
+        match win.pin {
+            Some(slot) if slot < slots.len() && placement[slot].is_none() => {
+                placement[slot] = place(i, Reason::Pinned);
+            }
+            _ => free_windows.push(i),
+        }
+    }
+    free_windows.retain(|&i| match windows[i].previous {
+        Some(slot) if slot < slots.len() && placement[slot].is_none() => {
+            placement[slot] = place(i, Reason::Previous);
+            false
+        }
+        _ => true,
+    });
+
+    let free_slots: Vec<usize> = (0..slots.len()).filter(|&s| placement[s].is_none()).collect();
+    free_windows.truncate(free_slots.len());
+    if free_windows.is_empty() {
+        return placement;
+    }
+
+    let scale = movement_scale(slots);
+    let max_area = slots.iter().map(slot_area).fold(1.0, f64::max);
+    let n = free_windows.len();
+    let mut cost: Vec<Vec<f64>> = free_windows
+        .iter()
+        .enumerate()
+        .map(|(rank, &w)| {
+            let win = &windows[w];
+            let priority = 1.0 - rank as f64 / n as f64;
+            free_slots
+                .iter()
+                .map(|&s| {
+                    let slot = &slots[s];
+                    let movement = win
+                        .rect
+                        .map_or(MINIMIZED_MOVE_COST, |r| movement(&r, slot) / scale);
+                    movement + SIZE_WEIGHT * priority * (1.0 - slot_area(slot) / max_area)
+                })
+                .collect()
+        })
+        .collect();
+
+    // Putting a window in a slot it is too small for costs more than all the
+    // other costs together could, so the matching first minimizes misfits
+    let max_cost = cost.iter().flatten().fold(0.0, |a: f64, &c| a.max(c));
+    let misfit_cost = max_cost * n as f64 + 1.0;
+    for (row, &w) in cost.iter_mut().zip(&free_windows) {
+        for (c, &s) in row.iter_mut().zip(&free_slots) {
+            if !windows[w].limits.fits(&slots[s]) {
+                *c += misfit_cost;
+            }
+        }
+    }
+
+    for (row, col) in min_cost_matching(&cost).into_iter().enumerate() {
+        placement[free_slots[col]] = place(free_windows[row], Reason::Optimized);
+    }
+    placement
+}
+
+fn slot_area(slot: &Slot) -> f64 {
+    slot.w.max(0) as f64 * slot.h.max(0) as f64
+}
+
+/// How far a window travels: center distance plus half the size change.
+fn movement(from: &Rect, to: &Slot) -> f64 {
+    let dx = (from.x as f64 + from.w as f64 / 2.0) - (to.x as f64 + to.w as f64 / 2.0);
+    let dy = (from.y as f64 + from.h as f64 / 2.0) - (to.y as f64 + to.h as f64 / 2.0);
+    let resize = ((from.w - to.w).abs() + (from.h - to.h).abs()) as f64 / 2.0;
+    (dx * dx + dy * dy).sqrt() + resize
+}
+
+/// Diagonal of the slots' bounding box, so movement costs are comparable
+/// across monitor sizes.
+fn movement_scale(slots: &[Slot]) -> f64 {
+    let left = slots.iter().map(|s| s.x).min().unwrap_or(0);
+    let top = slots.iter().map(|s| s.y).min().unwrap_or(0);
+    let right = slots.iter().map(|s| s.x + s.w).max().unwrap_or(1);
+    let bottom = slots.iter().map(|s| s.y + s.h).max().unwrap_or(1);
+    let (w, h) = ((right - left) as f64, (bottom - top) as f64);
+    (w * w + h * h).sqrt().max(1.0)
+}
+
+/// Minimum-cost assignment of rows to distinct columns (Hungarian method,
+/// O(n²m)). Requires rows <= columns. Returns the column for each row.
+fn min_cost_matching(cost: &[Vec<f64>]) -> Vec<usize> {
+    let n = cost.len();
+    let m = cost.first().map_or(0, |r| r.len());
+    debug_assert!(n <= m, "more rows than columns");
+
+    // 1-based potentials and matching, column 0 is a sentinel
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut row_of = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut col0 = 0;
+        let mut min_v = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[col0] = true;
+            let r = row_of[col0];
+            let mut delta = f64::INFINITY;
+            let mut col1 = 0;
+            for col in 1..=m {
+                if used[col] {
+                    continue;
+                }
+                let reduced = cost[r - 1][col - 1] - u[r] - v[col];
+                if reduced < min_v[col] {
+                    min_v[col] = reduced;
+                    way[col] = col0;
+                }
+                if min_v[col] < delta {
+                    delta = min_v[col];
+                    col1 = col;
+                }
+            }
+            for col in 0..=m {
+                if used[col] {
+                    u[row_of[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_v[col] -= delta;
+                }
+            }
+            col0 = col1;
+            if row_of[col0] == 0 {
+                break;
+            }
+        }
+        // Walk the augmenting path back
+        loop {
+            let prev = way[col0];
+            row_of[col0] = row_of[prev];
+            col0 = prev;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut result = vec![0; n];
+    for col in 1..=m {
+        if row_of[col] != 0 {
+            result[row_of[col] - 1] = col - 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(cost: &[Vec<f64>], cols: &[usize]) -> f64 {
+        cols.iter().enumerate().map(|(r, &c)| cost[r][c]).sum()
+    }
+
+    /// Cheapest total over every assignment of rows to distinct columns.
+    fn brute_force(cost: &[Vec<f64>]) -> f64 {
+        fn go(cost: &[Vec<f64>], row: usize, used: &mut Vec<bool>) -> f64 {
+            if row == cost.len() {
+                return 0.0;
+            }
+            let mut best = f64::INFINITY;
+            for col in 0..used.len() {
+                if !used[col] {
+                    used[col] = true;
+                    best = best.min(cost[row][col] + go(cost, row + 1, used));
+                    used[col] = false;
+                }
+            }
+            best
+        }
+        let m = cost.first().map_or(0, |r| r.len());
+        go(cost, 0, &mut vec![false; m])
+    }
+
+    fn assert_distinct(cols: &[usize]) {
+        let mut sorted = cols.to_vec();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), cols.len(), "columns reused: {cols:?}");
+    }
+
+    fn slot(x: i32, w: i32) -> Slot {
+        Slot { x, y: 0, w, h: 1000 }
+    }
+
+    fn window(x: i32, w: i32, min_w: i32) -> Candidate {
+        Candidate {
+            rect: Some(Rect { x, y: 0, w, h: 1000 }),
+            limits: SizeLimits {
+                min_w,
+                ..SizeLimits::default()
+            },
+            pin: None,
+            previous: None,
+        }
+    }
+
+    #[test]
+    fn matching_square() {
+        let cost = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        let cols = min_cost_matching(&cost);
+        assert_distinct(&cols);
+        assert_eq!(total(&cost, &cols), 5.0);
+        assert_eq!(cols, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn matching_rectangular() {
+        let cost = vec![vec![7.0, 3.0, 9.0, 1.0], vec![2.0, 8.0, 6.0, 1.5]];
+        let cols = min_cost_matching(&cost);
+        assert_distinct(&cols);
+        assert_eq!(cols, vec![3, 0]);
+        assert!(min_cost_matching(&[]).is_empty());
+    }
+
+    #[test]
+    fn matching_ties() {
+        let cost = vec![vec![1.0; 3]; 3];
+        let cols = min_cost_matching(&cost);
+        assert_distinct(&cols);
+        assert_eq!(total(&cost, &cols), 3.0);
+    }
+
+    #[test]
+    fn matching_is_optimal() {
+        let mut seed = 0x853c_49e6_748f_ea9b_u64;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+        for _ in 0..500 {
+            let rows = 1 + next() % 6;
+            let cols = rows + next() % 3;
+            // Few distinct values, so ties are common
+            let cost: Vec<Vec<f64>> = (0..rows)
+                .map(|_| (0..cols).map(|_| (next() % 5) as f64).collect())
+                .collect();
+            let result = min_cost_matching(&cost);
+            assert_distinct(&result);
+            assert_eq!(total(&cost, &result), brute_force(&cost), "{cost:?}");
+        }
+    }
+
+    #[test]
+    fn misfit_outweighs_movement() {
+        // The top window already sits in the big slot, and the one that
+        // needs it is far away on another monitor; it still gets the slot
+        let slots = [slot(0, 400), slot(400, 1200)];
+        let windows = [window(400, 1200, 0), window(50_000, 1200, 1000)];
+        let placement = assign(&windows, &slots);
+        assert_eq!(placement[1].map(|p| p.window), Some(1));
+        assert_eq!(placement[0].map(|p| p.window), Some(0));
+    }
+
+    #[test]
+    fn misfit_only_when_nothing_fits() {
+        // Both need the big slot; one gets it and the other still gets placed
+        let slots = [slot(0, 400), slot(400, 1200)];
+        let windows = [window(0, 400, 1000), window(400, 1200, 1000)];
+        let placement = assign(&windows, &slots);
+        assert!(placement.iter().all(|p| p.is_some()));
+        let big = placement[1].unwrap().window;
+        assert!(windows[big].limits.fits(&slots[1]));
+    }
+}
@@ -0,0 +1,230 @@
+use super::WindowBackend;
+use crate::monitor::{monitor_index, MonitorInfo, Rect};
+use crate::windows::{categorize_process, should_manage, ManagedWindow, TargetFilter};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// In-memory desktop for running the arrange logic without touching real
+/// windows, e.g. `--simulate scenario.toml`. Moves update the stored rects
+/// (respecting each window's minimum size) instead of the screen.
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    state: Mutex<Scenario>,
+}
+
+/// A desktop described in TOML: `[[monitor]]` and `[[window]]` tables, with
+/// windows in z-order (topmost first).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub monitor: Vec<FakeMonitor>,
+    #[serde(default)]
+    pub window: Vec<FakeWindow>,
+    #[serde(default)]
+    pub foreground: Option<isize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FakeMonitor {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FakeWindow {
+    pub hwnd: isize,
+    pub process: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub class: String,
+    /// Executable path, for rules matching on it.
+    #[serde(default)]
+    pub exe: String,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    #[serde(default)]
+    pub minimized: bool,
+    /// Smallest size the window accepts; moves below it are rounded up,
+    /// like apps that refuse to shrink.
+    #[serde(default)]
+    pub min_width: i32,
+    #[serde(default)]
+    pub min_height: i32,
+}
+
+impl FakeWindow {
+    fn rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+impl FakeBackend {
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            state: Mutex::new(scenario),
+        }
+    }
+
+    /// Load a scenario file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let scenario = toml::from_str::<Scenario>(&content).map_err(|e| e.to_string())?;
+        Ok(Self::new(scenario))
+    }
+
+    /// Current state of every window.
+    pub fn windows(&self) -> Vec<FakeWindow> {
+        self.with_state(|s| s.window.clone())
+    }
+
+    /// Open a window on top of the others.
+    #[cfg(test)]
+    pub fn open(&self, window: FakeWindow) {
+        self.with_state(|s| s.window.insert(0, window));
+    }
+
+    /// Close a window.
+    #[cfg(test)]
+    pub fn close(&self, hwnd: isize) {
+        self.with_state(|s| s.window.retain(|w| w.hwnd != hwnd));
+    }
+
+    fn with_state<T>(&self, f: impl FnOnce(&mut Scenario) -> T) -> T {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut state)
+    }
+
+    fn with_window(&self, hwnd: isize, f: impl FnOnce(&mut FakeWindow)) {
+        self.with_state(|s| {
+            if let Some(win) = s.window.iter_mut().find(|w| w.hwnd == hwnd) {
+                f(win);
+            }
+        })
+    }
+}
+
+impl WindowBackend for FakeBackend {
+    fn enumerate_windows(
+        &self,
+        filter: &TargetFilter,
+        app_hwnd: isize,
+        extra_exclude: &[String],
+    ) -> Vec<ManagedWindow> {
+        let monitors = self.monitors();
+        self.with_state(|s| {
+            s.window
+                .iter()
+                .filter(|w| w.hwnd != app_hwnd)
+                .map(|w| ManagedWindow {
+                    hwnd: w.hwnd,
+                    title: w.title.clone(),
+                    process_name: w.process.clone(),
+                    class_name: w.class.clone(),
+                    exe_path: w.exe.clone(),
+                    category: categorize_process(&w.process),
+                    rect: w.rect(),
+                    monitor: monitor_index(&monitors, &w.rect()),
+                    is_minimized: w.minimized,
+                })
+                .filter(|w| should_manage(filter, w, extra_exclude))
+                .collect()
+        })
+    }
+
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        self.with_state(|s| {
+            s.monitor
+                .iter()
+                .map(|m| MonitorInfo {
+                    is_primary: m.primary,
+                    work_area: Rect {
+                        x: m.x,
+                        y: m.y,
+                        w: m.w,
+                        h: m.h,
+                    },
+                })
+                .collect()
+        })
+    }
+
+    fn move_window(&self, hwnd: isize, rect: &Rect) -> Result<(), String> {
+        self.with_state(|s| {
+            let win = s
+                .window
+                .iter_mut()
+                .find(|w| w.hwnd == hwnd)
+                .ok_or_else(|| format!("no window {}", hwnd))?;
+            win.x = rect.x;
+            win.y = rect.y;
+            win.w = rect.w.max(win.min_width);
+            win.h = rect.h.max(win.min_height);
+            Ok(())
+        })
+    }
+
+    fn window_rect(&self, hwnd: isize) -> Option<Rect> {
+        self.with_state(|s| s.window.iter().find(|w| w.hwnd == hwnd).map(|w| w.rect()))
+    }
+
+    fn minimize(&self, hwnd: isize) {
+        self.with_window(hwnd, |w| w.minimized = true);
+    }
+
+    fn is_minimized(&self, hwnd: isize) -> bool {
+        self.with_state(|s| s.window.iter().any(|w| w.hwnd == hwnd && w.minimized))
+    }
+
+    fn restore(&self, hwnd: isize) {
+        self.with_window(hwnd, |w| w.minimized = false);
+    }
+
+    fn focus(&self, hwnd: isize) {
+        self.with_window(hwnd, |w| w.minimized = false);
+        self.with_state(|s| {
+            if s.window.iter().any(|w| w.hwnd == hwnd) {
+                s.foreground = Some(hwnd);
+            }
+        });
+    }
+
+    fn raise(&self, hwnd: isize) {
+        self.with_state(|s| {
+            if let Some(i) = s.window.iter().position(|w| w.hwnd == hwnd) {
+                let win = s.window.remove(i);
+                s.window.insert(0, win);
+            }
+        });
+    }
+
+    fn foreground_window(&self) -> Option<isize> {
+        self.with_state(|s| s.foreground)
+    }
+
+    fn process_name(&self, hwnd: isize) -> Option<String> {
+        self.with_state(|s| s.window.iter().find(|w| w.hwnd == hwnd).map(|w| w.process.clone()))
+    }
+
+    fn window_title(&self, hwnd: isize) -> String {
+        self.with_state(|s| {
+            s.window
+                .iter()
+                .find(|w| w.hwnd == hwnd)
+                .map(|w| w.title.clone())
+                .unwrap_or_default()
+        })
+    }
+}
@@ -0,0 +1,121 @@
+//! Platform access for window management. Everything that talks to the OS
+//! about windows and monitors goes through [`WindowBackend`], so the layout,
+//! assignment, pin and activity logic never calls platform APIs directly.
+
+pub mod fake;
+#[cfg(windows)]
+pub mod win32;
+#[cfg(target_os = "linux")]
+pub mod sway;
+#[cfg(target_os = "linux")]
+pub mod x11;
+
+use crate::monitor::{MonitorInfo, Rect};
+use crate::windows::{ManagedWindow, TargetFilter};
+use std::sync::Arc;
+
+pub trait WindowBackend: Send + Sync {
+    /// Visible top-level windows accepted by `filter`, skipping `app_hwnd`
+    /// and any process in `extra_exclude` (lowercase names). Topmost first
+    /// where the platform has a stacking order.
+    fn enumerate_windows(
+        &self,
+        filter: &TargetFilter,
+        app_hwnd: isize,
+        extra_exclude: &[String],
+    ) -> Vec<ManagedWindow>;
+
+    /// Monitors with their work areas, primary flagged.
+    fn monitors(&self) -> Vec<MonitorInfo>;
+
+    /// Move and resize a window without activating it.
+    fn move_window(&self, hwnd: isize, rect: &Rect) -> Result<(), String>;
+
+    /// Current screen rect of a window.
+    fn window_rect(&self, hwnd: isize) -> Option<Rect>;
+
+    fn minimize(&self, hwnd: isize);
+
+    fn is_minimized(&self, hwnd: isize) -> bool;
+
+    /// Restore a minimized window, without activating it where the platform
+    /// allows. Focus is left to the caller.
+    fn restore(&self, hwnd: isize);
+
+    /// Bring a window to the front, restoring it first if minimized.
+    fn focus(&self, hwnd: isize);
+
+    /// Put a window above all others, without activating it where the
+    /// platform allows.
+    fn raise(&self, hwnd: isize);
+
+    fn foreground_window(&self) -> Option<isize>;
+
+    fn process_name(&self, hwnd: isize) -> Option<String>;
+
+    fn window_title(&self, hwnd: isize) -> String;
+
+    /// Whether a window has its new rect as soon as `move_window` returns.
+    /// X11 window managers and Wayland compositors apply moves later, so
+    /// reading the rect back right away can't tell what size a window took.
+    fn moves_are_synchronous(&self) -> bool {
+        true
+    }
+
+    /// Whether `raise` also activates the window, so raising several
+    /// windows in a row moves focus (and the visible workspace) each time.
+    fn raise_focuses(&self) -> bool {
+        false
+    }
+}
+
+/// The backend for the platform we're running on. Without a usable display
+/// connection this is an empty desktop, so the GUI still runs.
+pub fn native() -> Arc<dyn WindowBackend> {
+    #[cfg(windows)]
+    {
+        Arc::new(win32::Win32Backend)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // Wayland clients can't move other windows, but the compositor can
+        if let Some(backend) = sway::SwayBackend::from_env() {
+            return Arc::new(backend);
+        }
+        match x11::X11Backend::connect() {
+            Ok(backend) => Arc::new(backend),
+            Err(e) => {
+                log::warn!("No X11 window manager available: {}", e);
+                Arc::new(fake::FakeBackend::default())
+            }
+        }
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        log::warn!("Window management is not supported on this platform");
+        Arc::new(fake::FakeBackend::default())
+    }
+}
+
+/// File name of a process's executable (`comm` is cut at 15 characters, so
+/// it's only the fallback).
+#[cfg(target_os = "linux")]
+fn process_name(pid: u32) -> Option<String> {
+    std::fs::read_link(format!("/proc/{}/exe", pid))
+        .ok()
+        .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .or_else(|| {
+            std::fs::read_to_string(format!("/proc/{}/comm", pid))
+                .ok()
+                .map(|s| s.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+}
+
+/// Full path of a process's executable, empty if it can't be read.
+#[cfg(target_os = "linux")]
+fn exe_path(pid: u32) -> String {
+    std::fs::read_link(format!("/proc/{}/exe", pid))
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
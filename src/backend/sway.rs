@@ -0,0 +1,561 @@
+use super::{exe_path, process_name, WindowBackend};
+use crate::monitor::{monitor_index, MonitorInfo, Rect};
+use crate::windows::{categorize_process, should_manage, ManagedWindow, TargetFilter};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+/// Workspace that holds minimized (scratchpad) windows.
+const SCRATCHPAD: &str = "__i3_scratch";
+
+/// sway (or i3) through its IPC socket. Windows are made floating and placed
+/// with `move absolute position` / `resize set`; window handles are con ids.
+pub struct SwayBackend {
+    socket: PathBuf,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    rect: IpcRect,
+    focused: bool,
+    visible: Option<bool>,
+    /// Only set on views, not on containers. i3 never reports it.
+    pid: Option<u32>,
+    /// X window id of i3 and XWayland views.
+    window: Option<u32>,
+    /// Native Wayland clients.
+    app_id: Option<String>,
+    /// XWayland clients.
+    window_properties: Option<WindowProperties>,
+    nodes: Vec<Node>,
+    floating_nodes: Vec<Node>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct WindowProperties {
+    class: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+struct IpcRect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    #[serde(default)]
+    active: bool,
+    #[serde(default)]
+    focused: bool,
+    rect: IpcRect,
+    current_workspace: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Workspace {
+    name: String,
+    /// The output minus bars and other exclusive zones.
+    rect: IpcRect,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandReply {
+    success: bool,
+    error: Option<String>,
+}
+
+impl From<IpcRect> for Rect {
+    fn from(r: IpcRect) -> Self {
+        Rect {
+            x: r.x,
+            y: r.y,
+            w: r.width,
+            h: r.height,
+        }
+    }
+}
+
+impl Node {
+    fn is_view(&self) -> bool {
+        self.pid.is_some() || self.window.is_some()
+    }
+
+    fn title(&self) -> String {
+        self.name.clone().unwrap_or_default()
+    }
+
+    fn class(&self) -> &str {
+        self.app_id
+            .as_deref()
+            .or_else(|| self.window_properties.as_ref()?.class.as_deref())
+            .unwrap_or("")
+    }
+
+    fn process_name(&self) -> Option<String> {
+        self.pid
+            .and_then(process_name)
+            .or_else(|| Some(self.class().to_string()).filter(|c| !c.is_empty()))
+    }
+
+    fn children(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().chain(&self.floating_nodes)
+    }
+
+    /// Every view with whether it's in the scratchpad.
+    fn views<'a>(&'a self, in_scratchpad: bool, out: &mut Vec<(&'a Node, bool)>) {
+        let hidden = in_scratchpad
+            || (self.kind == "workspace" && self.name.as_deref() == Some(SCRATCHPAD));
+        if self.is_view() {
+            out.push((self, hidden));
+        }
+        for child in self.children() {
+            child.views(hidden, out);
+        }
+    }
+
+    fn find(&self, id: i64) -> Option<&Node> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children().find_map(|child| child.find(id))
+    }
+
+    fn focused_view(&self) -> Option<&Node> {
+        if self.focused && self.is_view() {
+            return Some(self);
+        }
+        self.children().find_map(Node::focused_view)
+    }
+}
+
+impl SwayBackend {
+    /// The compositor from `$SWAYSOCK` (or `$I3SOCK`), if there is one.
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SWAYSOCK")
+            .or_else(|| std::env::var_os("I3SOCK"))
+            .filter(|path| !path.is_empty())
+            .map(|path| Self::new(PathBuf::from(path)))
+    }
+
+    pub fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// Send one message and read its reply. A fresh connection per request
+    /// keeps the backend free of locking.
+    fn request<T: DeserializeOwned>(&self, kind: u32, payload: &str) -> Result<T, String> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .map_err(|e| format!("{}: {}", self.socket.display(), e))?;
+
+        let mut message = Vec::with_capacity(14 + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        stream.write_all(&message).map_err(|e| e.to_string())?;
+
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+        if &header[..6] != MAGIC {
+            return Err("not an i3-ipc reply".into());
+        }
+        let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&body).map_err(|e| e.to_string())
+    }
+
+    fn tree(&self) -> Option<Node> {
+        self.request(GET_TREE, "")
+            .map_err(|e| log::warn!("sway GET_TREE failed: {}", e))
+            .ok()
+    }
+
+    fn run(&self, command: &str) -> Result<(), String> {
+        let replies: Vec<CommandReply> = self.request(RUN_COMMAND, command)?;
+        match replies.into_iter().find(|r| !r.success) {
+            Some(reply) => Err(reply.error.unwrap_or_else(|| "command failed".into())),
+            None => Ok(()),
+        }
+    }
+
+    fn with_view<T>(&self, hwnd: isize, f: impl FnOnce(&Node) -> T) -> Option<T> {
+        let tree = self.tree()?;
+        tree.find(hwnd as i64).filter(|n| n.is_view()).map(f)
+    }
+}
+
+impl WindowBackend for SwayBackend {
+    fn enumerate_windows(
+        &self,
+        filter: &TargetFilter,
+        app_hwnd: isize,
+        extra_exclude: &[String],
+    ) -> Vec<ManagedWindow> {
+        let Some(tree) = self.tree() else {
+            return Vec::new();
+        };
+        let mut views = Vec::new();
+        tree.views(false, &mut views);
+
+        // Our own window has no usable handle on Wayland, so match the pid too
+        let own_pid = std::process::id();
+        let monitors = self.monitors();
+        let mut results = Vec::with_capacity(views.len());
+        for (node, in_scratchpad) in views {
+            if node.id as isize == app_hwnd || node.pid == Some(own_pid) {
+                continue;
+            }
+            // Skip windows on workspaces that aren't shown
+            if !in_scratchpad && node.visible == Some(false) {
+                continue;
+            }
+            let Some(process_name) = node.process_name() else {
+                continue;
+            };
+            let rect = Rect::from(node.rect);
+            if rect.w <= 0 || rect.h <= 0 {
+                continue;
+            }
+
+            let win = ManagedWindow {
+                hwnd: node.id as isize,
+                title: node.title(),
+                category: categorize_process(&process_name),
+                process_name,
+                class_name: node.class().to_string(),
+                exe_path: node.pid.map(exe_path).unwrap_or_default(),
+                rect,
+                monitor: monitor_index(&monitors, &rect),
+                is_minimized: in_scratchpad,
+            };
+            if should_manage(filter, &win, extra_exclude) {
+                results.push(win);
+            }
+        }
+        results
+    }
+
+    /// sway has no primary output, so the focused one stands in for it.
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        let outputs: Vec<Output> = match self.request(GET_OUTPUTS, "") {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                log::warn!("sway GET_OUTPUTS failed: {}", e);
+                return Vec::new();
+            }
+        };
+        let workspaces: Vec<Workspace> = self.request(GET_WORKSPACES, "").unwrap_or_default();
+
+        outputs
+            .into_iter()
+            .filter(|o| o.active)
+            .map(|output| {
+                let work_area = output
+                    .current_workspace
+                    .as_deref()
+                    .and_then(|name| workspaces.iter().find(|ws| ws.name == name))
+                    .map_or(output.rect, |ws| ws.rect);
+                MonitorInfo {
+                    is_primary: output.focused,
+                    work_area: work_area.into(),
+                }
+            })
+            .collect()
+    }
+
+    fn move_window(&self, hwnd: isize, rect: &Rect) -> Result<(), String> {
+        // Tiled windows can't be placed freely
+        self.run(&format!(
+            "[con_id={}] floating enable, resize set width {} px height {} px, \
+             move absolute position {} px {} px",
+            hwnd, rect.w, rect.h, rect.x, rect.y
+        ))
+    }
+
+    fn window_rect(&self, hwnd: isize) -> Option<Rect> {
+        self.with_view(hwnd, |node| node.rect.into())
+    }
+
+    fn minimize(&self, hwnd: isize) {
+        let _ = self.run(&format!("[con_id={}] move scratchpad", hwnd));
+    }
+
+    fn is_minimized(&self, hwnd: isize) -> bool {
+        let Some(tree) = self.tree() else {
+            return false;
+        };
+        let mut views = Vec::new();
+        tree.views(false, &mut views);
+        views
+            .iter()
+            .any(|(node, in_scratchpad)| node.id as isize == hwnd && *in_scratchpad)
+    }
+
+    /// sway only shows a scratchpad window again by focusing it.
+    fn restore(&self, hwnd: isize) {
+        self.focus(hwnd);
+    }
+
+    fn focus(&self, hwnd: isize) {
+        let _ = self.run(&format!("[con_id={}] focus", hwnd));
+    }
+
+    /// sway can only raise a window by focusing it.
+    fn raise(&self, hwnd: isize) {
+        self.focus(hwnd);
+    }
+
+    fn foreground_window(&self) -> Option<isize> {
+        let tree = self.tree()?;
+        tree.focused_view().map(|node| node.id as isize)
+    }
+
+    fn process_name(&self, hwnd: isize) -> Option<String> {
+        self.with_view(hwnd, Node::process_name).flatten()
+    }
+
+    fn window_title(&self, hwnd: isize) -> String {
+        self.with_view(hwnd, Node::title).unwrap_or_default()
+    }
+
+    fn moves_are_synchronous(&self) -> bool {
+        false
+    }
+
+    fn raise_focuses(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrange::{execute_plan, plan_arrangement, ArrangeRequest};
+    use crate::layout::{LayoutPreset, Spacing};
+    use std::collections::HashSet;
+    use std::os::unix::net::UnixListener;
+    use std::sync::{Arc, Mutex};
+
+    const SWAY_TREE: &str = include_str!("fixtures/sway_get_tree.json");
+    const SWAY_OUTPUTS: &str = include_str!("fixtures/sway_get_outputs.json");
+    const SWAY_WORKSPACES: &str = include_str!("fixtures/sway_get_workspaces.json");
+    const I3_TREE: &str = include_str!("fixtures/i3_get_tree.json");
+
+    /// A compositor socket that replays recorded replies and keeps the
+    /// commands it was sent.
+    struct MockIpc {
+        socket: PathBuf,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockIpc {
+        fn start(name: &str, tree: &'static str) -> Self {
+            let socket = std::env::temp_dir()
+                .join(format!("psm-{}-{}.sock", name, std::process::id()));
+            let _ = std::fs::remove_file(&socket);
+            let listener = UnixListener::bind(&socket).unwrap();
+            let commands = Arc::new(Mutex::new(Vec::new()));
+            let sent = Arc::clone(&commands);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { break };
+                    let mut header = [0u8; 14];
+                    if stream.read_exact(&mut header).is_err() {
+                        continue;
+                    }
+                    assert_eq!(&header[..6], MAGIC);
+                    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+                    let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+                    let mut payload = vec![0u8; len as usize];
+                    stream.read_exact(&mut payload).unwrap();
+                    let reply = match kind {
+                        RUN_COMMAND => {
+                            sent.lock().unwrap().push(String::from_utf8(payload).unwrap());
+                            r#"[{"success": true}]"#
+                        }
+                        GET_WORKSPACES => SWAY_WORKSPACES,
+                        GET_OUTPUTS => SWAY_OUTPUTS,
+                        GET_TREE => tree,
+                        other => panic!("unexpected message type {}", other),
+                    };
+                    let mut message = Vec::new();
+                    message.extend_from_slice(MAGIC);
+                    message.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+                    message.extend_from_slice(&kind.to_ne_bytes());
+                    message.extend_from_slice(reply.as_bytes());
+                    let _ = stream.write_all(&message);
+                }
+            });
+            Self { socket, commands }
+        }
+
+        fn backend(&self) -> SwayBackend {
+            SwayBackend::new(self.socket.clone())
+        }
+
+        fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for MockIpc {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.socket);
+        }
+    }
+
+    #[test]
+    fn enumerates_views() {
+        let ipc = MockIpc::start("enumerate", SWAY_TREE);
+        let windows = ipc.backend().enumerate_windows(&TargetFilter::Universal, 0, &[]);
+        let found: Vec<(isize, &str, &str, bool)> = windows
+            .iter()
+            .map(|w| (w.hwnd, w.title.as_str(), w.class_name.as_str(), w.is_minimized))
+            .collect();
+        // Slack is on a hidden workspace; Notes is in the scratchpad
+        assert_eq!(
+            found,
+            vec![
+                (14, "Notes", "org.gnome.TextEditor", true),
+                (10, "~/src: fish", "foot", false),
+                (12, "Mozilla Firefox", "firefox", false),
+                (13, "Steam", "steam", false),
+            ]
+        );
+        // No such pids, so the process name falls back to the class
+        assert_eq!(windows[1].process_name, "foot");
+        assert_eq!(ipc.backend().foreground_window(), Some(10));
+    }
+
+    #[test]
+    fn monitors_use_the_workspace_area() {
+        let ipc = MockIpc::start("monitors", SWAY_TREE);
+        let monitors = ipc.backend().monitors();
+        assert_eq!(monitors.len(), 1);
+        let area = monitors[0].work_area;
+        assert_eq!((area.x, area.y, area.w, area.h), (0, 30, 1920, 1050));
+        assert!(monitors[0].is_primary);
+    }
+
+    #[test]
+    fn move_window_floats_and_places() {
+        let ipc = MockIpc::start("move", SWAY_TREE);
+        let rect = Rect {
+            x: 10,
+            y: 40,
+            w: 800,
+            h: 600,
+        };
+        ipc.backend().move_window(12, &rect).unwrap();
+        assert_eq!(
+            ipc.commands(),
+            vec![
+                "[con_id=12] floating enable, resize set width 800 px height 600 px, \
+                 move absolute position 10 px 40 px"
+            ]
+        );
+    }
+
+    #[test]
+    fn arranges_through_ipc() {
+        let ipc = MockIpc::start("arrange", SWAY_TREE);
+        let backend = ipc.backend();
+        let preset = LayoutPreset::Columns(2);
+        let filter = TargetFilter::Custom(vec!["foot".into(), "firefox".into()]);
+        let request = ArrangeRequest {
+            preset: &preset,
+            grid_shape: None,
+            filter: &filter,
+            monitor_spec: "primary",
+            spacing: &Spacing::default(),
+            disabled: &HashSet::new(),
+            app_hwnd: 0,
+            extra_exclude: &[],
+            smart_sort: false,
+            activity: None,
+            pin_rules: &[],
+            layout_name: None,
+            slot_names: &[],
+            placements: &[],
+            size_rules: &[],
+            persist_slots: false,
+        };
+        let plan = plan_arrangement(&backend, &request);
+        let result = execute_plan(&backend, &plan, false);
+        assert_eq!(result.arranged, 2, "{:?}", result.errors);
+
+        let mut commands = ipc.commands();
+        commands.sort();
+        assert_eq!(
+            commands,
+            vec![
+                "[con_id=10] floating enable, resize set width 960 px height 1050 px, \
+                 move absolute position 0 px 30 px",
+                "[con_id=12] floating enable, resize set width 960 px height 1050 px, \
+                 move absolute position 960 px 30 px",
+            ]
+        );
+    }
+
+    #[test]
+    fn restoring_a_session_focuses_once() {
+        let _turn = crate::history::fresh();
+        let ipc = MockIpc::start("restore", SWAY_TREE);
+        let session: crate::config::Session = toml::from_str(
+            r#"
+            name = "work"
+            [[window]]
+            process = "foot"
+            x = 0
+            y = 0
+            w = 960
+            h = 1050
+            [[window]]
+            process = "firefox"
+            x = 960
+            y = 0
+            w = 960
+            h = 1050
+            "#,
+        )
+        .unwrap();
+        let filter = TargetFilter::Custom(vec!["foot".into(), "firefox".into()]);
+        let result = crate::session::restore(&ipc.backend(), &session, &filter, 0, &[]);
+        assert_eq!(result.restored, 2);
+
+        // Raising each window in turn would hand focus to every one of them
+        let focused: Vec<String> =
+            ipc.commands().into_iter().filter(|c| c.ends_with(" focus")).collect();
+        assert_eq!(focused, vec!["[con_id=10] focus"]);
+    }
+
+    #[test]
+    fn i3_views_have_no_pid() {
+        let ipc = MockIpc::start("i3", I3_TREE);
+        let windows = ipc.backend().enumerate_windows(&TargetFilter::Universal, 0, &[]);
+        let found: Vec<(&str, &str)> = windows
+            .iter()
+            .map(|w| (w.title.as_str(), w.process_name.as_str()))
+            .collect();
+        assert_eq!(found, vec![("user@host: ~", "XTerm"), ("Calculator", "Galculator")]);
+        assert_eq!(windows[1].hwnd, 94000000000006);
+    }
+}
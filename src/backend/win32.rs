@@ -0,0 +1,344 @@
+use super::WindowBackend;
+use crate::monitor::{monitor_index, MonitorInfo, Rect};
+use crate::windows::{categorize_process, should_manage, ManagedWindow, TargetFilter};
+use windows::Win32::Foundation::{BOOL, CloseHandle, HMODULE, HWND, LPARAM, RECT, TRUE};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFOEXW,
+};
+use windows::Win32::System::ProcessStatus::K32GetModuleFileNameExW;
+use windows::Win32::System::Threading::{
+    OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    BringWindowToTop, EnumWindows, GetClassNameW, GetForegroundWindow, GetWindowRect,
+    GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindowVisible, GWL_EXSTYLE,
+    GetWindowLongPtrW, HWND_TOP, SW_HIDE, SW_MINIMIZE, SW_RESTORE, SW_SHOW, SW_SHOWNOACTIVATE,
+    SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SetForegroundWindow, SetWindowPos,
+    ShowWindow, WS_EX_TOOLWINDOW,
+};
+
+/// Windows desktop via the Win32 API.
+pub struct Win32Backend;
+
+impl WindowBackend for Win32Backend {
+    fn enumerate_windows(
+        &self,
+        filter: &TargetFilter,
+        app_hwnd: isize,
+        extra_exclude: &[String],
+    ) -> Vec<ManagedWindow> {
+        find_windows(filter, app_hwnd, extra_exclude)
+    }
+
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        enumerate_monitors()
+    }
+
+    fn move_window(&self, hwnd: isize, rect: &Rect) -> Result<(), String> {
+        unsafe {
+            SetWindowPos(
+                HWND(hwnd as *mut _),
+                None,
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn window_rect(&self, hwnd: isize) -> Option<Rect> {
+        unsafe {
+            let mut rect = RECT::default();
+            GetWindowRect(HWND(hwnd as *mut _), &mut rect).ok()?;
+            Some(Rect {
+                x: rect.left,
+                y: rect.top,
+                w: rect.right - rect.left,
+                h: rect.bottom - rect.top,
+            })
+        }
+    }
+
+    fn minimize(&self, hwnd: isize) {
+        unsafe {
+            let _ = ShowWindow(HWND(hwnd as *mut _), SW_MINIMIZE);
+        }
+    }
+
+    fn is_minimized(&self, hwnd: isize) -> bool {
+        unsafe { IsIconic(HWND(hwnd as *mut _)).as_bool() }
+    }
+
+    fn restore(&self, hwnd: isize) {
+        unsafe {
+            let _ = ShowWindow(HWND(hwnd as *mut _), SW_SHOWNOACTIVATE);
+        }
+    }
+
+    fn focus(&self, hwnd: isize) {
+        unsafe {
+            let h = HWND(hwnd as *mut _);
+            if IsIconic(h).as_bool() {
+                let _ = ShowWindow(h, SW_RESTORE);
+            }
+            let _ = SetForegroundWindow(h);
+        }
+    }
+
+    fn raise(&self, hwnd: isize) {
+        unsafe {
+            let _ = SetWindowPos(
+                HWND(hwnd as *mut _),
+                HWND_TOP,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    fn foreground_window(&self) -> Option<isize> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0.is_null() {
+                None
+            } else {
+                Some(hwnd.0 as isize)
+            }
+        }
+    }
+
+    fn process_name(&self, hwnd: isize) -> Option<String> {
+        unsafe {
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(HWND(hwnd as *mut _), Some(&mut pid));
+            if pid == 0 {
+                return None;
+            }
+            get_process_name(pid)
+        }
+    }
+
+    fn window_title(&self, hwnd: isize) -> String {
+        get_window_text(HWND(hwnd as *mut _))
+    }
+}
+
+/// Show and restore the app window via direct Win32 calls.
+/// Works even when eframe's update loop is paused (hidden window).
+pub fn show_app_window(hwnd: isize) {
+    unsafe {
+        let h = HWND(hwnd as *mut _);
+        let _ = ShowWindow(h, SW_SHOW);
+        let _ = ShowWindow(h, SW_RESTORE);
+        let _ = BringWindowToTop(h);
+        let _ = SetForegroundWindow(h);
+    }
+}
+
+/// Hide the app window via direct Win32 call.
+pub fn hide_app_window(hwnd: isize) {
+    unsafe {
+        let _ = ShowWindow(HWND(hwnd as *mut _), SW_HIDE);
+    }
+}
+
+/// Whether the app window is visible (the tray thread may have restored it).
+pub fn is_app_window_visible(hwnd: isize) -> bool {
+    unsafe { IsWindowVisible(HWND(hwnd as *mut _)).as_bool() }
+}
+
+fn find_windows(filter: &TargetFilter, app_hwnd: isize, extra_exclude: &[String]) -> Vec<ManagedWindow> {
+    struct EnumState {
+        filter: TargetFilter,
+        app_hwnd: isize,
+        extra_exclude: Vec<String>,
+        monitors: Vec<MonitorInfo>,
+        results: Vec<ManagedWindow>,
+    }
+
+    unsafe extern "system" fn enum_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let state = &mut *(lparam.0 as *mut EnumState);
+
+        // Skip own window
+        if hwnd.0 as isize == state.app_hwnd {
+            return TRUE;
+        }
+
+        if !IsWindowVisible(hwnd).as_bool() {
+            return TRUE;
+        }
+
+        // Skip tool windows
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        if (ex_style as u32) & WS_EX_TOOLWINDOW.0 != 0 {
+            return TRUE;
+        }
+
+        // Get window rect
+        let mut rect = RECT::default();
+        if GetWindowRect(hwnd, &mut rect).is_err() {
+            return TRUE;
+        }
+        let w = rect.right - rect.left;
+        let h = rect.bottom - rect.top;
+        if w <= 0 || h <= 0 {
+            return TRUE;
+        }
+
+        // Get process name
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return TRUE;
+        }
+
+        let exe_path = get_process_path(pid).unwrap_or_default();
+        let process_name = file_name(&exe_path).to_string();
+        if process_name.is_empty() {
+            return TRUE;
+        }
+
+        let mut class_buf = [0u16; 256];
+        let class_len = GetClassNameW(hwnd, &mut class_buf);
+        let class_name = if class_len > 0 {
+            String::from_utf16_lossy(&class_buf[..class_len as usize])
+        } else {
+            String::new()
+        };
+
+        let rect = Rect {
+            x: rect.left,
+            y: rect.top,
+            w,
+            h,
+        };
+        let win = ManagedWindow {
+            hwnd: hwnd.0 as isize,
+            title: get_window_text(hwnd),
+            category: categorize_process(&process_name),
+            process_name,
+            class_name,
+            exe_path,
+            rect,
+            monitor: monitor_index(&state.monitors, &rect),
+            is_minimized: IsIconic(hwnd).as_bool(),
+        };
+        if should_manage(&state.filter, &win, &state.extra_exclude) {
+            state.results.push(win);
+        }
+
+        TRUE
+    }
+
+    let mut state = EnumState {
+        filter: filter.clone(),
+        app_hwnd,
+        extra_exclude: extra_exclude.to_vec(),
+        monitors: enumerate_monitors(),
+        results: Vec::with_capacity(32),
+    };
+
+    unsafe {
+        let _ = EnumWindows(
+            Some(enum_callback),
+            LPARAM(&mut state as *mut EnumState as isize),
+        );
+    }
+
+    state.results
+}
+
+fn enumerate_monitors() -> Vec<MonitorInfo> {
+    struct EnumState {
+        monitors: Vec<(HMONITOR, Rect, bool)>,
+    }
+
+    unsafe extern "system" fn enum_callback(
+        hmon: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        lparam: LPARAM,
+    ) -> BOOL {
+        let state = &mut *(lparam.0 as *mut EnumState);
+
+        let mut info: MONITORINFOEXW = std::mem::zeroed();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+
+        if GetMonitorInfoW(hmon, &mut info.monitorInfo).as_bool() {
+            let wa = info.monitorInfo.rcWork;
+            let work_area = Rect {
+                x: wa.left,
+                y: wa.top,
+                w: wa.right - wa.left,
+                h: wa.bottom - wa.top,
+            };
+            let is_primary = (info.monitorInfo.dwFlags & 1) != 0; // MONITORINFOF_PRIMARY
+            state.monitors.push((hmon, work_area, is_primary));
+        }
+
+        TRUE
+    }
+
+    let mut state = EnumState {
+        monitors: Vec::with_capacity(4),
+    };
+
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(enum_callback),
+            LPARAM(&mut state as *mut EnumState as isize),
+        );
+    }
+
+    state
+        .monitors
+        .into_iter()
+        .map(|(_hmon, work_area, is_primary)| MonitorInfo {
+            is_primary,
+            work_area,
+        })
+        .collect()
+}
+
+fn get_window_text(hwnd: HWND) -> String {
+    unsafe {
+        let mut buf = [0u16; 256];
+        let len = GetWindowTextW(hwnd, &mut buf);
+        if len > 0 {
+            String::from_utf16_lossy(&buf[..len as usize])
+        } else {
+            String::new()
+        }
+    }
+}
+
+fn get_process_name(pid: u32) -> Option<String> {
+    get_process_path(pid).map(|path| file_name(&path).to_string())
+}
+
+fn get_process_path(pid: u32) -> Option<String> {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid).ok()?;
+        let mut buf = [0u16; 260];
+        let len = K32GetModuleFileNameExW(handle, HMODULE::default(), &mut buf);
+        let _ = CloseHandle(handle);
+
+        if len == 0 {
+            return None;
+        }
+
+        Some(String::from_utf16_lossy(&buf[..len as usize]))
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('\\').next().unwrap_or_default()
+}
@@ -0,0 +1,554 @@
+use super::{exe_path, process_name, WindowBackend};
+use crate::monitor::{monitor_index, MonitorInfo, Rect};
+use crate::windows::{categorize_process, should_manage, ManagedWindow, TargetFilter};
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_CLIENT_LIST,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_RESTACK_WINDOW,
+        _NET_WM_PID,
+        _NET_WORKAREA,
+        _NET_ACTIVE_WINDOW,
+        _NET_MOVERESIZE_WINDOW,
+        _NET_CURRENT_DESKTOP,
+        _NET_WM_DESKTOP,
+        _NET_WM_NAME,
+        _NET_WM_STATE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_WINDOW_TYPE_DESKTOP,
+        _NET_FRAME_EXTENTS,
+        WM_CHANGE_STATE,
+    }
+}
+
+/// Source indication for client messages: we act as a pager, which window
+/// managers obey more readily than requests from applications.
+const SOURCE_PAGER: u32 = 2;
+/// Window gravity for `_NET_MOVERESIZE_WINDOW`: x/y are the client's own
+/// position, so frame extents can be accounted for exactly.
+const STATIC_GRAVITY: u32 = 10;
+/// `_NET_MOVERESIZE_WINDOW` flags: x, y, width and height are all present.
+const MOVE_RESIZE_ALL: u32 = 0xF << 8;
+const STATE_REMOVE: u32 = 0;
+/// `_NET_RESTACK_WINDOW` detail: above all siblings.
+const STACK_ABOVE: u32 = 0;
+/// ICCCM `WM_CHANGE_STATE` value for minimizing.
+const ICONIC_STATE: u32 = 3;
+/// `_NET_WM_DESKTOP` value of windows shown on all desktops.
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+/// X11 desktops through the EWMH hints the window manager publishes.
+/// Window handles are X window ids of client windows.
+pub struct X11Backend {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+impl X11Backend {
+    /// Connect to `$DISPLAY`. Fails without an X server or without an EWMH
+    /// window manager (nothing publishes `_NET_CLIENT_LIST`).
+    pub fn connect() -> Result<Self, String> {
+        let (conn, screen) = x11rb::connect(None).map_err(|e| e.to_string())?;
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        let backend = Self { conn, root, atoms };
+        if backend.property32(root, atoms._NET_CLIENT_LIST).is_none() {
+            return Err("the window manager doesn't publish _NET_CLIENT_LIST".into());
+        }
+        Ok(backend)
+    }
+
+    fn property32(&self, window: Window, property: Atom) -> Option<Vec<u32>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?;
+        reply.value32().map(|values| values.collect())
+    }
+
+    fn property_string(&self, window: Window, property: Atom) -> Option<String> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?;
+        if reply.format != 8 || reply.value.is_empty() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&reply.value).into_owned())
+    }
+
+    fn title(&self, window: Window) -> String {
+        self.property_string(window, self.atoms._NET_WM_NAME)
+            .or_else(|| self.property_string(window, AtomEnum::WM_NAME.into()))
+            .unwrap_or_default()
+    }
+
+    /// The class part of `WM_CLASS` ("instance\0Class\0").
+    fn class_name(&self, window: Window) -> String {
+        self.property_string(window, AtomEnum::WM_CLASS.into())
+            .and_then(|s| s.split('\0').nth(1).map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    fn pid(&self, window: Window) -> Option<u32> {
+        self.property32(window, self.atoms._NET_WM_PID)?.first().copied()
+    }
+
+    fn current_desktop(&self) -> u32 {
+        self.property32(self.root, self.atoms._NET_CURRENT_DESKTOP)
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0)
+    }
+
+    /// Frame size the window manager adds: [left, right, top, bottom].
+    fn frame_extents(&self, window: Window) -> [i32; 4] {
+        match self.property32(window, self.atoms._NET_FRAME_EXTENTS) {
+            Some(v) if v.len() == 4 => [v[0] as i32, v[1] as i32, v[2] as i32, v[3] as i32],
+            _ => [0; 4],
+        }
+    }
+
+    /// Outer rect including the frame, like Win32's `GetWindowRect`.
+    fn frame_rect(&self, window: Window) -> Option<Rect> {
+        let geometry = self.conn.get_geometry(window).ok()?.reply().ok()?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .ok()?
+            .reply()
+            .ok()?;
+        let [left, right, top, bottom] = self.frame_extents(window);
+        Some(Rect {
+            x: origin.dst_x as i32 - left,
+            y: origin.dst_y as i32 - top,
+            w: geometry.width as i32 + left + right,
+            h: geometry.height as i32 + top + bottom,
+        })
+    }
+
+    fn has_atom(&self, window: Window, property: Atom, value: Atom) -> bool {
+        self.property32(window, property)
+            .is_some_and(|values| values.contains(&value))
+    }
+
+    /// Ask the window manager to do something with `window`.
+    fn client_message(&self, window: Window, message_type: Atom, data: [u32; 5]) -> Result<(), String> {
+        let event = ClientMessageEvent::new(32, window, message_type, data);
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn.flush().map_err(|e| e.to_string())
+    }
+
+    /// The current desktop's work area from `_NET_WORKAREA`.
+    fn work_area(&self) -> Option<Rect> {
+        let areas = self.property32(self.root, self.atoms._NET_WORKAREA)?;
+        let i = self.current_desktop() as usize * 4;
+        let area = areas.get(i..i + 4).or_else(|| areas.get(0..4))?;
+        Some(Rect {
+            x: area[0] as i32,
+            y: area[1] as i32,
+            w: area[2] as i32,
+            h: area[3] as i32,
+        })
+    }
+
+    /// Monitor bounds from RandR, or the whole root window without it.
+    fn monitor_bounds(&self) -> Vec<(Rect, bool)> {
+        let monitors = self
+            .conn
+            .randr_get_monitors(self.root, true)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|reply| reply.monitors)
+            .unwrap_or_default();
+        if !monitors.is_empty() {
+            return monitors
+                .iter()
+                .map(|m| {
+                    let bounds = Rect {
+                        x: m.x as i32,
+                        y: m.y as i32,
+                        w: m.width as i32,
+                        h: m.height as i32,
+                    };
+                    (bounds, m.primary)
+                })
+                .collect();
+        }
+        self.conn
+            .get_geometry(self.root)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|g| {
+                let bounds = Rect {
+                    x: 0,
+                    y: 0,
+                    w: g.width as i32,
+                    h: g.height as i32,
+                };
+                vec![(bounds, true)]
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl WindowBackend for X11Backend {
+    fn enumerate_windows(
+        &self,
+        filter: &TargetFilter,
+        app_hwnd: isize,
+        extra_exclude: &[String],
+    ) -> Vec<ManagedWindow> {
+        // The stacking list runs bottom to top
+        let clients: Vec<Window> = match self.property32(self.root, self.atoms._NET_CLIENT_LIST_STACKING) {
+            Some(stacking) => stacking.into_iter().rev().collect(),
+            None => self
+                .property32(self.root, self.atoms._NET_CLIENT_LIST)
+                .unwrap_or_default(),
+        };
+        let desktop = self.current_desktop();
+        let monitors = self.monitors();
+        let mut results = Vec::with_capacity(clients.len());
+
+        for window in clients {
+            // Skip own window
+            if window as isize == app_hwnd {
+                continue;
+            }
+
+            // Skip windows on other desktops
+            let on_desktop = self
+                .property32(window, self.atoms._NET_WM_DESKTOP)
+                .and_then(|v| v.first().copied());
+            if on_desktop.is_some_and(|d| d != desktop && d != ALL_DESKTOPS) {
+                continue;
+            }
+
+            // Skip panels and the desktop window
+            let window_type = self.atoms._NET_WM_WINDOW_TYPE;
+            if self.has_atom(window, window_type, self.atoms._NET_WM_WINDOW_TYPE_DOCK)
+                || self.has_atom(window, window_type, self.atoms._NET_WM_WINDOW_TYPE_DESKTOP)
+            {
+                continue;
+            }
+
+            let Some(pid) = self.pid(window) else {
+                continue;
+            };
+            let Some(process_name) = process_name(pid) else {
+                continue;
+            };
+            let Some(rect) = self.frame_rect(window) else {
+                continue;
+            };
+            if rect.w <= 0 || rect.h <= 0 {
+                continue;
+            }
+
+            let win = ManagedWindow {
+                hwnd: window as isize,
+                title: self.title(window),
+                category: categorize_process(&process_name),
+                process_name,
+                class_name: self.class_name(window),
+                exe_path: exe_path(pid),
+                rect,
+                monitor: monitor_index(&monitors, &rect),
+                is_minimized: self.has_atom(
+                    window,
+                    self.atoms._NET_WM_STATE,
+                    self.atoms._NET_WM_STATE_HIDDEN,
+                ),
+            };
+            if should_manage(filter, &win, extra_exclude) {
+                results.push(win);
+            }
+        }
+
+        results
+    }
+
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        let work_area = self.work_area();
+        self.monitor_bounds()
+            .into_iter()
+            .map(|(bounds, is_primary)| MonitorInfo {
+                is_primary,
+                // _NET_WORKAREA spans all monitors, so clip it to each one
+                work_area: work_area
+                    .and_then(|area| intersect(&bounds, &area))
+                    .unwrap_or(bounds),
+            })
+            .collect()
+    }
+
+    fn move_window(&self, hwnd: isize, rect: &Rect) -> Result<(), String> {
+        let window = hwnd as Window;
+        let [left, right, top, bottom] = self.frame_extents(window);
+
+        // Window managers ignore moves of maximized windows
+        self.client_message(
+            window,
+            self.atoms._NET_WM_STATE,
+            [
+                STATE_REMOVE,
+                self.atoms._NET_WM_STATE_MAXIMIZED_VERT,
+                self.atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+                SOURCE_PAGER,
+                0,
+            ],
+        )?;
+
+        self.client_message(
+            window,
+            self.atoms._NET_MOVERESIZE_WINDOW,
+            [
+                STATIC_GRAVITY | MOVE_RESIZE_ALL | (SOURCE_PAGER << 12),
+                (rect.x + left) as u32,
+                (rect.y + top) as u32,
+                (rect.w - left - right).max(1) as u32,
+                (rect.h - top - bottom).max(1) as u32,
+            ],
+        )
+    }
+
+    fn window_rect(&self, hwnd: isize) -> Option<Rect> {
+        self.frame_rect(hwnd as Window)
+    }
+
+    fn minimize(&self, hwnd: isize) {
+        let _ = self.client_message(
+            hwnd as Window,
+            self.atoms.WM_CHANGE_STATE,
+            [ICONIC_STATE, 0, 0, 0, 0],
+        );
+    }
+
+    fn is_minimized(&self, hwnd: isize) -> bool {
+        self.has_atom(
+            hwnd as Window,
+            self.atoms._NET_WM_STATE,
+            self.atoms._NET_WM_STATE_HIDDEN,
+        )
+    }
+
+    /// Mapping an iconic window brings it back to the normal state (ICCCM
+    /// 4.1.4) without the activation `_NET_ACTIVE_WINDOW` would cause.
+    fn restore(&self, hwnd: isize) {
+        let _ = self.conn.map_window(hwnd as Window);
+        let _ = self.conn.flush();
+    }
+
+    fn focus(&self, hwnd: isize) {
+        let _ = self.client_message(
+            hwnd as Window,
+            self.atoms._NET_ACTIVE_WINDOW,
+            [SOURCE_PAGER, 0, 0, 0, 0],
+        );
+    }
+
+    fn raise(&self, hwnd: isize) {
+        let _ = self.client_message(
+            hwnd as Window,
+            self.atoms._NET_RESTACK_WINDOW,
+            [SOURCE_PAGER, 0, STACK_ABOVE, 0, 0],
+        );
+    }
+
+    fn foreground_window(&self) -> Option<isize> {
+        self.property32(self.root, self.atoms._NET_ACTIVE_WINDOW)?
+            .first()
+            .filter(|&&w| w != 0)
+            .map(|&w| w as isize)
+    }
+
+    fn process_name(&self, hwnd: isize) -> Option<String> {
+        self.pid(hwnd as Window).and_then(process_name)
+    }
+
+    fn window_title(&self, hwnd: isize) -> String {
+        self.title(hwnd as Window)
+    }
+
+    fn moves_are_synchronous(&self) -> bool {
+        false
+    }
+}
+
+fn intersect(a: &Rect, b: &Rect) -> Option<Rect> {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = (a.x + a.w).min(b.x + b.w);
+    let bottom = (a.y + a.h).min(b.y + b.h);
+    (right > x && bottom > y).then(|| Rect {
+        x,
+        y,
+        w: right - x,
+        h: bottom - y,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrange::{execute_plan, plan_arrangement, ArrangeRequest};
+    use crate::layout::{LayoutPreset, Spacing};
+    use std::collections::HashSet;
+    use std::process::{Child, Command};
+    use std::time::{Duration, Instant};
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    fn parts(r: Option<Rect>) -> Option<(i32, i32, i32, i32)> {
+        r.map(|r| (r.x, r.y, r.w, r.h))
+    }
+
+    #[test]
+    fn intersect_overlapping() {
+        let a = rect(0, 0, 100, 100);
+        let b = rect(50, 60, 100, 100);
+        assert_eq!(parts(intersect(&a, &b)), Some((50, 60, 50, 40)));
+        assert_eq!(parts(intersect(&b, &a)), Some((50, 60, 50, 40)));
+    }
+
+    #[test]
+    fn intersect_contained() {
+        let outer = rect(-1920, 0, 1920, 1080);
+        let inner = rect(-1000, 100, 200, 300);
+        assert_eq!(parts(intersect(&outer, &inner)), Some((-1000, 100, 200, 300)));
+    }
+
+    #[test]
+    fn intersect_touching_or_apart() {
+        let a = rect(0, 0, 100, 100);
+        assert!(intersect(&a, &rect(100, 0, 50, 50)).is_none());
+        assert!(intersect(&a, &rect(0, 100, 50, 50)).is_none());
+        assert!(intersect(&a, &rect(300, 300, 50, 50)).is_none());
+        assert!(intersect(&a, &rect(10, 10, 0, 0)).is_none());
+    }
+
+    /// Kills the spawned processes when the test ends, pass or fail.
+    struct Processes(Vec<Child>);
+
+    impl Drop for Processes {
+        fn drop(&mut self) {
+            for child in self.0.iter_mut().rev() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    fn wait_for<T>(what: &str, mut f: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(value) = f() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Arranges three xterms on a virtual X server and reads their geometry
+    /// back. Needs `Xvfb`, `xterm` and an EWMH window manager (`openbox`, or
+    /// the one named by `PSM_TEST_WM`):
+    ///
+    /// ```sh
+    /// cargo test xvfb -- --ignored
+    /// ```
+    #[test]
+    #[ignore = "needs Xvfb, xterm and a window manager"]
+    fn xvfb_arranges_xterms() {
+        let display = std::env::var("PSM_TEST_DISPLAY").unwrap_or_else(|_| ":97".into());
+        let wm = std::env::var("PSM_TEST_WM").unwrap_or_else(|_| "openbox".into());
+        let spawn = |cmd: &mut Command| {
+            let program = cmd.get_program().to_string_lossy().into_owned();
+            cmd.env("DISPLAY", &display)
+                .spawn()
+                .unwrap_or_else(|e| panic!("can't start {}: {}", program, e))
+        };
+
+        let mut procs = Processes(Vec::new());
+        let screen = ["-screen", "0", "1280x800x24"];
+        procs.0.push(spawn(Command::new("Xvfb").arg(&display).args(screen)));
+        std::env::set_var("DISPLAY", &display);
+        wait_for("Xvfb", || x11rb::connect(None).ok());
+        procs.0.push(spawn(&mut Command::new(&wm)));
+        let backend = wait_for("the window manager", || X11Backend::connect().ok());
+        for i in 0..3 {
+            procs.0.push(spawn(Command::new("xterm").args(["-T", &format!("psm-test-{}", i)])));
+        }
+
+        let filter = TargetFilter::Custom(vec!["xterm".into()]);
+        wait_for("three xterms", || {
+            (backend.enumerate_windows(&filter, 0, &[]).len() == 3).then_some(())
+        });
+
+        let preset = LayoutPreset::Columns(3);
+        let request = ArrangeRequest {
+            preset: &preset,
+            grid_shape: None,
+            filter: &filter,
+            monitor_spec: "primary",
+            spacing: &Spacing::default(),
+            disabled: &HashSet::new(),
+            app_hwnd: 0,
+            extra_exclude: &[],
+            smart_sort: false,
+            activity: None,
+            pin_rules: &[],
+            layout_name: None,
+            slot_names: &[],
+            placements: &[],
+            size_rules: &[],
+            persist_slots: false,
+        };
+        let plan = plan_arrangement(&backend, &request);
+        assert_eq!(plan.moves.len(), 3, "{:?}", plan.errors);
+        let result = execute_plan(&backend, &plan, false);
+        assert_eq!(result.arranged, 3, "{:?}", result.errors);
+
+        // xterm sizes itself in whole character cells, so it may come up
+        // short of its slot by less than a cell
+        for mv in &plan.moves {
+            let t = &mv.target;
+            let placed = |r: &Rect| {
+                (r.x - t.x).abs() <= 2
+                    && (r.y - t.y).abs() <= 2
+                    && (0..=24).contains(&(t.w - r.w))
+                    && (0..=24).contains(&(t.h - r.h))
+            };
+            wait_for(&format!("'{}' in slot {}", mv.title, mv.slot), || {
+                backend.window_rect(mv.hwnd).filter(placed)
+            });
+        }
+    }
+}
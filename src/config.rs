@@ -1,11 +1,23 @@
-use crate::layout::LayoutPreset;
+use crate::layout::{
+    builtin_presets, normalize_weights, CellSpan, GridShape, LayoutPreset, Margins, RelRect,
+    SidePosition, Spacing, DEFAULT_CENTER_RATIO, DEFAULT_MAIN_RATIO, DEFAULT_SPIRAL_RATIO,
+    FOCUS_MAIN_RATIO,
+};
+use crate::constraints::SizeLimits;
+use crate::rules::Rule;
+use crate::template;
+use crate::watch::ClosePolicy;
+use crate::windows::{AppCategory, ManagedWindow, TargetFilter};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedGrid {
     pub name: String,
+    #[serde(default)]
     pub cols: u32,
+    #[serde(default)]
     pub rows: u32,
     #[serde(default)]
     pub col_weights: Vec<f32>,
@@ -13,9 +25,29 @@ pub struct SavedGrid {
     pub row_weights: Vec<f32>,
     #[serde(default)]
     pub disabled_cells: Vec<usize>,
+    #[serde(default)]
+    pub merged: Vec<CellSpan>,
+    /// Preset string (e.g. "columns:3") when this entry is a tuned preset
+    /// rather than a custom grid; `cols`/`rows` are then unused.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Per-slot weights for a saved preset.
+    #[serde(default)]
+    pub weights: Vec<f32>,
+    /// Names for the slots in order, for pin rules to refer to.
+    #[serde(default)]
+    pub slot_names: Vec<String>,
 }
 
+/// A named freeform layout: rectangles in fractions of the work area.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreeLayout {
+    pub name: String,
+    #[serde(default)]
+    pub rects: Vec<RelRect>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub defaults: Defaults,
@@ -27,16 +59,42 @@ pub struct Config {
     pub pin: Vec<PinRule>,
     #[serde(default)]
     pub saved_grid: Vec<SavedGrid>,
+    #[serde(default)]
+    pub free_layout: Vec<FreeLayout>,
+    #[serde(default)]
+    pub size_constraint: Vec<SizeConstraint>,
+    #[serde(default)]
+    pub session: Vec<Session>,
+    #[serde(default)]
+    pub profile: Vec<LaunchProfile>,
+    /// Windows matching any of these rules are never managed.
+    #[serde(default)]
+    pub exclude: Vec<Rule>,
+    /// Problems found in the rules when the config was loaded.
+    #[serde(skip)]
+    pub errors: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Defaults {
     #[serde(default = "default_target")]
     pub target: String,
+    /// Narrows `target` to the windows matching this rule.
+    #[serde(default)]
+    pub target_match: Option<Rule>,
     #[serde(default = "default_monitor")]
     pub monitor: String,
     #[serde(default = "default_gap")]
     pub gap: i32,
+    /// Gap between side-by-side slots, if different from `gap`.
+    #[serde(default)]
+    pub gap_x: Option<i32>,
+    /// Gap between stacked slots, if different from `gap`.
+    #[serde(default)]
+    pub gap_y: Option<i32>,
+    /// Space to leave free at the work area edges (docks, overlays).
+    #[serde(default)]
+    pub outer_margin: Margins,
     #[serde(default)]
     pub theme: usize,
     #[serde(default = "default_true")]
@@ -56,9 +114,32 @@ pub struct Defaults {
     #[serde(default)]
     pub row_weights: Vec<f32>,
     #[serde(default)]
+    pub merged_cells: Vec<CellSpan>,
+    #[serde(default)]
+    pub use_free: bool,
+    #[serde(default)]
+    pub free_rects: Vec<RelRect>,
+    #[serde(default)]
     pub smart_sort: bool,
+    /// Keep the last window-to-slot mapping on disk so re-applying a layout
+    /// after a restart leaves windows where they are.
+    #[serde(default)]
+    pub persist_slot_memory: bool,
+    /// Keep the selected layout live: new windows snap into free slots.
+    #[serde(default)]
+    pub watch: bool,
+    /// What happens to the other windows when an arranged window closes.
+    #[serde(default)]
+    pub watch_close_policy: ClosePolicy,
     #[serde(default = "default_decay_half_life")]
     pub decay_half_life_days: f64,
+    /// Main/side split override for the selected preset (set by dragging the preview divider).
+    #[serde(default)]
+    pub main_ratio: Option<f32>,
+    /// Per-slot weight override for the selected preset (set by dragging the
+    /// preview dividers of column, row and main/side presets).
+    #[serde(default)]
+    pub slot_weights: Vec<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,8 +149,43 @@ pub struct LayoutDef {
     pub grid: Option<String>,
     #[serde(default)]
     pub style: Option<String>,
+    /// Number of slots; a bsp layout without one gets a slot per window.
     #[serde(default)]
     pub count: Option<u32>,
+    /// Share of the remaining area each slot takes (spiral style), the
+    /// center column's width share (center-main style), or the main area's
+    /// share (main-side / focus styles).
+    #[serde(default)]
+    pub ratio: Option<f32>,
+    /// Edge the side stack sits on for main-side / focus: left, right, top, bottom.
+    #[serde(default)]
+    pub side: Option<String>,
+    /// Windows left and right of the center column (center-main style). By
+    /// default `count` is split evenly, the extra one going left.
+    #[serde(default)]
+    pub left: Option<u32>,
+    #[serde(default)]
+    pub right: Option<u32>,
+    /// Nested-split expression, e.g. "h(0.6: main, v(a, b, c))". Takes
+    /// precedence over `grid` and `style`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Names for the slots in order, e.g. ["editor", "logs", "chat"], for
+    /// pin rules to refer to. A template's leaf names are used if unset.
+    #[serde(default)]
+    pub slot_names: Vec<String>,
+    /// Relative slot sizes, e.g. [2, 1, 1] for columns at 50/25/25. Applies
+    /// to the columns, rows, left/right, top/bottom and (side stack of)
+    /// main-side styles.
+    #[serde(default)]
+    pub weights: Vec<f32>,
+    /// Margins for this layout, replacing `defaults.outer_margin`.
+    #[serde(default)]
+    pub outer_margin: Option<Margins>,
+    #[serde(default)]
+    pub gap_x: Option<i32>,
+    #[serde(default)]
+    pub gap_y: Option<i32>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -98,7 +214,175 @@ pub struct PinRule {
     pub process: Option<String>,
     #[serde(default)]
     pub title_contains: Option<String>,
+    /// Full rule for the window; with `process` or `title_contains` also
+    /// set, both have to match.
+    #[serde(default, rename = "match")]
+    pub rule: Option<Rule>,
+    /// Slot to pin to when `slots` isn't set.
+    #[serde(default)]
     pub slot: usize,
+    /// Slots to pin to instead, filled in order: a list like `[2, 3, 4]`
+    /// or ranges like `"2-4, 6"`. Slots can also go by name, as in
+    /// `"editor"` or `["logs", 3]`.
+    #[serde(default)]
+    pub slots: Option<SlotList>,
+    /// Layout the rule applies to, by name or preset string; every layout
+    /// if unset.
+    #[serde(default)]
+    pub layout: Option<String>,
+    /// Rules with a higher priority claim their slots first; ties go in
+    /// config order.
+    #[serde(default)]
+    pub priority: i32,
+    /// Most windows the rule pins, one per slot if unset. Windows past the
+    /// limit are arranged like unpinned ones.
+    #[serde(default)]
+    pub max_windows: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SlotList {
+    List(Vec<SlotRef>),
+    Ranges(String),
+}
+
+/// A slot by index or by the name its layout gives it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SlotRef {
+    Index(usize),
+    Name(String),
+}
+
+impl SlotList {
+    /// The slots in order, without repeats. Parts of a range string that
+    /// aren't numbers or ranges are slot names.
+    pub fn refs(&self) -> Result<Vec<SlotRef>, String> {
+        let mut refs: Vec<SlotRef> = Vec::new();
+        match self {
+            Self::List(list) => refs.extend(list.iter().cloned()),
+            Self::Ranges(spec) => {
+                for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                    let bad = || format!("invalid slot range '{}'", part);
+                    if !part.starts_with(|c: char| c.is_ascii_digit()) {
+                        refs.push(SlotRef::Name(part.to_string()));
+                        continue;
+                    }
+                    let (start, end) = match part.split_once('-') {
+                        Some((a, b)) => (a.trim(), b.trim()),
+                        None => (part, part),
+                    };
+                    let start: usize = start.parse().map_err(|_| bad())?;
+                    let end: usize = end.parse().map_err(|_| bad())?;
+                    if start > end {
+                        return Err(bad());
+                    }
+                    refs.extend((start..=end).map(SlotRef::Index));
+                }
+            }
+        }
+        let mut unique: Vec<SlotRef> = Vec::with_capacity(refs.len());
+        for r in refs {
+            if !unique.contains(&r) {
+                unique.push(r);
+            }
+        }
+        Ok(unique)
+    }
+}
+
+/// Size limits for windows of a process or category, e.g. apps that refuse
+/// to shrink below a minimum and would otherwise overlap their neighbors.
+/// Minimums learned from windows that wouldn't shrink are only kept until
+/// the app exits; configure them here to keep them for good.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SizeConstraint {
+    #[serde(default)]
+    pub process: Option<String>,
+    /// Category name, e.g. "chat" or "browser".
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub min_width: Option<i32>,
+    #[serde(default)]
+    pub min_height: Option<i32>,
+    #[serde(default)]
+    pub max_width: Option<i32>,
+    #[serde(default)]
+    pub max_height: Option<i32>,
+}
+
+/// Where every window was at one point, e.g. "standup" or "deploy". Unlike
+/// a saved grid this stores concrete placements, not a layout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub name: String,
+    /// Topmost first.
+    #[serde(default)]
+    pub window: Vec<SessionWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub process: String,
+    /// Saved as the full title; shorten it to match windows whose titles
+    /// change (case-insensitive substring).
+    #[serde(default)]
+    pub title_contains: Option<String>,
+    /// Which of several windows matching process and title this is, in
+    /// stacking order.
+    #[serde(default)]
+    pub ordinal: usize,
+    /// Monitor index; the position is relative to its work area.
+    #[serde(default)]
+    pub monitor: usize,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    #[serde(default)]
+    pub minimized: bool,
+}
+
+/// Apps to start if they aren't open, and the slots they go to, e.g. an
+/// "on-call" setup of terminals, a dashboard and chat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchProfile {
+    pub name: String,
+    /// Layout name, as in the picker, or spec, e.g. "2x3" or
+    /// "h(0.6: main, v(a, b))".
+    pub layout: String,
+    /// Monitor to use instead of `defaults.monitor`.
+    #[serde(default)]
+    pub monitor: Option<String>,
+    /// How long to wait for launched apps to open their windows.
+    #[serde(default = "default_launch_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub app: Vec<LaunchApp>,
+}
+
+/// One window of a launch profile. Several entries with the same process
+/// and title stand for that many windows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchApp {
+    pub exe: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Slot index in the profile's layout.
+    pub slot: usize,
+    /// Process that owns the window, if not `exe` (e.g. the terminal a
+    /// shell opens in).
+    #[serde(default)]
+    pub process: Option<String>,
+    /// Tells apart windows of the same process (case-insensitive substring).
+    #[serde(default)]
+    pub title_contains: Option<String>,
 }
 
 fn default_target() -> String {
@@ -110,6 +394,9 @@ fn default_monitor() -> String {
 fn default_gap() -> i32 {
     4
 }
+fn default_launch_timeout() -> u64 {
+    20
+}
 fn default_true() -> bool {
     true
 }
@@ -124,8 +411,12 @@ impl Default for Defaults {
     fn default() -> Self {
         Self {
             target: default_target(),
+            target_match: None,
             monitor: default_monitor(),
             gap: default_gap(),
+            gap_x: None,
+            gap_y: None,
+            outer_margin: Margins::default(),
             theme: 0,
             settings_open: true,
             about_open: true,
@@ -135,26 +426,41 @@ impl Default for Defaults {
             selected_preset: 0,
             col_weights: Vec::new(),
             row_weights: Vec::new(),
+            merged_cells: Vec::new(),
+            use_free: false,
+            free_rects: Vec::new(),
             smart_sort: false,
+            persist_slot_memory: false,
+            watch: false,
+            watch_close_policy: ClosePolicy::default(),
             decay_half_life_days: default_decay_half_life(),
+            main_ratio: None,
+            slot_weights: Vec::new(),
         }
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            defaults: Defaults::default(),
-            layout: Vec::new(),
-            categories: CategoryOverrides::default(),
-            pin: Vec::new(),
-            saved_grid: Vec::new(),
+impl Defaults {
+    pub fn spacing(&self) -> Spacing {
+        Spacing {
+            gap_x: self.gap_x.unwrap_or(self.gap),
+            gap_y: self.gap_y.unwrap_or(self.gap),
+            margin: self.outer_margin,
         }
     }
 }
 
 impl LayoutDef {
     pub fn to_preset(&self) -> Option<LayoutPreset> {
+        if let Some(src) = &self.template {
+            return match template::parse(src) {
+                Ok(t) => Some(LayoutPreset::Template(t)),
+                Err(e) => {
+                    log::warn!("Layout '{}': {}", self.name, e.render(src));
+                    None
+                }
+            };
+        }
         if let Some(grid) = &self.grid {
             return LayoutPreset::parse(grid);
         }
@@ -165,13 +471,212 @@ impl LayoutDef {
                 "rows" => return Some(LayoutPreset::Rows(count)),
                 "left-right" => return Some(LayoutPreset::LeftRight),
                 "top-bottom" => return Some(LayoutPreset::TopBottom),
-                "main-side" => return Some(LayoutPreset::MainSide { side_count: count }),
-                "focus" => return Some(LayoutPreset::Focus { side_count: count }),
-                _ => {}
+                "main-side" | "focus" => {
+                    let default_ratio = if style == "focus" {
+                        FOCUS_MAIN_RATIO
+                    } else {
+                        DEFAULT_MAIN_RATIO
+                    };
+                    let side = match &self.side {
+                        Some(s) => SidePosition::parse(s)?,
+                        None => SidePosition::Right,
+                    };
+                    return Some(LayoutPreset::MainSide {
+                        side_count: count.max(1),
+                        ratio: self.ratio.unwrap_or(default_ratio).clamp(0.1, 0.9),
+                        side,
+                    });
+                }
+                "bsp" | "dwindle" => return Some(LayoutPreset::Bsp(self.count.filter(|&n| n > 0))),
+                "spiral" => {
+                    let ratio = self.ratio.unwrap_or(DEFAULT_SPIRAL_RATIO).clamp(0.1, 0.9);
+                    return Some(LayoutPreset::Spiral { count, ratio });
+                }
+                "center-main" => {
+                    let ratio = self.ratio.unwrap_or(DEFAULT_CENTER_RATIO).clamp(0.1, 0.9);
+                    let (left, right) = match (self.left, self.right) {
+                        (Some(left), Some(right)) => (left, right),
+                        (Some(left), None) => (left, count.saturating_sub(left)),
+                        (None, Some(right)) => (count.saturating_sub(right), right),
+                        (None, None) => (count.div_ceil(2), count / 2),
+                    };
+                    return Some(LayoutPreset::CenterMain { left, right, ratio });
+                }
+                // Full preset strings, e.g. "main-side:3@0.6,bottom"
+                other => return LayoutPreset::parse(other),
             }
         }
         None
     }
+
+    /// Slot weights and spacing overrides for the preset, if any were
+    /// configured. Weights that don't fit the preset are ignored.
+    pub fn to_shape(&self, preset: &LayoutPreset, defaults: &Defaults) -> Option<GridShape> {
+        let weights = normalize_weights(&self.weights, preset.weight_count());
+        if weights.is_empty() && !self.weights.is_empty() {
+            log::warn!(
+                "Layout '{}': expected {} positive weights, ignoring {:?}",
+                self.name,
+                preset.weight_count(),
+                self.weights
+            );
+        }
+        let spacing = self.spacing(defaults);
+        if weights.is_empty() && spacing.is_none() {
+            return None;
+        }
+        Some(GridShape {
+            weights,
+            spacing,
+            ..Default::default()
+        })
+    }
+
+    /// The defaults' spacing with this layout's overrides, if it has any.
+    fn spacing(&self, defaults: &Defaults) -> Option<Spacing> {
+        if self.outer_margin.is_none() && self.gap_x.is_none() && self.gap_y.is_none() {
+            return None;
+        }
+        let base = defaults.spacing();
+        Some(Spacing {
+            gap_x: self.gap_x.unwrap_or(base.gap_x),
+            gap_y: self.gap_y.unwrap_or(base.gap_y),
+            margin: self.outer_margin.unwrap_or(base.margin),
+        })
+    }
+}
+
+impl SavedGrid {
+    pub fn to_preset(&self) -> Option<LayoutPreset> {
+        match &self.preset {
+            Some(spec) => LayoutPreset::parse(spec),
+            None if self.cols > 0 && self.rows > 0 => Some(LayoutPreset::Grid {
+                cols: self.cols,
+                rows: self.rows,
+            }),
+            None => None,
+        }
+    }
+
+    pub fn to_shape(&self, preset: &LayoutPreset) -> GridShape {
+        GridShape {
+            col_weights: self.col_weights.clone(),
+            row_weights: self.row_weights.clone(),
+            merges: self.merged.clone(),
+            weights: normalize_weights(&self.weights, preset.weight_count()),
+            spacing: None,
+        }
+    }
+}
+
+impl Config {
+    /// The windows to manage: `defaults.target`, narrowed by `target_match`
+    /// and the `[[exclude]]` rules.
+    pub fn target_filter(&self) -> TargetFilter {
+        let base = TargetFilter::from_str(&self.defaults.target);
+        if self.defaults.target_match.is_none() && self.exclude.is_empty() {
+            return base;
+        }
+        TargetFilter::Rules {
+            base: Box::new(base),
+            include: self.defaults.target_match.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+
+    /// Slot names of the layout called `layout` (a config layout or saved
+    /// grid), or else the leaf names of a template `preset`. Unnamed slots
+    /// have empty names.
+    pub fn slot_names(&self, layout: Option<&str>, preset: Option<&LayoutPreset>) -> Vec<String> {
+        let configured = |name: &str| {
+            self.layout
+                .iter()
+                .find(|l| l.name.eq_ignore_ascii_case(name))
+                .map(|l| l.slot_names.clone())
+                .or_else(|| {
+                    self.saved_grid
+                        .iter()
+                        .find(|g| g.name.eq_ignore_ascii_case(name))
+                        .map(|g| g.slot_names.clone())
+                })
+                .unwrap_or_default()
+        };
+        let mut names = layout.map(configured).unwrap_or_default();
+        if names.is_empty() {
+            if let Some(LayoutPreset::Template(t)) = preset {
+                names = t.slot_names();
+            }
+        }
+        // Anonymous template slots are written `_`
+        names.into_iter().map(|n| if n == "_" { String::new() } else { n }).collect()
+    }
+
+    /// The preset and shape of a built-in layout, config layout, saved grid
+    /// or free layout by name, as the picker and tray list them, or of a
+    /// preset string.
+    pub fn layout_named(&self, name: &str) -> Option<(LayoutPreset, Option<GridShape>)> {
+        let named = |n: &String| n.eq_ignore_ascii_case(name);
+        if let Some((_, preset)) = builtin_presets().into_iter().find(|(n, _)| named(n)) {
+            return Some((preset, None));
+        }
+        let def = self.layout.iter().find(|l| named(&l.name));
+        if let Some((def, preset)) = def.and_then(|d| Some((d, d.to_preset()?))) {
+            let shape = def.to_shape(&preset, &self.defaults);
+            return Some((preset, shape));
+        }
+        let grid = self.saved_grid.iter().find(|g| named(&g.name));
+        if let Some((grid, preset)) = grid.and_then(|g| Some((g, g.to_preset()?))) {
+            let shape = grid.to_shape(&preset);
+            return Some((preset, Some(shape)));
+        }
+        if let Some(free) = self.free_layout.iter().find(|f| named(&f.name)) {
+            return Some((LayoutPreset::Free(free.rects.clone()), None));
+        }
+        LayoutPreset::parse(name).map(|preset| (preset, None))
+    }
+
+    /// Invalid rules, each prefixed with where it is in the config.
+    fn rule_errors(&self) -> Vec<String> {
+        let mut rules: Vec<(String, &Rule)> = Vec::new();
+        if let Some(rule) = &self.defaults.target_match {
+            rules.push(("defaults.target_match".into(), rule));
+        }
+        for (i, rule) in self.exclude.iter().enumerate() {
+            rules.push((format!("exclude #{}", i + 1), rule));
+        }
+        for (i, pin) in self.pin.iter().enumerate() {
+            if let Some(rule) = &pin.rule {
+                rules.push((format!("pin #{}", i + 1), rule));
+            }
+        }
+        let mut errors: Vec<String> = rules
+            .into_iter()
+            .flat_map(|(place, rule)| {
+                rule.errors().into_iter().map(move |e| format!("{}: {}", place, e))
+            })
+            .collect();
+        for (i, pin) in self.pin.iter().enumerate() {
+            if let Some(Err(e)) = pin.slots.as_ref().map(SlotList::refs) {
+                errors.push(format!("pin #{}: {}", i + 1, e));
+            }
+            let Some(layout) = &pin.layout else {
+                continue;
+            };
+            let Some((preset, _)) = self.layout_named(layout) else {
+                errors.push(format!("pin #{}: unknown layout '{}'", i + 1, layout));
+                continue;
+            };
+            let names = self.slot_names(Some(layout), Some(&preset));
+            for r in pin.slot_refs() {
+                if let SlotRef::Name(name) = r {
+                    if !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                        errors.push(format!("pin #{}: layout '{}' has no slot '{}'", i + 1, layout, name));
+                    }
+                }
+            }
+        }
+        errors
+    }
 }
 
 impl CategoryOverrides {
@@ -182,31 +687,157 @@ impl CategoryOverrides {
 }
 
 impl PinRule {
-    /// Check if a window matches this pin rule.
+    /// Check if a window matches this pin rule. `process` and
+    /// `title_contains` match if either does; a rule with no conditions at
+    /// all matches nothing.
+    pub fn matches(&self, win: &ManagedWindow) -> bool {
+        let by_process = self
+            .process
+            .as_ref()
+            .is_some_and(|p| win.process_name.eq_ignore_ascii_case(p));
+        let by_title = self
+            .title_contains
+            .as_ref()
+            .is_some_and(|t| win.title.to_lowercase().contains(&t.to_lowercase()));
+        let legacy = self.process.is_some() || self.title_contains.is_some();
+        match &self.rule {
+            Some(rule) => (!legacy || by_process || by_title) && rule.matches(win),
+            None => by_process || by_title,
+        }
+    }
+
+    /// Whether the rule applies to `preset`, called `layout` in the picker
+    /// if it has a name there. The scope matches by name or as a preset:
+    /// `2x2 Grid` and `2x2` are the same layout, named or not.
+    pub fn applies_to(&self, layout: Option<&str>, preset: &LayoutPreset) -> bool {
+        let Some(scope) = &self.layout else {
+            return true;
+        };
+        layout.is_some_and(|name| scope.eq_ignore_ascii_case(name))
+            || preset.spec().is_some_and(|spec| scope_spec(scope) == Some(spec))
+    }
+
+    fn slot_refs(&self) -> Vec<SlotRef> {
+        match &self.slots {
+            Some(list) => list.refs().unwrap_or_default(),
+            None => vec![SlotRef::Index(self.slot)],
+        }
+    }
+
+    /// Slots the rule pins to, in the order they fill up, with names looked
+    /// up in `slot_names`. Names the layout doesn't have are left out, and
+    /// so is everything if `slots` doesn't parse.
+    pub fn slot_indices(&self, slot_names: &[String]) -> Vec<usize> {
+        self.slot_refs()
+            .into_iter()
+            .filter_map(|r| match r {
+                SlotRef::Index(i) => Some(i),
+                SlotRef::Name(name) => slot_names.iter().position(|n| n.eq_ignore_ascii_case(&name)),
+            })
+            .collect()
+    }
+
+    /// The rule's slots for display, numbered from 1 like the preview.
+    pub fn slots_label(&self) -> String {
+        let refs = self.slot_refs();
+        let labels: Vec<String> = refs
+            .iter()
+            .map(|r| match r {
+                SlotRef::Index(i) => (i + 1).to_string(),
+                SlotRef::Name(name) => name.clone(),
+            })
+            .collect();
+        match refs.len() {
+            1 => format!("Slot {}", labels[0]),
+            _ => format!("Slots {}", labels.join(",")),
+        }
+    }
+}
+
+/// The preset string of a pin scope written as a built-in picker label or
+/// as a preset string.
+fn scope_spec(scope: &str) -> Option<String> {
+    builtin_presets()
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(scope))
+        .map(|(_, preset)| preset)
+        .or_else(|| LayoutPreset::parse(scope))
+        .and_then(|preset| preset.spec())
+}
+
+impl SessionWindow {
+    /// Check if a window matches by process and title (ignoring ordinal).
     pub fn matches(&self, process_name: &str, title: &str) -> bool {
+        process_name.eq_ignore_ascii_case(&self.process)
+            && self
+                .title_contains
+                .as_ref()
+                .is_none_or(|t| title.to_lowercase().contains(&t.to_lowercase()))
+    }
+}
+
+impl LaunchApp {
+    /// Process name of the app's window, like `pwsh.exe` for `exe = "pwsh"`.
+    pub fn process_name(&self) -> String {
+        if let Some(process) = &self.process {
+            return process.clone();
+        }
+        let path = std::path::Path::new(&self.exe);
+        let name = path
+            .file_name()
+            .map_or_else(|| self.exe.clone(), |n| n.to_string_lossy().into_owned());
+        if cfg!(windows) && path.extension().is_none() {
+            format!("{}.exe", name)
+        } else {
+            name
+        }
+    }
+
+    pub fn matches(&self, process_name: &str, title: &str) -> bool {
+        process_name.eq_ignore_ascii_case(&self.process_name())
+            && self
+                .title_contains
+                .as_ref()
+                .is_none_or(|t| title.to_lowercase().contains(&t.to_lowercase()))
+    }
+}
+
+impl SizeConstraint {
+    /// Check if a window matches this constraint (by process or category).
+    pub fn matches(&self, process_name: &str, category: AppCategory) -> bool {
         if let Some(proc) = &self.process {
-            if process_name.to_lowercase() == proc.to_lowercase() {
+            if process_name.eq_ignore_ascii_case(proc) {
                 return true;
             }
         }
-        if let Some(substr) = &self.title_contains {
-            if title.to_lowercase().contains(&substr.to_lowercase()) {
+        if let Some(cat) = &self.category {
+            if category.display_name().eq_ignore_ascii_case(cat) {
                 return true;
             }
         }
         false
     }
+
+    pub fn limits(&self) -> SizeLimits {
+        SizeLimits {
+            min_w: self.min_width.unwrap_or(0).max(0),
+            min_h: self.min_height.unwrap_or(0).max(0),
+            max_w: self.max_width,
+            max_h: self.max_height,
+        }
+    }
 }
 
 pub fn load() -> Config {
     if let Some(path) = config_path() {
         if path.exists() {
             if let Ok(content) = std::fs::read_to_string(&path) {
-                if let Ok(config) = toml::from_str::<Config>(&content) {
-                    log::info!("Loaded config from {}", path.display());
-                    return config;
-                } else {
-                    log::warn!("Failed to parse config at {}", path.display());
+                match toml::from_str::<Config>(&content) {
+                    Ok(config) => {
+                        log::info!("Loaded config from {}", path.display());
+                        return checked(config);
+                    }
+                    Err(e) => log::warn!("Failed to parse config at {}: {}", path.display(), e),
                 }
             }
         }
@@ -218,7 +849,7 @@ pub fn load() -> Config {
         if let Ok(content) = std::fs::read_to_string(&cwd_path) {
             if let Ok(config) = toml::from_str::<Config>(&content) {
                 log::info!("Loaded config from CWD");
-                return config;
+                return checked(config);
             }
         }
     }
@@ -227,6 +858,16 @@ pub fn load() -> Config {
     Config::default()
 }
 
+/// Report invalid rules. They load anyway and never match, so one bad regex
+/// doesn't throw away the rest of the config.
+fn checked(mut config: Config) -> Config {
+    config.errors = config.rule_errors();
+    for error in &config.errors {
+        log::error!("Config: {}", error);
+    }
+    config
+}
+
 pub fn save(config: &Config) {
     if let Some(path) = config_path() {
         if let Some(parent) = path.parent() {
@@ -249,6 +890,11 @@ fn config_path() -> Option<PathBuf> {
     dirs::home_dir().map(|h| h.join(".powershellmanager").join("config.toml"))
 }
 
+/// Path to the slot memory file (last window-to-slot mapping).
+pub fn slot_memory_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".powershellmanager").join("slots.toml"))
+}
+
 /// Path to the activity database file.
 pub fn activity_path() -> Option<PathBuf> {
     dirs::home_dir().map(|h| h.join(".powershellmanager").join("activity.toml"))
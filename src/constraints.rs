@@ -0,0 +1,372 @@
+use crate::config::SizeConstraint;
+use crate::layout::{GridShape, LayoutPreset, Slot, Spacing};
+use crate::monitor::Rect;
+use crate::windows::ManagedWindow;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Slack for window borders and DPI rounding when comparing a window's
+/// actual size to the size it was given.
+const LEARN_TOLERANCE: i32 = 2;
+
+/// Size bounds for one window, in pixels. A zero minimum means unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SizeLimits {
+    pub min_w: i32,
+    pub min_h: i32,
+    pub max_w: Option<i32>,
+    pub max_h: Option<i32>,
+}
+
+impl SizeLimits {
+    pub fn fits(&self, slot: &Slot) -> bool {
+        slot.w >= self.min_w && slot.h >= self.min_h
+    }
+
+    /// The tighter of both limits.
+    fn merge(self, other: SizeLimits) -> SizeLimits {
+        let min_opt = |a: Option<i32>, b: Option<i32>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        SizeLimits {
+            min_w: self.min_w.max(other.min_w),
+            min_h: self.min_h.max(other.min_h),
+            max_w: min_opt(self.max_w, other.max_w),
+            max_h: min_opt(self.max_h, other.max_h),
+        }
+    }
+
+    /// Where the window goes inside its slot: shrunk to its maximum size and
+    /// centered. Windows below their minimum keep the slot size and are left
+    /// to grow themselves.
+    pub fn fit_into(&self, slot: &Slot) -> Slot {
+        let w = self.max_w.map_or(slot.w, |m| slot.w.min(m.max(1)));
+        let h = self.max_h.map_or(slot.h, |m| slot.h.min(m.max(1)));
+        Slot {
+            x: slot.x + (slot.w - w) / 2,
+            y: slot.y + (slot.h - h) / 2,
+            w,
+            h,
+        }
+    }
+}
+
+/// Minimum sizes observed at runtime, by lowercase process name. They live
+/// in memory only and are learned again after a restart.
+fn learned() -> &'static Mutex<HashMap<String, (i32, i32)>> {
+    static LEARNED: OnceLock<Mutex<HashMap<String, (i32, i32)>>> = OnceLock::new();
+    LEARNED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Record a window that ended up bigger than the slot it was moved into,
+/// i.e. one that refuses to shrink below its current size.
+pub fn learn(process_name: &str, requested: &Slot, actual: &Rect) {
+    let grew_w = actual.w > requested.w + LEARN_TOLERANCE;
+    let grew_h = actual.h > requested.h + LEARN_TOLERANCE;
+    if !grew_w && !grew_h {
+        return;
+    }
+    if let Ok(mut map) = learned().lock() {
+        let entry = map.entry(process_name.to_lowercase()).or_insert((0, 0));
+        if grew_w {
+            entry.0 = entry.0.max(actual.w);
+        }
+        if grew_h {
+            entry.1 = entry.1.max(actual.h);
+        }
+        log::info!(
+            "Learned minimum size {}x{} for {}",
+            entry.0,
+            entry.1,
+            process_name
+        );
+    }
+}
+
+/// Limits for a window: every matching config rule plus anything learned.
+pub fn limits_for(win: &ManagedWindow, rules: &[SizeConstraint]) -> SizeLimits {
+    let mut limits = SizeLimits::default();
+    for rule in rules {
+        if rule.matches(&win.process_name, win.category) {
+            limits = limits.merge(rule.limits());
+        }
+    }
+    if let Ok(map) = learned().lock() {
+        if let Some(&(min_w, min_h)) = map.get(&win.process_name.to_lowercase()) {
+            limits = limits.merge(SizeLimits {
+                min_w,
+                min_h,
+                ..Default::default()
+            });
+        }
+    }
+    limits
+}
+
+/// New weights so every slot is at least its minimum size, taking space from
+/// the slots that have room in proportion to their weights. `usable` is the
+/// space left after gaps. Returns `None` if nothing needs to change or the
+/// minimums can't all fit.
+pub fn rebalance_weights(weights: &[f32], usable: i32, mins: &[i32]) -> Option<Vec<f32>> {
+    let n = mins.len();
+    if n == 0 || usable <= 0 || mins.iter().sum::<i32>() > usable {
+        return None;
+    }
+    let base: Vec<f32> = if weights.len() == n && weights.iter().all(|w| *w > 0.0) {
+        weights.to_vec()
+    } else {
+        vec![1.0; n]
+    };
+
+    // Repeatedly pin undersized slots to their minimum and share the rest
+    let mut fixed: Vec<Option<i32>> = vec![None; n];
+    loop {
+        let free_space = usable - fixed.iter().flatten().sum::<i32>();
+        let free_weight: f32 = (0..n).filter(|&i| fixed[i].is_none()).map(|i| base[i]).sum();
+        let mut changed = false;
+        for i in 0..n {
+            if fixed[i].is_none() && free_space as f32 * base[i] / free_weight < mins[i] as f32 {
+                fixed[i] = Some(mins[i]);
+                changed = true;
+            }
+        }
+        if !changed || fixed.iter().all(|f| f.is_some()) {
+            break;
+        }
+    }
+    if fixed.iter().all(|f| f.is_none()) {
+        return None;
+    }
+
+    let free_space = usable - fixed.iter().flatten().sum::<i32>();
+    let free_weight: f32 = (0..n).filter(|&i| fixed[i].is_none()).map(|i| base[i]).sum();
+    let sizes: Vec<f32> = (0..n)
+        .map(|i| match fixed[i] {
+            Some(min) => min as f32,
+            None => free_space as f32 * base[i] / free_weight,
+        })
+        .collect();
+    Some(sizes.iter().map(|s| s / usable as f32).collect())
+}
+
+/// Adjust a preset's weights so the windows placed in it get at least their
+/// minimum size. `slot_mins` holds (min width, min height) for every slot of
+/// `slots` (zero for empty slots). Grids rebalance columns and rows (unless
+/// cells are merged); column, row, two-pane and main/side presets rebalance
+/// their weighted slots, and main/side presets also move the main/side split.
+/// BSP, spiral, center-main, template and free layouts have no weights and
+/// are left as they are, so a window there may still outgrow its slot.
+/// Returns the adjusted preset and shape, or `None` when no change is
+/// needed or possible.
+pub fn rebalance_shape(
+    preset: &LayoutPreset,
+    shape: Option<&GridShape>,
+    area: &Rect,
+    spacing: &Spacing,
+    slots: &[Slot],
+    slot_mins: &[(i32, i32)],
+) -> Option<(LayoutPreset, GridShape)> {
+    let spacing = shape.and_then(|s| s.spacing.as_ref()).unwrap_or(spacing);
+    let area = spacing.margin.inset(area);
+    let mut new_shape = shape.cloned().unwrap_or_default();
+
+    match preset {
+        LayoutPreset::Grid { cols, rows } => {
+            if !new_shape.merges.is_empty() {
+                return None;
+            }
+            let (cols, rows) = (*cols as usize, *rows as usize);
+            if slot_mins.len() != cols * rows {
+                return None;
+            }
+            let col_mins: Vec<i32> = (0..cols)
+                .map(|c| (0..rows).map(|r| slot_mins[r * cols + c].0).max().unwrap_or(0))
+                .collect();
+            let row_mins: Vec<i32> = (0..rows)
+                .map(|r| (0..cols).map(|c| slot_mins[r * cols + c].1).max().unwrap_or(0))
+                .collect();
+            let usable_w = area.w - spacing.gap_x * (cols as i32 - 1);
+            let usable_h = area.h - spacing.gap_y * (rows as i32 - 1);
+            let new_cols = rebalance_weights(&new_shape.col_weights, usable_w, &col_mins);
+            let new_rows = rebalance_weights(&new_shape.row_weights, usable_h, &row_mins);
+            if new_cols.is_none() && new_rows.is_none() {
+                return None;
+            }
+            if let Some(w) = new_cols {
+                new_shape.col_weights = w;
+            }
+            if let Some(w) = new_rows {
+                new_shape.row_weights = w;
+            }
+            Some((preset.clone(), new_shape))
+        }
+        _ => {
+            let n = preset.weight_count();
+            if n == 0 {
+                if slot_mins.iter().any(|&(w, h)| w > 0 || h > 0) {
+                    log::info!(
+                        "Size constraints can't resize the slots of {} layouts",
+                        preset.display_name()
+                    );
+                }
+                return None;
+            }
+            if slots.len() < n || slot_mins.len() != slots.len() {
+                return None;
+            }
+            let vertical = preset.weights_stack_vertically();
+            let first = slots.len() - n;
+            let (start, end) = if vertical {
+                (slots[first].y, slots[slots.len() - 1].y + slots[slots.len() - 1].h)
+            } else {
+                (slots[first].x, slots[slots.len() - 1].x + slots[slots.len() - 1].w)
+            };
+            let gap = if vertical { spacing.gap_y } else { spacing.gap_x };
+            let usable = end - start - gap * (n as i32 - 1);
+            let mins: Vec<i32> = slot_mins[first..]
+                .iter()
+                .map(|&(w, h)| if vertical { h } else { w })
+                .collect();
+            let new_weights = rebalance_weights(&new_shape.weights, usable, &mins);
+            let new_preset = rebalance_main_ratio(preset, &area, spacing, slot_mins);
+            if new_weights.is_none() && new_preset.is_none() {
+                return None;
+            }
+            if let Some(w) = new_weights {
+                new_shape.weights = w;
+            }
+            Some((new_preset.unwrap_or_else(|| preset.clone()), new_shape))
+        }
+    }
+}
+
+/// A main/side preset with its split moved so the main slot (slot 0) and
+/// the side stack each get their widest minimum across the split, or `None`
+/// if the split already fits or can't.
+fn rebalance_main_ratio(
+    preset: &LayoutPreset,
+    area: &Rect,
+    spacing: &Spacing,
+    slot_mins: &[(i32, i32)],
+) -> Option<LayoutPreset> {
+    let LayoutPreset::MainSide { ratio, side, .. } = preset else {
+        return None;
+    };
+    let across = |&(w, h): &(i32, i32)| if side.is_horizontal() { h } else { w };
+    let main_min = across(slot_mins.first()?);
+    let side_min = slot_mins[1..].iter().map(across).max().unwrap_or(0);
+    let usable = if side.is_horizontal() {
+        area.h - spacing.gap_y
+    } else {
+        area.w - spacing.gap_x
+    };
+    let split = rebalance_weights(&[*ratio, 1.0 - *ratio], usable, &[main_min, side_min])?;
+    Some(preset.with_main_ratio(split[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::SidePosition;
+
+    fn assert_weights(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}");
+        }
+    }
+
+    fn area() -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            w: 1000,
+            h: 800,
+        }
+    }
+
+    #[test]
+    fn a_minimum_takes_space_from_its_neighbours() {
+        let weights = rebalance_weights(&[1.0, 1.0, 1.0], 900, &[0, 500, 0]).unwrap();
+        assert_weights(&weights, &[200.0 / 900.0, 500.0 / 900.0, 200.0 / 900.0]);
+        // Neighbours give space in proportion to their weights
+        let weights = rebalance_weights(&[2.0, 1.0, 1.0], 1000, &[0, 0, 600]).unwrap();
+        assert_weights(&weights, &[0.4 * 2.0 / 3.0, 0.4 / 3.0, 0.6]);
+    }
+
+    #[test]
+    fn nothing_changes_when_minimums_fit_or_cannot() {
+        assert_eq!(rebalance_weights(&[1.0, 1.0], 1000, &[400, 400]), None);
+        assert_eq!(rebalance_weights(&[1.0, 1.0], 1000, &[600, 500]), None);
+        assert_eq!(rebalance_weights(&[1.0, 1.0], 0, &[0, 0]), None);
+        assert_eq!(rebalance_weights(&[], 1000, &[]), None);
+    }
+
+    #[test]
+    fn missing_or_non_positive_weights_count_as_even() {
+        for weights in [&[][..], &[1.0][..], &[1.0, 0.0][..], &[-1.0, 3.0][..]] {
+            let rebalanced = rebalance_weights(weights, 1000, &[700, 0]).unwrap();
+            assert_weights(&rebalanced, &[0.7, 0.3]);
+        }
+    }
+
+    #[test]
+    fn grids_take_the_widest_minimum_of_each_column_and_row() {
+        let preset = LayoutPreset::Grid { cols: 2, rows: 2 };
+        let spacing = Spacing::default();
+        let slots = preset.compute_slots(&area(), &spacing);
+        let mins = [(0, 0), (700, 0), (300, 600), (650, 100)];
+        let (new_preset, shape) =
+            rebalance_shape(&preset, None, &area(), &spacing, &slots, &mins).unwrap();
+        assert_eq!(new_preset, preset);
+        assert_weights(&shape.col_weights, &[0.3, 0.7]);
+        assert_weights(&shape.row_weights, &[0.25, 0.75]);
+
+        // Merged cells keep the grid as it is
+        let merged = GridShape {
+            merges: vec![crate::layout::CellSpan {
+                col: 0,
+                row: 0,
+                col_span: 2,
+                row_span: 1,
+            }],
+            ..Default::default()
+        };
+        let shape = Some(&merged);
+        assert!(rebalance_shape(&preset, shape, &area(), &spacing, &slots, &mins).is_none());
+    }
+
+    #[test]
+    fn main_side_moves_the_split() {
+        let preset = LayoutPreset::MainSide {
+            side_count: 2,
+            ratio: 0.6,
+            side: SidePosition::Right,
+        };
+        let spacing = Spacing::default();
+        let slots = preset.compute_slots(&area(), &spacing);
+        let mins = [(800, 0), (0, 0), (0, 0)];
+        let (new_preset, shape) =
+            rebalance_shape(&preset, None, &area(), &spacing, &slots, &mins).unwrap();
+        let LayoutPreset::MainSide { ratio, .. } = new_preset else {
+            panic!("{new_preset:?}");
+        };
+        assert!((ratio - 0.8).abs() < 1e-4, "{ratio}");
+        assert!(shape.weights.is_empty());
+
+        // The side stack's tallest minimum rebalances the side slots
+        let mins = [(0, 0), (0, 0), (0, 600)];
+        let (_, shape) = rebalance_shape(&preset, None, &area(), &spacing, &slots, &mins).unwrap();
+        assert_weights(&shape.weights, &[0.25, 0.75]);
+    }
+
+    #[test]
+    fn layouts_without_weights_are_left_alone() {
+        let preset = LayoutPreset::Bsp(Some(3));
+        let spacing = Spacing::default();
+        let slots = preset.compute_slots(&area(), &spacing);
+        let mins = [(900, 0), (0, 0), (0, 0)];
+        assert!(rebalance_shape(&preset, None, &area(), &spacing, &slots, &mins).is_none());
+    }
+}
@@ -1,8 +1,11 @@
-use crate::app::{DividerAxis, PsmApp};
-use crate::config;
+use crate::app::{DividerAxis, FreeDrag, PsmApp};
+use crate::history;
+use crate::layout::{
+    CellSpan, DEFAULT_MAIN_RATIO, LayoutPreset, Margins, RelRect, SidePosition, Spacing,
+};
 use crate::monitor::Rect;
 use crate::theme::{Theme, THEMES};
-use crate::windows;
+use crate::watch::ClosePolicy;
 
 
 static ICON_PNG: &[u8] = include_bytes!("../assets/tront-icon.png");
@@ -34,6 +37,15 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
             }
             ui.separator();
 
+            // Rules that failed to load never match, so say which ones
+            if !app.config.errors.is_empty() {
+                let error_color = ui.visuals().error_fg_color;
+                for error in &app.config.errors {
+                    ui.colored_label(error_color, error);
+                }
+                ui.separator();
+            }
+
             // Target mode toggle
             ui.horizontal(|ui| {
                 ui.label("Mode:");
@@ -52,7 +64,7 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
 
                 if target_str != app.config.defaults.target {
                     app.config.defaults.target = target_str;
-                    config::save(&app.config);
+                    app.save_config();
                     app.refresh_windows();
                 }
 
@@ -60,7 +72,16 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                     let mut smart = app.config.defaults.smart_sort;
                     if ui.checkbox(&mut smart, "Smart Sort").changed() {
                         app.config.defaults.smart_sort = smart;
-                        config::save(&app.config);
+                        app.save_config();
+                    }
+                    let mut watch = app.config.defaults.watch;
+                    if ui
+                        .checkbox(&mut watch, "Auto-arrange")
+                        .on_hover_text("Keep the layout live: new windows snap into free slots")
+                        .changed()
+                    {
+                        app.config.defaults.watch = watch;
+                        app.save_config();
                     }
                 });
             });
@@ -77,14 +98,14 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                     if !app.managed_windows.is_empty() {
                         if ui.small_button(if narrow { "Min" } else { "Minimize All" }).clicked() {
                             for win in &app.managed_windows {
-                                windows::minimize_window(win.hwnd);
+                                app.backend.minimize(win.hwnd);
                             }
                         }
                         if ui.small_button(if narrow { "Rst" } else { "Restore All" }).clicked() {
                             for win in &app.managed_windows {
-                                windows::restore_window(win.hwnd);
+                                app.backend.restore(win.hwnd);
                             }
-                            windows::focus_window(app.app_hwnd);
+                            app.backend.focus(app.app_hwnd);
                         }
                     }
                 });
@@ -97,7 +118,9 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                 .max_height(list_height)
                 .show(ui, |ui| {
                     // Clone to avoid borrow issues with button clicks
-                    let wins: Vec<_> = app.managed_windows.iter().cloned().collect();
+                    let wins: Vec<_> = app.managed_windows.to_vec();
+                    let layout_name = app.layout_name();
+                    let preset = app.active_preset();
                     let scores = if app.config.defaults.smart_sort {
                         app.activity.score_windows(&wins)
                     } else {
@@ -107,34 +130,27 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                     for (i, win) in wins.iter().enumerate() {
                         ui.horizontal(|ui| {
                             if ui.small_button("Focus").clicked() {
-                                windows::focus_window(win.hwnd);
+                                app.backend.focus(win.hwnd);
                             }
 
                             // Pin button
                             let is_pinned = app.config.pin.iter().any(|p| {
-                                p.matches(&win.process_name, &win.title)
+                                p.applies_to(layout_name.as_deref(), &preset) && p.matches(win)
                             });
                             let pin_label = if is_pinned { "Unpin" } else { "Pin" };
-                            if ui.small_button(pin_label).clicked() {
+                            // The freeform editor has no cells to click
+                            let can_pin = is_pinned || !app.use_free;
+                            if ui.add_enabled(can_pin, egui::Button::new(pin_label).small()).clicked() {
                                 if is_pinned {
                                     // Remove matching pin rule
                                     app.config.pin.retain(|p| {
-                                        !p.matches(&win.process_name, &win.title)
+                                        !(p.applies_to(layout_name.as_deref(), &preset) && p.matches(win))
                                     });
+                                    app.save_config();
                                 } else {
-                                    // Add pin rule for this process at slot 0 (or next available)
-                                    let next_slot = if app.config.pin.is_empty() {
-                                        0
-                                    } else {
-                                        app.config.pin.iter().map(|p| p.slot).max().unwrap_or(0) + 1
-                                    };
-                                    app.config.pin.push(config::PinRule {
-                                        process: Some(win.process_name.clone()),
-                                        title_contains: None,
-                                        slot: next_slot,
-                                    });
+                                    // The slot is picked by clicking a preview cell
+                                    app.pinning = Some(win.process_name.clone());
                                 }
-                                config::save(&app.config);
                             }
 
                             if win.is_minimized {
@@ -174,10 +190,20 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
             ui.separator();
 
             // Layout mode toggle
-            let old_use_custom = app.use_custom;
+            let old_mode = (app.use_custom, app.use_free);
             ui.horizontal(|ui| {
-                ui.selectable_value(&mut app.use_custom, false, "Preset");
-                ui.selectable_value(&mut app.use_custom, true, "Custom Grid");
+                if ui.selectable_label(!app.use_custom && !app.use_free, "Preset").clicked() {
+                    app.use_custom = false;
+                    app.use_free = false;
+                }
+                if ui.selectable_label(app.use_custom, "Custom Grid").clicked() {
+                    app.use_custom = true;
+                    app.use_free = false;
+                }
+                if ui.selectable_label(app.use_free, "Freeform").clicked() {
+                    app.use_custom = false;
+                    app.use_free = true;
+                }
             });
 
             ui.add_space(4.0);
@@ -193,23 +219,35 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
 
                     if app.custom_cols != old_cols || app.custom_rows != old_rows {
                         app.disabled_cells.clear();
+                        app.merged_cells.clear();
+                        app.config.defaults.merged_cells.clear();
                         app.col_weights = vec![1.0 / app.custom_cols as f32; app.custom_cols as usize];
                         app.row_weights = vec![1.0 / app.custom_rows as f32; app.custom_rows as usize];
                         app.config.defaults.custom_cols = app.custom_cols;
                         app.config.defaults.custom_rows = app.custom_rows;
                         app.config.defaults.col_weights = app.col_weights.clone();
                         app.config.defaults.row_weights = app.row_weights.clone();
-                        config::save(&app.config);
+                        app.save_config();
                     }
 
-                    if !app.weights_are_uniform() {
-                        if ui.small_button("Reset Sizes").clicked() {
-                            app.col_weights = vec![1.0 / app.custom_cols as f32; app.custom_cols as usize];
-                            app.row_weights = vec![1.0 / app.custom_rows as f32; app.custom_rows as usize];
-                            app.config.defaults.col_weights = app.col_weights.clone();
-                            app.config.defaults.row_weights = app.row_weights.clone();
-                            config::save(&app.config);
-                        }
+                    if !app.weights_are_uniform() && ui.small_button("Reset Sizes").clicked() {
+                        app.col_weights = vec![1.0 / app.custom_cols as f32; app.custom_cols as usize];
+                        app.row_weights = vec![1.0 / app.custom_rows as f32; app.custom_rows as usize];
+                        app.config.defaults.col_weights = app.col_weights.clone();
+                        app.config.defaults.row_weights = app.row_weights.clone();
+                        app.save_config();
+                    }
+                });
+            } else if app.use_free {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        theme.text_muted,
+                        format!("{} panes", app.free_rects.len()),
+                    );
+                    if !app.free_rects.is_empty() && ui.small_button("Clear").clicked() {
+                        app.free_rects.clear();
+                        app.disabled_cells.clear();
+                        app.save_free_rects();
                     }
                 });
             } else {
@@ -218,14 +256,14 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                     let current_name = app
                         .presets
                         .get(app.selected_preset)
-                        .map(|(n, _)| n.as_str())
+                        .map(|(n, _, _)| n.as_str())
                         .unwrap_or("None");
 
                     let old_preset = app.selected_preset;
                     egui::ComboBox::from_id_salt("layout_picker")
                         .selected_text(current_name)
                         .show_ui(ui, |ui| {
-                            for (i, (name, _)) in app.presets.iter().enumerate() {
+                            for (i, (name, _, _)) in app.presets.iter().enumerate() {
                                 ui.selectable_value(&mut app.selected_preset, i, name);
                             }
                         });
@@ -233,39 +271,73 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                     if app.selected_preset != old_preset {
                         app.disabled_cells.clear();
                         app.config.defaults.selected_preset = app.selected_preset;
-                        config::save(&app.config);
+                        app.config.defaults.main_ratio = None;
+                        app.config.defaults.slot_weights.clear();
+                        app.save_config();
+                    }
+
+                    if !app.config.defaults.slot_weights.is_empty()
+                        && ui.small_button("Reset Sizes").clicked()
+                    {
+                        app.config.defaults.slot_weights.clear();
+                        app.save_config();
                     }
                 });
             }
 
-            if app.use_custom != old_use_custom {
+            if (app.use_custom, app.use_free) != old_mode {
+                app.disabled_cells.clear();
                 app.config.defaults.use_custom = app.use_custom;
-                config::save(&app.config);
+                app.config.defaults.use_free = app.use_free;
+                app.save_config();
             }
 
             ui.horizontal(|ui| {
                 if ui.button("Apply").clicked() {
                     app.apply_current_layout();
                 }
+                if ui
+                    .add_enabled(history::can_undo(), egui::Button::new("Undo"))
+                    .on_hover_text("Put windows back where they were before the last arrangement")
+                    .clicked()
+                {
+                    app.undo_arrangement();
+                }
+                if ui
+                    .add_enabled(history::can_redo(), egui::Button::new("Redo"))
+                    .on_hover_text("Reapply the arrangement that was undone")
+                    .clicked()
+                {
+                    app.redo_arrangement();
+                }
                 if ui.button("Refresh").clicked() {
                     app.refresh_windows();
                 }
-                if app.use_custom {
-                    if ui.button("Save Grid").clicked() {
+                let preset_tuned = app.config.defaults.main_ratio.is_some()
+                    || !app.config.defaults.slot_weights.is_empty();
+                if app.use_custom || app.use_free || preset_tuned {
+                    let label = if app.use_free {
+                        "Save Layout"
+                    } else if app.use_custom {
+                        "Save Grid"
+                    } else {
+                        "Save Preset"
+                    };
+                    if ui.button(label).clicked() {
                         app.show_save_dialog = !app.show_save_dialog;
                         if app.show_save_dialog {
                             app.save_grid_name.clear();
                         }
                     }
                 }
-                let enabled_count =
-                    app.active_preset().slot_count() - app.disabled_cells.len();
+                let slot_count = app.slot_count();
+                let enabled_count = slot_count.saturating_sub(app.disabled_cells.len());
                 ui.colored_label(
                     theme.text_muted,
                     format!(
                         "{} enabled / {} total slots",
                         enabled_count,
-                        app.active_preset().slot_count()
+                        slot_count
                     ),
                 );
             });
@@ -279,7 +351,11 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                     let can_save = !app.save_grid_name.trim().is_empty();
                     if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() || (enter && can_save) {
                         let name = app.save_grid_name.trim().to_string();
-                        app.save_current_as_grid(name);
+                        if app.use_free {
+                            app.save_current_as_free_layout(name);
+                        } else {
+                            app.save_current_as_grid(name);
+                        }
                         app.show_save_dialog = false;
                     }
                     if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
@@ -299,16 +375,24 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                             app.load_saved_grid(sg);
                         }
                         ui.label(&sg.name);
-                        ui.colored_label(
-                            theme.text_muted,
-                            format!("{}x{}", sg.cols, sg.rows),
-                        );
+                        let kind = match sg.to_preset() {
+                            Some(LayoutPreset::Grid { cols, rows }) => format!("{}x{}", cols, rows),
+                            Some(preset) => preset.display_name(),
+                            None => "invalid".to_string(),
+                        };
+                        ui.colored_label(theme.text_muted, kind);
                         if !sg.disabled_cells.is_empty() {
                             ui.colored_label(
                                 theme.text_muted,
                                 format!("({} disabled)", sg.disabled_cells.len()),
                             );
                         }
+                        if !sg.merged.is_empty() {
+                            ui.colored_label(
+                                theme.text_muted,
+                                format!("({} merged)", sg.merged.len()),
+                            );
+                        }
                         if ui.small_button("X").clicked() {
                             app.delete_saved_grid(&sg.name.clone());
                         }
@@ -316,20 +400,147 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                 }
             }
 
+            // Saved free layouts list
+            if !app.config.free_layout.is_empty() {
+                ui.add_space(2.0);
+                ui.colored_label(theme.text_muted, "Saved free layouts:");
+                let layouts: Vec<_> = app.config.free_layout.clone();
+                for fl in &layouts {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Load").clicked() {
+                            app.load_free_layout(fl);
+                        }
+                        ui.label(&fl.name);
+                        ui.colored_label(
+                            theme.text_muted,
+                            format!("{} panes", fl.rects.len()),
+                        );
+                        if ui.small_button("X").clicked() {
+                            app.delete_free_layout(&fl.name.clone());
+                        }
+                    });
+                }
+            }
+
+            // Sessions: concrete window placements saved by name
+            ui.add_space(2.0);
+            ui.horizontal(|ui| {
+                ui.colored_label(theme.text_muted, "Sessions:");
+                let resp = ui.add(
+                    egui::TextEdit::singleline(&mut app.session_name)
+                        .hint_text("name")
+                        .desired_width(100.0),
+                );
+                let enter = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let can_save = !app.session_name.trim().is_empty();
+                if ui
+                    .add_enabled(can_save, egui::Button::new("Save Session"))
+                    .on_hover_text("Remember where every window is now")
+                    .clicked()
+                    || (enter && can_save)
+                {
+                    let name = app.session_name.trim().to_string();
+                    app.save_session(name);
+                    app.session_name.clear();
+                }
+            });
+            let sessions: Vec<_> = app.config.session.clone();
+            for session in &sessions {
+                ui.horizontal(|ui| {
+                    if ui.small_button("Restore").clicked() {
+                        app.restore_session(session);
+                    }
+                    ui.label(&session.name);
+                    ui.colored_label(
+                        theme.text_muted,
+                        format!("{} windows", session.window.len()),
+                    );
+                    if ui.small_button("X").clicked() {
+                        app.delete_session(&session.name);
+                    }
+                });
+            }
+
+            // Launch profiles (edited in the config file)
+            if !app.config.profile.is_empty() {
+                ui.add_space(2.0);
+                ui.colored_label(theme.text_muted, "Launch profiles:");
+                let profiles: Vec<_> = app.config.profile.clone();
+                for profile in &profiles {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button("Launch")
+                            .on_hover_text("Start missing apps, then arrange them")
+                            .clicked()
+                        {
+                            app.launch_profile(profile);
+                        }
+                        ui.label(&profile.name);
+                        ui.colored_label(
+                            theme.text_muted,
+                            format!("{} apps, {}", profile.app.len(), profile.layout),
+                        );
+                    });
+                }
+            }
+
             ui.separator();
 
             // Interactive layout preview
             app.ensure_weights();
-            ui.label("Preview (click cells to toggle):");
-            let action = draw_interactive_preview(ui, ctx, app, &theme);
-            match action {
-                PreviewAction::ToggleCell(cell_idx) => {
-                    app.toggle_cell(cell_idx);
+            if app.use_custom {
+                ui.label("Preview (click cells to toggle, shift-drag to merge):");
+            } else if app.use_free {
+                ui.label("Editor (drag to draw, move or resize; right-click to delete):");
+            } else {
+                ui.label("Preview (click cells to toggle):");
+            }
+            if let Some(process) = &app.pinning {
+                let mut cancel = false;
+                ui.horizontal(|ui| {
+                    ui.colored_label(theme.accent2, format!("Click a cell to pin {} to it", process));
+                    cancel = ui.small_button("Cancel").clicked()
+                        || ui.input(|i| i.key_pressed(egui::Key::Escape));
+                });
+                if cancel || app.use_free {
+                    app.pinning = None;
                 }
+            }
+            let action = if app.use_free {
+                draw_free_editor(ui, ctx, app, &theme)
+            } else {
+                draw_interactive_preview(ui, ctx, app, &theme)
+            };
+            match action {
+                PreviewAction::ToggleCell(cell_idx) => match app.pinning.take() {
+                    Some(process) => {
+                        if !app.pin_to_cell(process.clone(), cell_idx) {
+                            app.pinning = Some(process);
+                        }
+                    }
+                    None => app.toggle_cell(cell_idx),
+                },
                 PreviewAction::WeightsChanged => {
                     app.config.defaults.col_weights = app.col_weights.clone();
                     app.config.defaults.row_weights = app.row_weights.clone();
-                    config::save(&app.config);
+                    app.save_config();
+                }
+                PreviewAction::RatioChanged | PreviewAction::SlotWeightsChanged => {
+                    app.save_config();
+                }
+                PreviewAction::FreeRectsChanged => {
+                    app.save_free_rects();
+                }
+                PreviewAction::MergeCells(span) => {
+                    // New merge replaces any it overlaps; a single-cell span just splits
+                    let mut merges: Vec<_> = app
+                        .merged_cells
+                        .iter()
+                        .copied()
+                        .filter(|m| !m.overlaps(&span))
+                        .collect();
+                    merges.push(span);
+                    app.set_merged_cells(merges);
                 }
                 PreviewAction::None => {}
             }
@@ -403,12 +614,53 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                         ui.label("Monitor:");
                         ui.colored_label(theme.text_muted, &app.config.defaults.monitor);
                     });
+                    let spacing = app.config.defaults.spacing();
                     ui.horizontal(|ui| {
                         ui.label("Gap:");
-                        ui.colored_label(
-                            theme.text_muted,
-                            format!("{}px", app.config.defaults.gap),
-                        );
+                        let gap = if spacing.gap_x == spacing.gap_y {
+                            format!("{}px", spacing.gap_x)
+                        } else {
+                            format!("{}px horizontal, {}px vertical", spacing.gap_x, spacing.gap_y)
+                        };
+                        ui.colored_label(theme.text_muted, gap);
+                    });
+                    if spacing.margin != Margins::default() {
+                        let m = spacing.margin;
+                        ui.horizontal(|ui| {
+                            ui.label("Margins:");
+                            ui.colored_label(
+                                theme.text_muted,
+                                format!(
+                                    "top {} / right {} / bottom {} / left {}",
+                                    m.top, m.right, m.bottom, m.left
+                                ),
+                            );
+                        });
+                    }
+                    let mut persist = app.config.defaults.persist_slot_memory;
+                    if ui
+                        .checkbox(&mut persist, "Remember slots across restarts")
+                        .changed()
+                    {
+                        app.config.defaults.persist_slot_memory = persist;
+                        app.save_config();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("When a window closes:");
+                        let current = app.config.defaults.watch_close_policy;
+                        egui::ComboBox::from_id_salt("close_policy_picker")
+                            .selected_text(current.display_name())
+                            .show_ui(ui, |ui| {
+                                for policy in ClosePolicy::ALL {
+                                    if ui
+                                        .selectable_label(current == policy, policy.display_name())
+                                        .clicked()
+                                    {
+                                        app.config.defaults.watch_close_policy = policy;
+                                        app.save_config();
+                                    }
+                                }
+                            });
                     });
 
                     // Pinned windows
@@ -418,13 +670,25 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                         let mut to_remove = Vec::new();
                         for (i, rule) in app.config.pin.iter().enumerate() {
                             ui.horizontal(|ui| {
-                                ui.colored_label(theme.accent, format!("Slot {}:", rule.slot));
+                                ui.colored_label(theme.accent, format!("{}:", rule.slots_label()));
                                 if let Some(proc) = &rule.process {
                                     ui.monospace(proc);
                                 }
                                 if let Some(title) = &rule.title_contains {
                                     ui.colored_label(theme.text_muted, format!("\"{}\"", title));
                                 }
+                                if rule.rule.is_some() {
+                                    ui.colored_label(theme.text_muted, "(rule)");
+                                }
+                                if rule.priority != 0 {
+                                    ui.colored_label(theme.text_muted, format!("priority {}", rule.priority));
+                                }
+                                if let Some(max) = rule.max_windows {
+                                    ui.colored_label(theme.text_muted, format!("max {}", max));
+                                }
+                                if let Some(layout) = &rule.layout {
+                                    ui.colored_label(theme.text_muted, format!("in {}", layout));
+                                }
                                 if ui.small_button("X").clicked() {
                                     to_remove.push(i);
                                 }
@@ -434,13 +698,13 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                             for i in to_remove.into_iter().rev() {
                                 app.config.pin.remove(i);
                             }
-                            config::save(&app.config);
+                            app.save_config();
                         }
                     }
                 });
             if settings_resp.header_response.clicked() {
                 app.config.defaults.settings_open = !app.config.defaults.settings_open;
-                config::save(&app.config);
+                app.save_config();
             }
 
             // About
@@ -486,7 +750,7 @@ pub fn draw(ctx: &egui::Context, app: &mut PsmApp) {
                 });
             if about_resp.header_response.clicked() {
                 app.config.defaults.about_open = !app.config.defaults.about_open;
-                config::save(&app.config);
+                app.save_config();
             }
         });
     });
@@ -506,6 +770,10 @@ enum PreviewAction {
     None,
     ToggleCell(usize),
     WeightsChanged,
+    RatioChanged,
+    SlotWeightsChanged,
+    MergeCells(CellSpan),
+    FreeRectsChanged,
 }
 
 impl PreviewAction {
@@ -514,14 +782,17 @@ impl PreviewAction {
     }
 }
 
-/// Draws interactive preview with optional draggable dividers (custom grid mode).
+/// Draws interactive preview with optional draggable dividers (custom grid
+/// mode, or the main/side split of a main/side preset).
 fn draw_interactive_preview(
     ui: &mut egui::Ui,
     ctx: &egui::Context,
     app: &mut PsmApp,
     theme: &Theme,
 ) -> PreviewAction {
-    let preset = app.active_preset();
+    // BSP shows a slot for every window it would arrange
+    let preset = app.active_preset().sized_for(app.managed_windows.len());
+    let slot_names = app.config.slot_names(app.layout_name().as_deref(), Some(&preset));
     let window_count = app.managed_windows.len();
     let show_dividers = app.use_custom && app.custom_cols > 0 && app.custom_rows > 0;
     let non_uniform = show_dividers && !app.weights_are_uniform();
@@ -552,20 +823,61 @@ fn draw_interactive_preview(
 
     // Compute slots using weights for custom grid, or preset for non-custom
     let area = Rect { x: 0, y: 0, w: 1920, h: 1080 };
-    let gap_virtual = 12;
+    let mut shape = app.grid_shape();
+    let layout_spacing = shape
+        .as_mut()
+        .and_then(|s| s.spacing.take())
+        .unwrap_or_else(|| app.config.defaults.spacing());
+    let spacing = preview_spacing(&layout_spacing);
 
     let slots = if show_dividers {
         crate::layout::compute_weighted_grid(
-            app.custom_cols, app.custom_rows, &area, gap_virtual,
-            &app.col_weights, &app.row_weights,
+            app.custom_cols, app.custom_rows, &area, &spacing,
+            &app.col_weights, &app.row_weights, &app.merged_cells,
         )
     } else {
-        preset.compute_slots(&area, gap_virtual)
+        crate::layout::compute_shaped_slots(&preset, &area, &spacing, shape.as_ref())
+    };
+    // Top-left cell behind each slot (custom grid only, for merges and labels)
+    let slot_cells = if show_dividers {
+        crate::layout::grid_slot_cells(app.custom_cols, app.custom_rows, &app.merged_cells)
+    } else {
+        Vec::new()
     };
 
     let scale_x = inner_w / area.w as f32;
     let scale_y = inner_h / area.h as f32;
 
+    // Unmerged cells, for divider positions and shift-drag merge hit testing
+    let cells = if show_dividers {
+        crate::layout::compute_weighted_grid(
+            app.custom_cols, app.custom_rows, &area, &spacing,
+            &app.col_weights, &app.row_weights, &[],
+        )
+    } else {
+        Vec::new()
+    };
+    let cell_rects: Vec<egui::Rect> = cells
+        .iter()
+        .map(|c| {
+            egui::Rect::from_min_size(
+                offset + egui::vec2(c.x as f32 * scale_x, c.y as f32 * scale_y),
+                egui::vec2(c.w as f32 * scale_x, c.h as f32 * scale_y),
+            )
+            .expand2(egui::vec2(
+                spacing.gap_x as f32 * 0.5 * scale_x,
+                spacing.gap_y as f32 * 0.5 * scale_y,
+            ))
+        })
+        .collect();
+    let cols_u = app.custom_cols.max(1);
+    let cell_at = |pos: egui::Pos2| -> Option<(u32, u32)> {
+        cell_rects
+            .iter()
+            .position(|r| r.contains(pos))
+            .map(|i| (i as u32 % cols_u, i as u32 / cols_u))
+    };
+
     // Divider hit detection and rendering
     let divider_hit_px = 5.0;
     let hover_pos = response.hover_pos();
@@ -578,25 +890,18 @@ fn draw_interactive_preview(
     if show_dividers {
         let cols = app.custom_cols as usize;
         let rows = app.custom_rows as usize;
-        let usable_w_virtual = area.w - gap_virtual * (cols as i32 - 1);
-        let usable_h_virtual = area.h - gap_virtual * (rows as i32 - 1);
-
-        // Column divider x positions (between columns)
-        let mut cx = 0.0_f32;
-        for c in 0..cols - 1 {
-            let col_w = usable_w_virtual as f32 * app.col_weights[c];
-            cx += col_w;
-            let screen_x = offset.x + (cx + gap_virtual as f32 * c as f32 + gap_virtual as f32 * 0.5) * scale_x;
-            col_divider_x.push(screen_x);
+
+        // Column divider x positions (middle of the gap after each column)
+        for cell in &cells[..cols - 1] {
+            let x = (cell.x + cell.w) as f32 + spacing.gap_x as f32 * 0.5;
+            col_divider_x.push(offset.x + x * scale_x);
         }
 
-        // Row divider y positions (between rows)
-        let mut ry = 0.0_f32;
+        // Row divider y positions (middle of the gap below each row)
         for r in 0..rows - 1 {
-            let row_h = usable_h_virtual as f32 * app.row_weights[r];
-            ry += row_h;
-            let screen_y = offset.y + (ry + gap_virtual as f32 * r as f32 + gap_virtual as f32 * 0.5) * scale_y;
-            row_divider_y.push(screen_y);
+            let cell = &cells[r * cols];
+            let y = (cell.y + cell.h) as f32 + spacing.gap_y as f32 * 0.5;
+            row_divider_y.push(offset.y + y * scale_y);
         }
 
         // Check hover on dividers
@@ -618,13 +923,148 @@ fn draw_interactive_preview(
         }
     }
 
+    // Main/side divider (preset mode, main/side layouts only)
+    let main_side = match &preset {
+        LayoutPreset::MainSide { side, .. } if !show_dividers => Some(*side),
+        _ => None,
+    };
+    let mut main_divider: Option<f32> = None;
+    if let (Some(side), Some(main)) = (main_side, slots.first()) {
+        let half_gap = if side.is_horizontal() { spacing.gap_y } else { spacing.gap_x } as f32 * 0.5;
+        let pos = match side {
+            SidePosition::Right => offset.x + ((main.x + main.w) as f32 + half_gap) * scale_x,
+            SidePosition::Left => offset.x + (main.x as f32 - half_gap) * scale_x,
+            SidePosition::Bottom => offset.y + ((main.y + main.h) as f32 + half_gap) * scale_y,
+            SidePosition::Top => offset.y + (main.y as f32 - half_gap) * scale_y,
+        };
+        main_divider = Some(pos);
+
+        if let Some(hp) = hover_pos {
+            let d = if side.is_horizontal() { hp.y - pos } else { hp.x - pos };
+            if d.abs() < divider_hit_px {
+                hovered_divider = Some((DividerAxis::Main, 0));
+            }
+        }
+    }
+
+    // Dividers between weighted slots (preset mode: columns, rows, side stacks).
+    // The weighted slots are the last `weight_count` and share one cross-axis extent.
+    let weight_count = if show_dividers { 0 } else { preset.weight_count() };
+    let weights_vertical = preset.weights_stack_vertically();
+    let first_weighted = slots.len().saturating_sub(weight_count);
+    let preset_weights = if weight_count > 0 { app.preset_weights() } else { Vec::new() };
+    let weights_tuned = preset_weights
+        .iter()
+        .any(|w| (w - 1.0 / weight_count as f32).abs() > 0.001);
+    let mut slot_divider_pos = Vec::new();
+    // (start, end) of the weighted run along the split axis, and across it
+    let mut run_along = (0.0, 0.0);
+    let mut run_across = (0.0, 0.0);
+    if weight_count > 1 {
+        let half_gap = if weights_vertical { spacing.gap_y } else { spacing.gap_x } as f32 * 0.5;
+        let (first, last) = (&slots[first_weighted], &slots[slots.len() - 1]);
+        if weights_vertical {
+            run_along = (offset.y + first.y as f32 * scale_y, offset.y + (last.y + last.h) as f32 * scale_y);
+            run_across = (offset.x + first.x as f32 * scale_x, offset.x + (first.x + first.w) as f32 * scale_x);
+        } else {
+            run_along = (offset.x + first.x as f32 * scale_x, offset.x + (last.x + last.w) as f32 * scale_x);
+            run_across = (offset.y + first.y as f32 * scale_y, offset.y + (first.y + first.h) as f32 * scale_y);
+        }
+        for s in &slots[first_weighted..slots.len() - 1] {
+            slot_divider_pos.push(if weights_vertical {
+                offset.y + ((s.y + s.h) as f32 + half_gap) * scale_y
+            } else {
+                offset.x + ((s.x + s.w) as f32 + half_gap) * scale_x
+            });
+        }
+
+        if let (Some(hp), None) = (hover_pos, hovered_divider) {
+            let (along, across) = if weights_vertical { (hp.y, hp.x) } else { (hp.x, hp.y) };
+            if across >= run_across.0 && across <= run_across.1 {
+                for (i, &pos) in slot_divider_pos.iter().enumerate() {
+                    if (along - pos).abs() < divider_hit_px {
+                        hovered_divider = Some((DividerAxis::Slot, i));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
     // Drag interaction for dividers
     let mut action = PreviewAction::None;
 
+    if !show_dividers {
+        if response.drag_started() && hovered_divider.is_some() {
+            app.dragging_divider = hovered_divider;
+        }
+
+        if response.dragged() {
+            let delta = response.drag_delta();
+            match (app.dragging_divider, main_side) {
+                (Some((DividerAxis::Main, _)), Some(side)) => {
+                    let ratio_delta = match side {
+                        SidePosition::Right => delta.x / inner_w,
+                        SidePosition::Left => -delta.x / inner_w,
+                        SidePosition::Bottom => delta.y / inner_h,
+                        SidePosition::Top => -delta.y / inner_h,
+                    };
+                    let current = preset.main_ratio().unwrap_or(DEFAULT_MAIN_RATIO);
+                    app.config.defaults.main_ratio = Some((current + ratio_delta).clamp(0.1, 0.9));
+                }
+                (Some((DividerAxis::Slot, idx)), _) => {
+                    let extent = (run_along.1 - run_along.0).max(1.0);
+                    let moved = if weights_vertical { delta.y } else { delta.x };
+                    let weight_delta = moved / extent;
+                    let min_weight = 0.05;
+                    let mut weights = preset_weights.clone();
+                    let w0 = (weights[idx] + weight_delta).max(min_weight);
+                    let w1 = (weights[idx + 1] - weight_delta).max(min_weight);
+                    let sum = w0 + w1;
+                    let old_sum = weights[idx] + weights[idx + 1];
+                    weights[idx] = w0 / sum * old_sum;
+                    weights[idx + 1] = w1 / sum * old_sum;
+                    app.config.defaults.slot_weights = weights;
+                }
+                _ => {}
+            }
+        }
+
+        if response.drag_stopped() {
+            if let Some((axis, _)) = app.dragging_divider.take() {
+                action = if axis == DividerAxis::Slot {
+                    PreviewAction::SlotWeightsChanged
+                } else {
+                    PreviewAction::RatioChanged
+                };
+            }
+        }
+    }
+
+    let mut merge_preview: Option<CellSpan> = None;
+
     if show_dividers {
         if response.drag_started() {
             if let Some(div) = hovered_divider {
                 app.dragging_divider = Some(div);
+            } else if ui.input(|i| i.modifiers.shift) {
+                app.merge_drag_start = ui
+                    .input(|i| i.pointer.press_origin())
+                    .and_then(cell_at);
+            }
+        }
+
+        // Shift-drag across cells to merge them (or within a merged cell to split it)
+        if let Some(start) = app.merge_drag_start {
+            let current = ui.input(|i| i.pointer.latest_pos()).and_then(cell_at);
+            if let Some(end) = current {
+                merge_preview = Some(CellSpan::from_corners(start, end));
+            }
+            if response.drag_stopped() {
+                app.merge_drag_start = None;
+                if let Some(span) = merge_preview.take() {
+                    action = PreviewAction::MergeCells(span);
+                }
             }
         }
 
@@ -654,24 +1094,23 @@ fn draw_interactive_preview(
                         app.row_weights[idx] = w0 / sum * old_sum;
                         app.row_weights[idx + 1] = w1 / sum * old_sum;
                     }
+                    DividerAxis::Main | DividerAxis::Slot => {}
                 }
             }
         }
 
-        if response.drag_stopped() {
-            if app.dragging_divider.is_some() {
-                app.dragging_divider = None;
-                // Normalize weights
-                let col_sum: f32 = app.col_weights.iter().sum();
-                if col_sum > 0.0 {
-                    for w in &mut app.col_weights { *w /= col_sum; }
-                }
-                let row_sum: f32 = app.row_weights.iter().sum();
-                if row_sum > 0.0 {
-                    for w in &mut app.row_weights { *w /= row_sum; }
-                }
-                action = PreviewAction::WeightsChanged;
+        if response.drag_stopped() && app.dragging_divider.is_some() {
+            app.dragging_divider = None;
+            // Normalize weights
+            let col_sum: f32 = app.col_weights.iter().sum();
+            if col_sum > 0.0 {
+                for w in &mut app.col_weights { *w /= col_sum; }
+            }
+            let row_sum: f32 = app.row_weights.iter().sum();
+            if row_sum > 0.0 {
+                for w in &mut app.row_weights { *w /= row_sum; }
             }
+            action = PreviewAction::WeightsChanged;
         }
     }
 
@@ -683,6 +1122,12 @@ fn draw_interactive_preview(
             ui.ctx().set_cursor_icon(match a {
                 DividerAxis::Col => egui::CursorIcon::ResizeHorizontal,
                 DividerAxis::Row => egui::CursorIcon::ResizeVertical,
+                DividerAxis::Main => match main_side {
+                    Some(side) if side.is_horizontal() => egui::CursorIcon::ResizeVertical,
+                    _ => egui::CursorIcon::ResizeHorizontal,
+                },
+                DividerAxis::Slot if weights_vertical => egui::CursorIcon::ResizeVertical,
+                DividerAxis::Slot => egui::CursorIcon::ResizeHorizontal,
             });
         }
     }
@@ -703,7 +1148,7 @@ fn draw_interactive_preview(
         );
 
         let is_disabled = app.disabled_cells.contains(&i);
-        let is_hovered = hover_pos.map_or(false, |p| slot_rect.contains(p))
+        let is_hovered = hover_pos.is_some_and(|p| slot_rect.contains(p))
             && hovered_divider.is_none()
             && app.dragging_divider.is_none();
 
@@ -753,16 +1198,37 @@ fn draw_interactive_preview(
                     theme.text_muted,
                 );
             } else {
-                let label = format!("{}", i + 1);
-                if non_uniform && slot_rect.width() > 30.0 && slot_rect.height() > 30.0 {
-                    // Show cell number above center and percentage below
+                let label = slot_names
+                    .get(i)
+                    .filter(|n| !n.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("{}", i + 1));
+                let roomy = slot_rect.width() > 30.0 && slot_rect.height() > 30.0;
+                let pct_label = if non_uniform && roomy {
                     let cols = app.custom_cols as usize;
-                    let row = i / cols;
-                    let col = i % cols;
-                    let w_pct = (app.col_weights[col] * 100.0).round() as u32;
-                    let h_pct = (app.row_weights[row] * 100.0).round() as u32;
-                    let pct_label = format!("{}%x{}%", w_pct, h_pct);
+                    let cell = slot_cells.get(i).copied().unwrap_or(i);
+                    let row = cell / cols;
+                    let col = cell % cols;
+                    let (col_span, row_span) = app
+                        .merged_cells
+                        .iter()
+                        .find(|m| (m.col as usize, m.row as usize) == (col, row))
+                        .map(|m| (m.col_span as usize, m.row_span as usize))
+                        .unwrap_or((1, 1));
+                    let w_pct = (app.col_weights[col..col + col_span].iter().sum::<f32>() * 100.0)
+                        .round() as u32;
+                    let h_pct = (app.row_weights[row..row + row_span].iter().sum::<f32>() * 100.0)
+                        .round() as u32;
+                    Some(format!("{}%x{}%", w_pct, h_pct))
+                } else if weights_tuned && roomy && i >= first_weighted {
+                    let pct = (preset_weights[i - first_weighted] * 100.0).round() as u32;
+                    Some(format!("{}%", pct))
+                } else {
+                    None
+                };
 
+                if let Some(pct_label) = pct_label {
+                    // Show cell number above center and percentage below
                     painter.text(
                         slot_rect.center() - egui::vec2(0.0, 7.0),
                         egui::Align2::CENTER_CENTER,
@@ -790,6 +1256,20 @@ fn draw_interactive_preview(
         }
     }
 
+    // Pending merge selection
+    if let Some(span) = merge_preview {
+        let first = (span.row * cols_u + span.col) as usize;
+        let last = ((span.row + span.row_span - 1) * cols_u + span.col + span.col_span - 1) as usize;
+        if let (Some(a), Some(b)) = (cell_rects.get(first), cell_rects.get(last)) {
+            painter.rect_stroke(
+                a.union(*b),
+                3.0,
+                egui::Stroke::new(2.0, theme.accent2),
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+
     // Draw divider lines on top
     if show_dividers {
         let top = offset.y;
@@ -828,6 +1308,40 @@ fn draw_interactive_preview(
         }
     }
 
+    for (i, &pos) in slot_divider_pos.iter().enumerate() {
+        let is_active = app.dragging_divider == Some((DividerAxis::Slot, i))
+            || hovered_divider == Some((DividerAxis::Slot, i));
+        let stroke_w = if is_active { 2.5 } else { 1.0 };
+        let color = if is_active {
+            theme.accent
+        } else {
+            theme.accent.linear_multiply(0.4)
+        };
+        let line = if weights_vertical {
+            [egui::pos2(run_across.0, pos), egui::pos2(run_across.1, pos)]
+        } else {
+            [egui::pos2(pos, run_across.0), egui::pos2(pos, run_across.1)]
+        };
+        painter.line_segment(line, egui::Stroke::new(stroke_w, color));
+    }
+
+    if let (Some(side), Some(pos)) = (main_side, main_divider) {
+        let is_active = app.dragging_divider == Some((DividerAxis::Main, 0))
+            || hovered_divider == Some((DividerAxis::Main, 0));
+        let stroke_w = if is_active { 2.5 } else { 1.0 };
+        let color = if is_active {
+            theme.accent
+        } else {
+            theme.accent.linear_multiply(0.4)
+        };
+        let line = if side.is_horizontal() {
+            [egui::pos2(offset.x, pos), egui::pos2(offset.x + inner_w, pos)]
+        } else {
+            [egui::pos2(pos, offset.y), egui::pos2(pos, offset.y + inner_h)]
+        };
+        painter.line_segment(line, egui::Stroke::new(stroke_w, color));
+    }
+
     if let Some(cell_idx) = clicked_cell {
         if action.is_none() {
             return PreviewAction::ToggleCell(cell_idx);
@@ -835,3 +1349,230 @@ fn draw_interactive_preview(
     }
     action
 }
+
+/// Spacing as drawn in the 1920x1080 preview: margins as configured, gaps
+/// enlarged so they stay visible at preview size.
+fn preview_spacing(spacing: &Spacing) -> Spacing {
+    let gap = |g: i32| (g * 3).clamp(2, 48);
+    Spacing {
+        gap_x: gap(spacing.gap_x),
+        gap_y: gap(spacing.gap_y),
+        margin: spacing.margin,
+    }
+}
+
+/// Freeform layout editor: drag on empty space to draw a pane, drag a pane to
+/// move it, drag its bottom-right corner to resize, right-click to delete.
+/// Panes are stored as fractions of the work area.
+fn draw_free_editor(
+    ui: &mut egui::Ui,
+    ctx: &egui::Context,
+    app: &mut PsmApp,
+    theme: &Theme,
+) -> PreviewAction {
+    let window_count = app.managed_windows.len();
+
+    let panel_width = ctx.screen_rect().width();
+    let preview_width = (panel_width - 16.0).max(60.0);
+    let panel_height = ctx.screen_rect().height();
+    let max_preview_h = (panel_height * 0.4).max(40.0);
+    let preview_height = (preview_width * 9.0 / 16.0).min(max_preview_h);
+    let preview_size = egui::vec2(preview_width, preview_height);
+
+    let (response, painter) = ui.allocate_painter(preview_size, egui::Sense::click_and_drag());
+    let rect = response.rect;
+
+    painter.rect_filled(rect, 4.0, theme.surface);
+    painter.rect_stroke(
+        rect,
+        4.0,
+        egui::Stroke::new(1.0, theme.border),
+        egui::StrokeKind::Outside,
+    );
+
+    let pad = 6.0;
+    let inner = egui::Rect::from_min_size(
+        rect.min + egui::vec2(pad, pad),
+        preview_size - egui::vec2(pad * 2.0, pad * 2.0),
+    );
+    let to_rel = |p: egui::Pos2| -> (f32, f32) {
+        (
+            ((p.x - inner.min.x) / inner.width()).clamp(0.0, 1.0),
+            ((p.y - inner.min.y) / inner.height()).clamp(0.0, 1.0),
+        )
+    };
+    let to_screen = |r: &RelRect| -> egui::Rect {
+        egui::Rect::from_min_size(
+            inner.min + egui::vec2(r.x * inner.width(), r.y * inner.height()),
+            egui::vec2(r.w * inner.width(), r.h * inner.height()),
+        )
+    };
+
+    // Topmost pane under a point, and whether it's on the resize handle
+    let handle_px = 8.0;
+    let hit = |rects: &[RelRect], p: egui::Pos2| -> Option<(usize, bool)> {
+        rects.iter().enumerate().rev().find_map(|(i, r)| {
+            let sr = to_screen(r);
+            if sr.expand(2.0).contains(p) {
+                let on_handle = (sr.max.x - p.x).abs() < handle_px && (sr.max.y - p.y).abs() < handle_px;
+                Some((i, on_handle))
+            } else {
+                None
+            }
+        })
+    };
+
+    let hover_pos = response.hover_pos();
+    let hovered = hover_pos.and_then(|p| hit(&app.free_rects, p));
+    let mut action = PreviewAction::None;
+
+    if response.drag_started() {
+        let origin = ui.input(|i| i.pointer.press_origin());
+        app.free_drag = origin.map(|p| match hit(&app.free_rects, p) {
+            Some((i, true)) => FreeDrag::Resize(i),
+            Some((i, false)) => FreeDrag::Move(i),
+            None => {
+                let (x, y) = to_rel(p);
+                FreeDrag::Draw(x, y)
+            }
+        });
+    }
+
+    let mut drawing: Option<RelRect> = None;
+    if let Some(drag) = app.free_drag {
+        let delta = response.drag_delta();
+        let dx = delta.x / inner.width();
+        let dy = delta.y / inner.height();
+        match drag {
+            FreeDrag::Move(i) => {
+                if let Some(r) = app.free_rects.get_mut(i) {
+                    r.x += dx;
+                    r.y += dy;
+                    *r = r.clamped();
+                }
+            }
+            FreeDrag::Resize(i) => {
+                if let Some(r) = app.free_rects.get_mut(i) {
+                    r.w = (r.w + dx).min(1.0 - r.x);
+                    r.h = (r.h + dy).min(1.0 - r.y);
+                    *r = r.clamped();
+                }
+            }
+            FreeDrag::Draw(x0, y0) => {
+                if let Some(p) = ui.input(|i| i.pointer.latest_pos()) {
+                    let (x1, y1) = to_rel(p);
+                    drawing = Some(RelRect {
+                        x: x0.min(x1),
+                        y: y0.min(y1),
+                        w: (x1 - x0).abs(),
+                        h: (y1 - y0).abs(),
+                    });
+                }
+            }
+        }
+
+        if response.drag_stopped() {
+            app.free_drag = None;
+            match drag {
+                FreeDrag::Draw(..) => {
+                    // Ignore accidental tiny drags
+                    if let Some(r) = drawing.take().filter(|r| r.w > 0.03 && r.h > 0.03) {
+                        app.free_rects.push(snap_rel_rect(&r));
+                        action = PreviewAction::FreeRectsChanged;
+                    }
+                }
+                FreeDrag::Move(i) | FreeDrag::Resize(i) => {
+                    if let Some(r) = app.free_rects.get_mut(i) {
+                        *r = snap_rel_rect(r);
+                    }
+                    action = PreviewAction::FreeRectsChanged;
+                }
+            }
+        }
+    }
+
+    if response.secondary_clicked() {
+        let target = response.interact_pointer_pos().and_then(|p| hit(&app.free_rects, p));
+        if let Some((i, _)) = target {
+            app.free_rects.remove(i);
+            app.disabled_cells.clear();
+            action = PreviewAction::FreeRectsChanged;
+        }
+    }
+
+    // Cursor feedback
+    match app.free_drag.or(hovered.map(|(i, h)| if h { FreeDrag::Resize(i) } else { FreeDrag::Move(i) })) {
+        Some(FreeDrag::Resize(_)) => ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeNwSe),
+        Some(FreeDrag::Move(_)) => ui.ctx().set_cursor_icon(egui::CursorIcon::Grab),
+        Some(FreeDrag::Draw(..)) => ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair),
+        None => {}
+    }
+
+    // Panes, in slot order
+    for (i, r) in app.free_rects.iter().enumerate() {
+        let sr = to_screen(r);
+        let is_hovered = hovered.map(|(h, _)| h) == Some(i) && app.free_drag.is_none();
+        let color = if is_hovered {
+            theme.cell_hover
+        } else if i < window_count {
+            theme.cell_occupied
+        } else {
+            theme.cell_enabled
+        };
+        painter.rect_filled(sr, 3.0, color.linear_multiply(0.85));
+        painter.rect_stroke(
+            sr,
+            3.0,
+            egui::Stroke::new(1.0, theme.accent.linear_multiply(0.5)),
+            egui::StrokeKind::Inside,
+        );
+        // Resize handle
+        painter.rect_filled(
+            egui::Rect::from_min_max(sr.max - egui::vec2(6.0, 6.0), sr.max),
+            1.0,
+            theme.accent.linear_multiply(0.7),
+        );
+        if sr.width() >= 20.0 && sr.height() >= 20.0 {
+            painter.text(
+                sr.center(),
+                egui::Align2::CENTER_CENTER,
+                format!("{}", i + 1),
+                egui::FontId::proportional(14.0),
+                theme.text,
+            );
+        }
+    }
+
+    if let Some(r) = drawing {
+        painter.rect_stroke(
+            to_screen(&r),
+            3.0,
+            egui::Stroke::new(2.0, theme.accent2),
+            egui::StrokeKind::Inside,
+        );
+    }
+
+    if app.free_rects.is_empty() && app.free_drag.is_none() {
+        painter.text(
+            inner.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drag to draw a pane",
+            egui::FontId::proportional(13.0),
+            theme.text_muted,
+        );
+    }
+
+    action
+}
+
+/// Round a freeform rect to whole percentages so saved layouts stay tidy.
+fn snap_rel_rect(r: &RelRect) -> RelRect {
+    let snap = |v: f32| (v * 100.0).round() / 100.0;
+    RelRect {
+        x: snap(r.x),
+        y: snap(r.y),
+        w: snap(r.w),
+        h: snap(r.h),
+    }
+    .clamped()
+}
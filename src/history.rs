@@ -0,0 +1,263 @@
+use crate::backend::WindowBackend;
+use crate::monitor::Rect;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+/// Arrangements kept for undo; older ones are dropped.
+const MAX_HISTORY: usize = 20;
+
+/// Where a window was and whether it was minimized.
+#[derive(Debug, Clone)]
+struct WindowState {
+    hwnd: isize,
+    /// Guards against window handles reused by a different process.
+    process: String,
+    rect: Rect,
+    minimized: bool,
+}
+
+type Snapshot = Vec<WindowState>;
+
+#[derive(Default)]
+struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+fn history() -> &'static Mutex<History> {
+    static HISTORY: OnceLock<Mutex<History>> = OnceLock::new();
+    HISTORY.get_or_init(|| Mutex::new(History::default()))
+}
+
+/// Save the current state of `hwnds` before an arrangement moves them. A new
+/// arrangement makes the redo stack meaningless, so it's cleared.
+pub fn record(backend: &dyn WindowBackend, hwnds: &[isize]) {
+    let snapshot = capture(backend, hwnds.iter().copied());
+    if snapshot.is_empty() {
+        return;
+    }
+    let Ok(mut history) = history().lock() else {
+        return;
+    };
+    history.redo.clear();
+    history.undo.push_back(snapshot);
+    while history.undo.len() > MAX_HISTORY {
+        history.undo.pop_front();
+    }
+}
+
+/// Put the windows of the last arrangement back. Returns how many windows
+/// were restored, or `None` if there was nothing to undo. Arrangements
+/// whose windows have all closed since are dropped on the way.
+pub fn undo(backend: &dyn WindowBackend) -> Option<usize> {
+    step(backend, |h| h.undo.pop_back(), |h, s| h.redo.push(s))
+}
+
+/// Reapply the arrangement the last undo reverted.
+pub fn redo(backend: &dyn WindowBackend) -> Option<usize> {
+    step(backend, |h| h.redo.pop(), |h, s| {
+        h.undo.push_back(s);
+        while h.undo.len() > MAX_HISTORY {
+            h.undo.pop_front();
+        }
+    })
+}
+
+/// Apply the snapshot `take` pops and hand the state it replaced to `put`.
+/// The lock isn't held while windows move, so a slow backend call can't
+/// block the GUI asking `can_undo`.
+fn step(
+    backend: &dyn WindowBackend,
+    take: impl Fn(&mut History) -> Option<Snapshot>,
+    put: impl Fn(&mut History, Snapshot),
+) -> Option<usize> {
+    loop {
+        let snapshot = take(&mut *history().lock().ok()?)?;
+        let open = snapshot.iter().filter(|s| still_open(backend, s)).map(|s| s.hwnd);
+        let current = capture(backend, open);
+        if current.is_empty() {
+            continue;
+        }
+        let restored = apply(backend, &snapshot);
+        if let Ok(mut history) = history().lock() {
+            put(&mut history, current);
+        }
+        return Some(restored);
+    }
+}
+
+pub fn can_undo() -> bool {
+    history().lock().is_ok_and(|h| !h.undo.is_empty())
+}
+
+pub fn can_redo() -> bool {
+    history().lock().is_ok_and(|h| !h.redo.is_empty())
+}
+
+fn capture(backend: &dyn WindowBackend, hwnds: impl Iterator<Item = isize>) -> Snapshot {
+    hwnds
+        .filter_map(|hwnd| {
+            Some(WindowState {
+                hwnd,
+                process: backend.process_name(hwnd)?,
+                rect: backend.window_rect(hwnd)?,
+                minimized: backend.is_minimized(hwnd),
+            })
+        })
+        .collect()
+}
+
+/// False for windows closed since, or whose handle now belongs to another
+/// process.
+fn still_open(backend: &dyn WindowBackend, state: &WindowState) -> bool {
+    backend
+        .process_name(state.hwnd)
+        .is_some_and(|p| p.eq_ignore_ascii_case(&state.process))
+}
+
+/// Move every window that still exists back to its saved state.
+fn apply(backend: &dyn WindowBackend, snapshot: &[WindowState]) -> usize {
+    let mut restored = 0;
+    for state in snapshot {
+        if !still_open(backend, state) {
+            continue;
+        }
+
+        // A minimized window's rect is its icon position, so only its
+        // minimized state is restored
+        if state.minimized {
+            if !backend.is_minimized(state.hwnd) {
+                backend.minimize(state.hwnd);
+            }
+        } else {
+            if backend.is_minimized(state.hwnd) {
+                backend.restore(state.hwnd);
+            }
+            if let Err(e) = backend.move_window(state.hwnd, &state.rect) {
+                log::warn!("Failed to restore window {}: {}", state.hwnd, e);
+                continue;
+            }
+        }
+        restored += 1;
+    }
+    restored
+}
+
+/// The history is shared by the whole process, so tests that record it
+/// take turns, each starting from an empty one.
+#[cfg(test)]
+pub(crate) fn fresh() -> std::sync::MutexGuard<'static, ()> {
+    static TURN: Mutex<()> = Mutex::new(());
+    let turn = TURN.lock().unwrap_or_else(|e| e.into_inner());
+    *history().lock().unwrap() = History::default();
+    turn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, Scenario};
+
+    fn desktop() -> FakeBackend {
+        let scenario = r#"
+            [[monitor]]
+            x = 0
+            y = 0
+            w = 1200
+            h = 800
+
+            [[window]]
+            hwnd = 1
+            process = "pwsh.exe"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+
+            [[window]]
+            hwnd = 2
+            process = "code.exe"
+            x = 600
+            y = 0
+            w = 600
+            h = 800
+            minimized = true
+        "#;
+        FakeBackend::new(toml::from_str::<Scenario>(scenario).unwrap())
+    }
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let _turn = fresh();
+        let fake = desktop();
+        record(&fake, &[1, 2]);
+        assert!(can_undo() && !can_redo());
+        fake.move_window(1, &rect(0, 0, 400, 400)).unwrap();
+        fake.restore(2);
+        fake.move_window(2, &rect(400, 0, 800, 800)).unwrap();
+
+        assert_eq!(undo(&fake), Some(2));
+        assert_eq!(fake.window_rect(1), Some(rect(0, 0, 600, 800)));
+        assert!(fake.is_minimized(2));
+        assert!(!can_undo() && can_redo());
+
+        assert_eq!(redo(&fake), Some(2));
+        assert_eq!(fake.window_rect(1), Some(rect(0, 0, 400, 400)));
+        assert!(!fake.is_minimized(2));
+        assert_eq!(fake.window_rect(2), Some(rect(400, 0, 800, 800)));
+        assert!(can_undo() && !can_redo());
+    }
+
+    #[test]
+    fn closed_windows_are_skipped() {
+        let _turn = fresh();
+        let fake = desktop();
+        record(&fake, &[1]);
+        record(&fake, &[1, 2]);
+        fake.close(2);
+        fake.move_window(1, &rect(0, 0, 400, 400)).unwrap();
+        assert_eq!(undo(&fake), Some(1));
+        assert_eq!(fake.window_rect(1), Some(rect(0, 0, 600, 800)));
+
+        // Arrangements of windows that are all gone are dropped
+        fake.close(1);
+        assert_eq!(undo(&fake), None);
+        assert!(!can_undo());
+        assert_eq!(redo(&fake), None);
+    }
+
+    #[test]
+    fn reused_handles_are_left_alone() {
+        let _turn = fresh();
+        let fake = desktop();
+        record(&fake, &[1]);
+        fake.close(1);
+        let window = r#"
+            hwnd = 1
+            process = "slack.exe"
+            x = 100
+            y = 100
+            w = 300
+            h = 300
+        "#;
+        fake.open(toml::from_str(window).unwrap());
+        assert_eq!(undo(&fake), None);
+        assert_eq!(fake.window_rect(1), Some(rect(100, 100, 300, 300)));
+        assert!(!can_redo());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let _turn = fresh();
+        let fake = desktop();
+        for _ in 0..MAX_HISTORY + 5 {
+            record(&fake, &[1]);
+        }
+        let undone = std::iter::from_fn(|| undo(&fake)).count();
+        assert_eq!(undone, MAX_HISTORY);
+    }
+}
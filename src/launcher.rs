@@ -0,0 +1,290 @@
+use crate::arrange::{self, ArrangeRequest, ArrangeResult};
+use crate::backend::WindowBackend;
+use crate::config::{Config, LaunchApp, LaunchProfile};
+use crate::windows::{ManagedWindow, TargetFilter};
+use std::collections::HashSet;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct LaunchResult {
+    pub launched: usize,
+    /// Apps with no window when the timeout ran out.
+    pub missing: usize,
+    pub arranged: ArrangeResult,
+}
+
+/// Start the profile's apps that aren't open, wait for their windows, then
+/// put every app in its slot. Blocks until all windows are there or the
+/// profile's timeout runs out.
+pub fn run_profile(
+    backend: &dyn WindowBackend,
+    profile: &LaunchProfile,
+    config: &Config,
+    app_hwnd: isize,
+) -> Result<LaunchResult, String> {
+    let (preset, shape) = config
+        .layout_named(&profile.layout)
+        .ok_or_else(|| format!("Unknown layout '{}' in profile '{}'", profile.layout, profile.name))?;
+
+    // Only the profile's apps are arranged
+    let mut processes: Vec<String> = profile
+        .app
+        .iter()
+        .map(|app| app.process_name().to_lowercase())
+        .collect();
+    processes.sort();
+    processes.dedup();
+    let filter = TargetFilter::Rules {
+        base: Box::new(TargetFilter::Custom(processes)),
+        include: None,
+        exclude: config.exclude.clone(),
+    };
+    let extra_exclude = config.categories.excluded_lower();
+    let find = || {
+        let windows = backend.enumerate_windows(&filter, app_hwnd, &extra_exclude);
+        match_apps(&profile.app, &windows)
+    };
+
+    let mut found = find();
+    let mut errors = Vec::new();
+    let mut waiting = vec![false; profile.app.len()];
+    for (i, app) in profile.app.iter().enumerate() {
+        if found[i].is_some() {
+            continue;
+        }
+        match spawn(app) {
+            Ok(()) => waiting[i] = true,
+            Err(e) => errors.push(format!("Failed to start '{}': {}", app.exe, e)),
+        }
+    }
+    let launched = waiting.iter().filter(|w| **w).count();
+
+    let deadline = Instant::now() + Duration::from_secs(profile.timeout_secs);
+    let still_waiting =
+        |found: &[Option<isize>]| waiting.iter().zip(found).any(|(w, f)| *w && f.is_none());
+    while still_waiting(&found) && Instant::now() < deadline {
+        std::thread::sleep(POLL_INTERVAL);
+        found = find();
+    }
+    let missing = found.iter().filter(|f| f.is_none()).count();
+    if still_waiting(&found) {
+        errors.push(format!(
+            "Timed out after {}s waiting for {} windows",
+            profile.timeout_secs,
+            waiting.iter().zip(&found).filter(|(w, f)| **w && f.is_none()).count()
+        ));
+    }
+
+    let placements: Vec<(isize, usize)> = profile
+        .app
+        .iter()
+        .zip(&found)
+        .filter_map(|(app, hwnd)| hwnd.map(|h| (h, app.slot)))
+        .collect();
+    let mut arranged = arrange::arrange_masked(
+        backend,
+        &ArrangeRequest {
+            preset: &preset,
+            grid_shape: shape.as_ref(),
+            filter: &filter,
+            monitor_spec: profile.monitor.as_deref().unwrap_or(&config.defaults.monitor),
+            spacing: &config.defaults.spacing(),
+            disabled: &HashSet::new(),
+            app_hwnd,
+            extra_exclude: &extra_exclude,
+            smart_sort: false,
+            activity: None,
+            pin_rules: &config.pin,
+            layout_name: Some(&profile.layout),
+            slot_names: &config.slot_names(Some(&profile.layout), Some(&preset)),
+            placements: &placements,
+            size_rules: &config.size_constraint,
+            persist_slots: config.defaults.persist_slot_memory,
+        },
+    );
+    errors.append(&mut arranged.errors);
+    arranged.errors = errors;
+
+    Ok(LaunchResult {
+        launched,
+        missing,
+        arranged,
+    })
+}
+
+/// Log how running a profile went.
+pub fn report(name: &str, result: &Result<LaunchResult, String>) {
+    match result {
+        Ok(result) => {
+            log::info!(
+                "Profile '{}': launched {}, arranged {} windows ({} missing)",
+                name,
+                result.launched,
+                result.arranged.arranged,
+                result.missing
+            );
+            result.arranged.log_pins();
+            for err in &result.arranged.errors {
+                log::warn!("  {}", err);
+            }
+        }
+        Err(e) => log::warn!("Profile '{}': {}", name, e),
+    }
+}
+
+/// The window of every app, each window used once. Windows come topmost
+/// first, so the most recently used window of a process is matched first.
+fn match_apps(apps: &[LaunchApp], windows: &[ManagedWindow]) -> Vec<Option<isize>> {
+    let mut claimed = vec![false; windows.len()];
+    apps.iter()
+        .map(|app| {
+            let i = (0..windows.len()).find(|&i| {
+                !claimed[i] && app.matches(&windows[i].process_name, &windows[i].title)
+            })?;
+            claimed[i] = true;
+            Some(windows[i].hwnd)
+        })
+        .collect()
+}
+
+fn spawn(app: &LaunchApp) -> std::io::Result<()> {
+    let mut command = Command::new(&app.exe);
+    command.args(&app.args).envs(&app.env);
+    if let Some(cwd) = &app.cwd {
+        command.current_dir(cwd);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // Console apps get their own console instead of sharing ours
+        const CREATE_NEW_CONSOLE: u32 = 0x10;
+        command.creation_flags(CREATE_NEW_CONSOLE);
+    }
+    let mut child = command.spawn()?;
+    // Reap the app when it exits so it doesn't linger as a zombie
+    let reaper = std::thread::Builder::new().name(format!("wait {}", app.exe));
+    if let Err(e) = reaper.spawn(move || child.wait()) {
+        log::warn!("Can't wait for '{}': {}", app.exe, e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, Scenario};
+
+    const DESKTOP: &str = r#"
+        [[monitor]]
+        x = 0
+        y = 0
+        w = 1200
+        h = 800
+        primary = true
+
+        [[window]]
+        hwnd = 1
+        process = "pwsh.exe"
+        title = "api"
+        x = 0
+        y = 0
+        w = 600
+        h = 800
+
+        [[window]]
+        hwnd = 2
+        process = "pwsh.exe"
+        title = "web"
+        x = 600
+        y = 0
+        w = 600
+        h = 800
+
+        [[window]]
+        hwnd = 3
+        process = "code.exe"
+        title = "main.rs"
+        x = 0
+        y = 0
+        w = 1200
+        h = 800
+    "#;
+
+    fn profile(toml: &str) -> LaunchProfile {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn windows(fake: &FakeBackend) -> Vec<ManagedWindow> {
+        fake.enumerate_windows(&TargetFilter::Universal, 0, &[])
+    }
+
+    #[test]
+    fn windows_of_one_process_go_to_one_app_each() {
+        let fake = FakeBackend::new(toml::from_str::<Scenario>(DESKTOP).unwrap());
+        let profile = profile(
+            r#"
+            name = "dev"
+            layout = "columns:3"
+            app = [
+                { exe = "pwsh.exe", slot = 0 },
+                { exe = "pwsh.exe", slot = 1 },
+                { exe = "pwsh.exe", slot = 2 },
+            ]
+            "#,
+        );
+        // Topmost first, and a third window that isn't there
+        assert_eq!(match_apps(&profile.app, &windows(&fake)), vec![Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn running_apps_are_matched_by_title() {
+        let fake = FakeBackend::new(toml::from_str::<Scenario>(DESKTOP).unwrap());
+        let profile = profile(
+            r#"
+            name = "dev"
+            layout = "columns:3"
+            app = [
+                { exe = "pwsh.exe", title_contains = "WEB", slot = 0 },
+                { exe = "/usr/bin/code", process = "code.exe", slot = 1 },
+                { exe = "pwsh.exe", title_contains = "docs", slot = 2 },
+            ]
+            "#,
+        );
+        assert_eq!(match_apps(&profile.app, &windows(&fake)), vec![Some(2), Some(3), None]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apps_without_a_window_time_out() {
+        let fake = FakeBackend::new(toml::from_str::<Scenario>(DESKTOP).unwrap());
+        // `true` starts fine but never opens a window
+        let ghost = profile(
+            r#"
+            name = "ghost"
+            layout = "columns:2"
+            timeout_secs = 0
+            app = [{ exe = "true", slot = 0 }]
+            "#,
+        );
+        let config: Config = toml::from_str("").unwrap();
+        let result = run_profile(&fake, &ghost, &config, 0).unwrap();
+        assert_eq!((result.launched, result.missing), (1, 1));
+        assert_eq!(result.arranged.arranged, 0);
+        assert!(
+            result.arranged.errors.iter().any(|e| e.starts_with("Timed out")),
+            "{:?}",
+            result.arranged.errors
+        );
+
+        let typo = profile(
+            r#"
+            name = "typo"
+            layout = "columnz"
+            app = []
+            "#,
+        );
+        assert!(run_profile(&fake, &typo, &config, 0).is_err());
+    }
+}
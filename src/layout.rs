@@ -1,6 +1,24 @@
 use crate::monitor::Rect;
+use crate::template::{self, LayoutTemplate};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+/// Golden-ratio share used by the spiral layout when none is configured.
+pub const DEFAULT_SPIRAL_RATIO: f32 = 0.618;
+
+/// Main-area share for the classic "Main + N Side" layout.
+pub const DEFAULT_MAIN_RATIO: f32 = 2.0 / 3.0;
+
+/// Main-area share for the "Focus" flavour of the main/side layout.
+pub const FOCUS_MAIN_RATIO: f32 = 0.75;
+
+/// Width share of the center column in the centered-master layout.
+pub const DEFAULT_CENTER_RATIO: f32 = 0.5;
+
+/// Slots of an uncapped BSP layout when there are no windows to count, as
+/// in the preview.
+pub const DEFAULT_BSP_COUNT: u32 = 4;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Slot {
     pub x: i32,
     pub y: i32,
@@ -8,6 +26,234 @@ pub struct Slot {
     pub h: i32,
 }
 
+impl From<Rect> for Slot {
+    fn from(r: Rect) -> Self {
+        Slot {
+            x: r.x,
+            y: r.y,
+            w: r.w,
+            h: r.h,
+        }
+    }
+}
+
+impl Slot {
+    fn rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+/// Split the `total` pixels starting at `start` into `weights.len()` spans
+/// separated by `gap`, as (position, length) pairs. Each span gets its
+/// weight's share of the space left after gaps; pixels lost to rounding go
+/// one each to the spans with the largest remainders (earlier spans first on
+/// ties), so the spans and gaps always tile exactly `total` pixels. Gaps
+/// shrink if `total` is too small to hold them.
+///
+/// Weights need not sum to 1; if none are positive the split is even.
+pub fn distribute(start: i32, total: i32, gap: i32, weights: &[f32]) -> Vec<(i32, i32)> {
+    let n = weights.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let total = total.max(0);
+    let gap = gap.clamp(0, total / (n as i32 - 1).max(1));
+    let usable = total - gap * (n as i32 - 1);
+    let weight_sum: f64 = weights.iter().map(|w| w.max(0.0) as f64).sum();
+    let shares: Vec<f64> = weights
+        .iter()
+        .map(|w| {
+            if weight_sum > 0.0 {
+                usable as f64 * w.max(0.0) as f64 / weight_sum
+            } else {
+                usable as f64 / n as f64
+            }
+        })
+        .collect();
+
+    let mut sizes: Vec<i32> = shares.iter().map(|s| s.floor() as i32).collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    // Stable sort: equal remainders keep span order
+    order.sort_by(|&a, &b| {
+        let ra = shares[a] - shares[a].floor();
+        let rb = shares[b] - shares[b].floor();
+        rb.partial_cmp(&ra).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut leftover = usable - sizes.iter().sum::<i32>();
+    let mut k = 0;
+    while leftover > 0 {
+        sizes[order[k % n]] += 1;
+        leftover -= 1;
+        k += 1;
+    }
+    while leftover < 0 {
+        // Float error overshot; take back from the largest span
+        let i = (0..n).max_by_key(|&i| sizes[i]).unwrap_or(0);
+        sizes[i] -= 1;
+        leftover += 1;
+    }
+
+    let mut pos = start;
+    let spans: Vec<(i32, i32)> = sizes
+        .into_iter()
+        .map(|size| {
+            let span = (pos, size);
+            pos += size + gap;
+            span
+        })
+        .collect();
+    debug_assert!(
+        pos - gap == start + total,
+        "spans must tile the area exactly"
+    );
+    spans
+}
+
+/// `distribute` with equal weights.
+pub fn distribute_even(start: i32, total: i32, gap: i32, count: usize) -> Vec<(i32, i32)> {
+    distribute(start, total, gap, &vec![1.0; count])
+}
+
+/// Space left free at each edge of the work area, in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Margins {
+    #[serde(default)]
+    pub top: i32,
+    #[serde(default)]
+    pub right: i32,
+    #[serde(default)]
+    pub bottom: i32,
+    #[serde(default)]
+    pub left: i32,
+}
+
+impl Margins {
+    /// The part of `area` inside the margins (never smaller than 1x1).
+    pub fn inset(&self, area: &Rect) -> Rect {
+        Rect {
+            x: area.x + self.left,
+            y: area.y + self.top,
+            w: (area.w - self.left - self.right).max(1),
+            h: (area.h - self.top - self.bottom).max(1),
+        }
+    }
+}
+
+/// Gaps between slots (per axis) and margins around the work area.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spacing {
+    /// Between side-by-side slots.
+    pub gap_x: i32,
+    /// Between stacked slots.
+    pub gap_y: i32,
+    pub margin: Margins,
+}
+
+impl Spacing {
+    /// Gap between slots split along the given axis.
+    fn gap(&self, vertical: bool) -> i32 {
+        if vertical {
+            self.gap_y
+        } else {
+            self.gap_x
+        }
+    }
+}
+
+/// Which edge of the work area the side stack sits on in a main/side layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidePosition {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl SidePosition {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "left" | "l" => Some(Self::Left),
+            "right" | "r" => Some(Self::Right),
+            "top" | "t" => Some(Self::Top),
+            "bottom" | "b" => Some(Self::Bottom),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Top => "Top",
+            Self::Bottom => "Bottom",
+        }
+    }
+
+    /// True when the side stack runs along the horizontal axis (top/bottom).
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Self::Top | Self::Bottom)
+    }
+}
+
+/// A rectangle in fractions of the work area (0.0..=1.0 on both axes),
+/// used by freeform layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RelRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl RelRect {
+    /// Keep the rect inside the unit square with a minimum size.
+    pub fn clamped(&self) -> Self {
+        let w = self.w.clamp(0.02, 1.0);
+        let h = self.h.clamp(0.02, 1.0);
+        Self {
+            x: self.x.clamp(0.0, 1.0 - w),
+            y: self.y.clamp(0.0, 1.0 - h),
+            w,
+            h,
+        }
+    }
+
+    /// Scale into the work area. Half the gap is trimmed from every edge that
+    /// doesn't touch the work area border, so adjacent rects end up a gap apart.
+    pub fn to_slot(self, area: &Rect, gap_x: i32, gap_y: i32) -> Slot {
+        let r = self.clamped();
+        let edge = 0.001;
+        let (half_x, half_y) = (gap_x / 2, gap_y / 2);
+        let mut left = area.x + (r.x * area.w as f32).round() as i32;
+        let mut top = area.y + (r.y * area.h as f32).round() as i32;
+        let mut right = area.x + ((r.x + r.w) * area.w as f32).round() as i32;
+        let mut bottom = area.y + ((r.y + r.h) * area.h as f32).round() as i32;
+        if r.x > edge {
+            left += half_x;
+        }
+        if r.y > edge {
+            top += half_y;
+        }
+        if r.x + r.w < 1.0 - edge {
+            right -= gap_x - half_x;
+        }
+        if r.y + r.h < 1.0 - edge {
+            bottom -= gap_y - half_y;
+        }
+        Slot {
+            x: left,
+            y: top,
+            w: (right - left).max(1),
+            h: (bottom - top).max(1),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LayoutPreset {
     Grid { cols: u32, rows: u32 },
@@ -15,12 +261,22 @@ pub enum LayoutPreset {
     Rows(u32),
     LeftRight,
     TopBottom,
-    MainSide { side_count: u32 },
-    Focus { side_count: u32 },
+    MainSide { side_count: u32, ratio: f32, side: SidePosition },
+    /// One slot per window being arranged, up to the cap if there is one.
+    Bsp(Option<u32>),
+    Spiral { count: u32, ratio: f32 },
+    CenterMain { left: u32, right: u32, ratio: f32 },
+    Free(Vec<RelRect>),
+    Template(LayoutTemplate),
 }
 
 impl LayoutPreset {
     pub fn parse(s: &str) -> Option<Self> {
+        // "h(0.6: main, v(a, b))" -- parsed before lowercasing to keep slot names
+        if template::looks_like_template(s) {
+            return template::parse(s).ok().map(Self::Template);
+        }
+
         let s = s.trim().to_lowercase();
 
         // "2x3", "3x2", etc.
@@ -56,32 +312,129 @@ impl LayoutPreset {
             return Some(Self::TopBottom);
         }
 
-        if s.starts_with("main-side") || s.starts_with("mainside") {
-            let rest = s
-                .trim_start_matches("main-side")
-                .trim_start_matches("mainside")
-                .trim_start_matches(':')
-                .trim();
-            let n = rest.parse::<u32>().unwrap_or(2);
+        // "main-side:3", "main-side:3@0.6", "main-side:3@0.6,bottom", "focus:3,left"
+        let main_side = if s.starts_with("main-side") || s.starts_with("mainside") {
+            Some((
+                s.trim_start_matches("main-side")
+                    .trim_start_matches("mainside"),
+                2,
+                DEFAULT_MAIN_RATIO,
+            ))
+        } else {
+            s.strip_prefix("focus")
+                .map(|rest| (rest, 3, FOCUS_MAIN_RATIO))
+        };
+        if let Some((rest, default_count, default_ratio)) = main_side {
+            let rest = rest.trim_start_matches(':').trim();
+            let (rest, side) = match rest.split_once(',') {
+                Some((r, side)) => (r.trim(), SidePosition::parse(side)?),
+                None => (rest, SidePosition::Right),
+            };
+            let (count_str, ratio_str) = match rest.split_once('@') {
+                Some((c, r)) => (c.trim(), r.trim()),
+                None => (rest, ""),
+            };
+            let n = count_str.parse::<u32>().unwrap_or(default_count);
+            let ratio = if ratio_str.is_empty() {
+                default_ratio
+            } else {
+                ratio_str.parse::<f32>().ok()?
+            };
             return Some(Self::MainSide {
                 side_count: n.max(1),
+                ratio: ratio.clamp(0.1, 0.9),
+                side,
             });
         }
 
-        if s.starts_with("focus") {
+        // "bsp", "bsp:5" (at most 5 slots), "dwindle:4"
+        if s.starts_with("bsp") || s.starts_with("dwindle") {
             let rest = s
-                .trim_start_matches("focus")
+                .trim_start_matches("bsp")
+                .trim_start_matches("dwindle")
                 .trim_start_matches(':')
                 .trim();
-            let n = rest.parse::<u32>().unwrap_or(3);
-            return Some(Self::Focus {
-                side_count: n.max(1),
+            if rest.is_empty() {
+                return Some(Self::Bsp(None));
+            }
+            let n = rest.parse::<u32>().ok()?;
+            if n > 0 {
+                return Some(Self::Bsp(Some(n)));
+            }
+        }
+
+        // "spiral", "spiral:6", "spiral:0.6", "spiral:6@0.6"
+        if let Some(rest) = s.strip_prefix("spiral") {
+            let rest = rest.trim_start_matches(':').trim();
+            let (count_str, ratio_str) = match rest.split_once('@') {
+                Some((c, r)) => (c.trim(), r.trim()),
+                None if rest.contains('.') => ("", rest),
+                None => (rest, ""),
+            };
+            let count = if count_str.is_empty() {
+                6
+            } else {
+                count_str.parse::<u32>().ok()?
+            };
+            let ratio = if ratio_str.is_empty() {
+                DEFAULT_SPIRAL_RATIO
+            } else {
+                ratio_str.parse::<f32>().ok()?
+            };
+            if count > 0 {
+                return Some(Self::Spiral {
+                    count,
+                    ratio: ratio.clamp(0.1, 0.9),
+                });
+            }
+        }
+
+        // "center-main", "center-main:4", "center-main:1,2", "center-main:2,2@0.6"
+        if s.starts_with("center-main") || s.starts_with("centermain") {
+            let rest = s
+                .trim_start_matches("center-main")
+                .trim_start_matches("centermain")
+                .trim_start_matches(':')
+                .trim();
+            let (counts, ratio_str) = match rest.split_once('@') {
+                Some((c, r)) => (c.trim(), r.trim()),
+                None => (rest, ""),
+            };
+            let (left, right) = match counts.split_once(',') {
+                Some((l, r)) => (l.trim().parse::<u32>().ok()?, r.trim().parse::<u32>().ok()?),
+                None if counts.is_empty() => (1, 1),
+                None => {
+                    let n = counts.parse::<u32>().ok()?;
+                    (n.div_ceil(2), n / 2)
+                }
+            };
+            let ratio = if ratio_str.is_empty() {
+                DEFAULT_CENTER_RATIO
+            } else {
+                ratio_str.parse::<f32>().ok()?
+            };
+            return Some(Self::CenterMain {
+                left,
+                right,
+                ratio: ratio.clamp(0.1, 0.9),
             });
         }
 
         None
     }
 
+    /// The preset for arranging `windows` windows: BSP layouts get a slot
+    /// per window, up to their cap. Every other preset is fixed.
+    pub fn sized_for(&self, windows: usize) -> LayoutPreset {
+        match self {
+            Self::Bsp(cap) => {
+                let n = windows.clamp(1, u32::MAX as usize) as u32;
+                Self::Bsp(Some(cap.map_or(n, |cap| cap.min(n))))
+            }
+            _ => self.clone(),
+        }
+    }
+
     pub fn slot_count(&self) -> usize {
         match self {
             Self::Grid { cols, rows } => (*cols as usize) * (*rows as usize),
@@ -89,144 +442,149 @@ impl LayoutPreset {
             Self::Rows(n) => *n as usize,
             Self::LeftRight => 2,
             Self::TopBottom => 2,
-            Self::MainSide { side_count } => 1 + *side_count as usize,
-            Self::Focus { side_count } => 1 + *side_count as usize,
+            Self::MainSide { side_count, .. } => 1 + *side_count as usize,
+            Self::Bsp(cap) => cap.unwrap_or(DEFAULT_BSP_COUNT) as usize,
+            Self::Spiral { count, .. } => *count as usize,
+            Self::CenterMain { left, right, .. } => 1 + *left as usize + *right as usize,
+            Self::Free(rects) => rects.len(),
+            Self::Template(t) => t.slot_count(),
         }
     }
 
-    pub fn compute_slots(&self, area: &Rect, gap: i32) -> Vec<Slot> {
+    /// Slots inside the work area, after applying the spacing's margins.
+    pub fn compute_slots(&self, area: &Rect, spacing: &Spacing) -> Vec<Slot> {
+        let area = &spacing.margin.inset(area);
         match self {
             Self::Grid { cols, rows } => {
-                let cols = *cols as i32;
-                let rows = *rows as i32;
-                let total_gap_x = gap * (cols - 1);
-                let total_gap_y = gap * (rows - 1);
-                let cell_w = (area.w - total_gap_x) / cols;
-                let cell_h = (area.h - total_gap_y) / rows;
-
-                let mut slots = Vec::with_capacity((cols * rows) as usize);
-                for r in 0..rows {
-                    for c in 0..cols {
-                        slots.push(Slot {
-                            x: area.x + c * (cell_w + gap),
-                            y: area.y + r * (cell_h + gap),
-                            w: cell_w,
-                            h: cell_h,
-                        });
+                let xs = distribute_even(area.x, area.w, spacing.gap_x, *cols as usize);
+                let ys = distribute_even(area.y, area.h, spacing.gap_y, *rows as usize);
+
+                let mut slots = Vec::with_capacity(xs.len() * ys.len());
+                for &(y, h) in &ys {
+                    for &(x, w) in &xs {
+                        slots.push(Slot { x, y, w, h });
                     }
                 }
                 slots
             }
-            Self::Columns(n) => {
-                let n = *n as i32;
-                let total_gap = gap * (n - 1);
-                let col_w = (area.w - total_gap) / n;
-
-                (0..n)
-                    .map(|i| Slot {
-                        x: area.x + i * (col_w + gap),
-                        y: area.y,
-                        w: col_w,
-                        h: area.h,
-                    })
-                    .collect()
-            }
-            Self::Rows(n) => {
-                let n = *n as i32;
-                let total_gap = gap * (n - 1);
-                let row_h = (area.h - total_gap) / n;
-
-                (0..n)
-                    .map(|i| Slot {
-                        x: area.x,
-                        y: area.y + i * (row_h + gap),
-                        w: area.w,
-                        h: row_h,
-                    })
-                    .collect()
+            Self::Columns(n) => split_weighted(area, spacing, &vec![1.0; *n as usize], false),
+            Self::Rows(n) => split_weighted(area, spacing, &vec![1.0; *n as usize], true),
+            Self::LeftRight => split_weighted(area, spacing, &[1.0, 1.0], false),
+            Self::TopBottom => split_weighted(area, spacing, &[1.0, 1.0], true),
+            Self::MainSide {
+                side_count,
+                ratio,
+                side,
+            } => compute_main_side(*side_count, *ratio, *side, area, spacing, &[]),
+            Self::Bsp(cap) => compute_bsp(cap.unwrap_or(DEFAULT_BSP_COUNT), area, spacing),
+            Self::Spiral { count, ratio } => compute_spiral(*count, *ratio, area, spacing),
+            Self::CenterMain { left, right, ratio } => {
+                compute_center_main(*left, *right, *ratio, area, spacing)
             }
-            Self::LeftRight => {
-                let half_w = (area.w - gap) / 2;
-                vec![
-                    Slot {
-                        x: area.x,
-                        y: area.y,
-                        w: half_w,
-                        h: area.h,
-                    },
-                    Slot {
-                        x: area.x + half_w + gap,
-                        y: area.y,
-                        w: half_w,
-                        h: area.h,
-                    },
-                ]
-            }
-            Self::TopBottom => {
-                let half_h = (area.h - gap) / 2;
-                vec![
-                    Slot {
-                        x: area.x,
-                        y: area.y,
-                        w: area.w,
-                        h: half_h,
-                    },
-                    Slot {
-                        x: area.x,
-                        y: area.y + half_h + gap,
-                        w: area.w,
-                        h: half_h,
-                    },
-                ]
-            }
-            Self::MainSide { side_count } => {
-                let side_count = *side_count as i32;
-                let main_w = (area.w - gap) * 2 / 3;
-                let side_w = area.w - main_w - gap;
-                let total_gap_y = gap * (side_count - 1);
-                let side_h = (area.h - total_gap_y) / side_count;
-
-                let mut slots = Vec::with_capacity(1 + side_count as usize);
-                slots.push(Slot {
-                    x: area.x,
-                    y: area.y,
-                    w: main_w,
-                    h: area.h,
-                });
-                for i in 0..side_count {
-                    slots.push(Slot {
-                        x: area.x + main_w + gap,
-                        y: area.y + i * (side_h + gap),
-                        w: side_w,
-                        h: side_h,
-                    });
-                }
-                slots
-            }
-            Self::Focus { side_count } => {
-                let side_count = *side_count as i32;
-                let main_w = (area.w - gap) * 3 / 4;
-                let side_w = area.w - main_w - gap;
-                let total_gap_y = gap * (side_count - 1);
-                let side_h = (area.h - total_gap_y) / side_count;
-
-                let mut slots = Vec::with_capacity(1 + side_count as usize);
-                slots.push(Slot {
-                    x: area.x,
-                    y: area.y,
-                    w: main_w,
-                    h: area.h,
-                });
-                for i in 0..side_count {
-                    slots.push(Slot {
-                        x: area.x + main_w + gap,
-                        y: area.y + i * (side_h + gap),
-                        w: side_w,
-                        h: side_h,
-                    });
-                }
-                slots
+            Self::Free(rects) => rects
+                .iter()
+                .map(|r| r.to_slot(area, spacing.gap_x, spacing.gap_y))
+                .collect(),
+            Self::Template(t) => t.compute_slots(area, spacing),
+        }
+    }
+
+    /// Main-area share for presets that have an adjustable main/side split.
+    pub fn main_ratio(&self) -> Option<f32> {
+        match self {
+            Self::MainSide { ratio, .. } => Some(*ratio),
+            _ => None,
+        }
+    }
+
+    /// Copy of this preset with its main/side split replaced. Presets without
+    /// a main area are returned unchanged.
+    pub fn with_main_ratio(&self, new_ratio: f32) -> Self {
+        match self {
+            Self::MainSide {
+                side_count, side, ..
+            } => Self::MainSide {
+                side_count: *side_count,
+                ratio: new_ratio.clamp(0.1, 0.9),
+                side: *side,
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Number of per-slot weights the preset accepts: one per column or row,
+    /// or one per side-stack slot for main/side. Zero for presets sized by a
+    /// ratio (or, for grids, by column/row weights in `GridShape`).
+    pub fn weight_count(&self) -> usize {
+        match self {
+            Self::Columns(n) | Self::Rows(n) => *n as usize,
+            Self::LeftRight | Self::TopBottom => 2,
+            Self::MainSide { side_count, .. } => *side_count as usize,
+            _ => 0,
+        }
+    }
+
+    /// True when the weighted slots are stacked top to bottom rather than
+    /// laid out side by side.
+    pub fn weights_stack_vertically(&self) -> bool {
+        match self {
+            Self::Rows(_) | Self::TopBottom => true,
+            Self::MainSide { side, .. } => !side.is_horizontal(),
+            _ => false,
+        }
+    }
+
+    /// Like `compute_slots`, with the weighted slots (the last `weight_count`)
+    /// sized by `weights`. A mismatched weight list falls back to even sizes.
+    pub fn compute_weighted_slots(
+        &self,
+        area: &Rect,
+        spacing: &Spacing,
+        weights: &[f32],
+    ) -> Vec<Slot> {
+        let n = self.weight_count();
+        if n == 0 || weights.len() != n {
+            return self.compute_slots(area, spacing);
+        }
+        let area = &spacing.margin.inset(area);
+        let vertical = self.weights_stack_vertically();
+        match self {
+            Self::MainSide {
+                side_count,
+                ratio,
+                side,
+            } => compute_main_side(*side_count, *ratio, *side, area, spacing, weights),
+            _ => split_weighted(area, spacing, weights, vertical),
+        }
+    }
+
+    /// Preset string that `parse` turns back into this preset, used to save
+    /// tuned presets. Freeform layouts have no string form.
+    pub fn spec(&self) -> Option<String> {
+        match self {
+            Self::Grid { cols, rows } => Some(format!("{}x{}", cols, rows)),
+            Self::Columns(n) => Some(format!("columns:{}", n)),
+            Self::Rows(n) => Some(format!("rows:{}", n)),
+            Self::LeftRight => Some("left-right".into()),
+            Self::TopBottom => Some("top-bottom".into()),
+            Self::MainSide {
+                side_count,
+                ratio,
+                side,
+            } => Some(format!(
+                "main-side:{}@{},{}",
+                side_count,
+                ratio,
+                side.display_name().to_lowercase()
+            )),
+            Self::Bsp(None) => Some("bsp".into()),
+            Self::Bsp(Some(n)) => Some(format!("bsp:{}", n)),
+            Self::Spiral { count, ratio } => Some(format!("spiral:{}@{}", count, ratio)),
+            Self::CenterMain { left, right, ratio } => {
+                Some(format!("center-main:{},{}@{}", left, right, ratio))
             }
+            Self::Free(_) => None,
+            Self::Template(t) => Some(t.source.clone()),
         }
     }
 
@@ -237,77 +595,366 @@ impl LayoutPreset {
             Self::Rows(n) => format!("{} Rows", n),
             Self::LeftRight => "Left / Right".to_string(),
             Self::TopBottom => "Top / Bottom".to_string(),
-            Self::MainSide { side_count } => format!("Main + {} Side", side_count),
-            Self::Focus { side_count } => format!("Focus + {} Side", side_count),
+            Self::MainSide {
+                side_count,
+                ratio,
+                side,
+            } => {
+                let name = if (*ratio - FOCUS_MAIN_RATIO).abs() < 0.001 {
+                    "Focus"
+                } else {
+                    "Main"
+                };
+                let mut label = format!("{} + {} Side", name, side_count);
+                if *side != SidePosition::Right {
+                    label.push_str(&format!(" ({})", side.display_name()));
+                }
+                if (*ratio - DEFAULT_MAIN_RATIO).abs() >= 0.001
+                    && (*ratio - FOCUS_MAIN_RATIO).abs() >= 0.001
+                {
+                    label.push_str(&format!(" {:.0}%", ratio * 100.0));
+                }
+                label
+            }
+            Self::Bsp(None) => "BSP".into(),
+            Self::Bsp(Some(n)) => format!("BSP (up to {})", n),
+            Self::Spiral { count, ratio } => {
+                if (*ratio - DEFAULT_SPIRAL_RATIO).abs() < 0.001 {
+                    format!("Spiral {}", count)
+                } else {
+                    format!("Spiral {} ({:.0}%)", count, ratio * 100.0)
+                }
+            }
+            Self::CenterMain { left, right, .. } => format!("Center + {}/{} Side", left, right),
+            Self::Free(rects) => format!("Freeform ({} panes)", rects.len()),
+            Self::Template(t) => format!("Template ({} slots)", t.slot_count()),
         }
     }
 }
 
-/// Compute grid slots with per-column and per-row weight fractions.
-/// Weights are normalized fractions that sum to 1.0.
-pub fn compute_weighted_grid(
-    cols: u32,
-    rows: u32,
+/// Main area taking `ratio` of the work area along one axis, with the side
+/// stack on the given edge. Slot 0 is always the main area. Side slots are
+/// sized by `stack_weights`, or evenly if it doesn't have one per slot.
+fn compute_main_side(
+    side_count: u32,
+    ratio: f32,
+    side: SidePosition,
     area: &Rect,
-    gap: i32,
-    col_weights: &[f32],
-    row_weights: &[f32],
+    spacing: &Spacing,
+    stack_weights: &[f32],
 ) -> Vec<Slot> {
-    let cols = cols as usize;
-    let rows = rows as usize;
+    let main_first = matches!(side, SidePosition::Right | SidePosition::Bottom);
+    let split = if main_first {
+        [ratio, 1.0 - ratio]
+    } else {
+        [1.0 - ratio, ratio]
+    };
+    let parts = split_weighted(area, spacing, &split, side.is_horizontal());
+    let (main, stack) = if main_first {
+        (&parts[0], &parts[1])
+    } else {
+        (&parts[1], &parts[0])
+    };
 
-    let usable_w = area.w - gap * (cols as i32 - 1);
-    let usable_h = area.h - gap * (rows as i32 - 1);
+    let even = vec![1.0; side_count as usize];
+    let weights = if stack_weights.len() == side_count as usize {
+        stack_weights
+    } else {
+        &even
+    };
 
-    // Compute column widths from weights
-    let mut col_widths: Vec<i32> = col_weights
-        .iter()
-        .map(|w| (usable_w as f32 * w) as i32)
-        .collect();
-    // Give leftover pixels to last column
-    let col_sum: i32 = col_widths.iter().sum();
-    if let Some(last) = col_widths.last_mut() {
-        *last += usable_w - col_sum;
+    let mut slots = Vec::with_capacity(1 + side_count as usize);
+    slots.push(main.clone());
+    slots.extend(split_weighted(&stack.rect(), spacing, weights, !side.is_horizontal()));
+    slots
+}
+
+/// Split `area` into `weights.len()` slots along one axis (see `distribute`).
+fn split_weighted(area: &Rect, spacing: &Spacing, weights: &[f32], vertical: bool) -> Vec<Slot> {
+    let gap = spacing.gap(vertical);
+    if vertical {
+        distribute(area.y, area.h, gap, weights)
+            .into_iter()
+            .map(|(y, h)| Slot {
+                x: area.x,
+                y,
+                w: area.w,
+                h,
+            })
+            .collect()
+    } else {
+        distribute(area.x, area.w, gap, weights)
+            .into_iter()
+            .map(|(x, w)| Slot {
+                x,
+                y: area.y,
+                w,
+                h: area.h,
+            })
+            .collect()
     }
+}
 
-    // Compute row heights from weights
-    let mut row_heights: Vec<i32> = row_weights
-        .iter()
-        .map(|w| (usable_h as f32 * w) as i32)
-        .collect();
-    let row_sum: i32 = row_heights.iter().sum();
-    if let Some(last) = row_heights.last_mut() {
-        *last += usable_h - row_sum;
+/// Dwindle-style binary space partition: each window takes half of the
+/// remaining area, splitting along its longer axis, and the last window
+/// gets whatever is left.
+fn compute_bsp(count: u32, area: &Rect, spacing: &Spacing) -> Vec<Slot> {
+    let mut slots = Vec::with_capacity(count as usize);
+    let mut rest = *area;
+
+    for _ in 1..count {
+        let parts = split_weighted(&rest, spacing, &[1.0, 1.0], rest.h > rest.w);
+        slots.push(parts[0].clone());
+        rest = parts[1].rect();
+    }
+    if count > 0 {
+        slots.push(Slot::from(rest));
+    }
+    slots
+}
+
+/// Fibonacci-style spiral: each window takes `ratio` of the remaining area,
+/// cutting from the left, top, right and bottom edges in turn (clockwise).
+/// The last window gets whatever is left.
+fn compute_spiral(count: u32, ratio: f32, area: &Rect, spacing: &Spacing) -> Vec<Slot> {
+    let mut slots = Vec::with_capacity(count as usize);
+    let mut rest = *area;
+
+    for i in 1..count {
+        // Left and top cuts take the first part, right and bottom the last
+        let vertical = i % 2 == 0;
+        let from_end = (i - 1) % 4 >= 2;
+        let weights = if from_end {
+            [1.0 - ratio, ratio]
+        } else {
+            [ratio, 1.0 - ratio]
+        };
+        let parts = split_weighted(&rest, spacing, &weights, vertical);
+        let (taken, left) = if from_end { (1, 0) } else { (0, 1) };
+        slots.push(parts[taken].clone());
+        rest = parts[left].rect();
+    }
+    if count > 0 {
+        slots.push(Slot::from(rest));
     }
+    slots
+}
+
+/// Centered master: slot 0 is a center column taking `ratio` of the width,
+/// followed by the left stack (top to bottom), then the right stack.
+/// An empty stack gives its share of the width to the center column.
+fn compute_center_main(
+    left: u32,
+    right: u32,
+    ratio: f32,
+    area: &Rect,
+    spacing: &Spacing,
+) -> Vec<Slot> {
+    let side_cols = (left > 0) as u32 + (right > 0) as u32;
+    let side_share = (1.0 - ratio) / 2.0;
+    let main_share = 1.0 - side_share * side_cols as f32;
 
-    // Cumulative x positions
-    let mut col_x = Vec::with_capacity(cols);
-    let mut cx = area.x;
-    for (i, &cw) in col_widths.iter().enumerate() {
-        col_x.push(cx);
-        if i + 1 < cols {
-            cx += cw + gap;
+    let mut col_weights = Vec::with_capacity(3);
+    if left > 0 {
+        col_weights.push(side_share);
+    }
+    col_weights.push(main_share);
+    if right > 0 {
+        col_weights.push(side_share);
+    }
+    let cols = split_weighted(area, spacing, &col_weights, false);
+    let main_idx = (left > 0) as usize;
+
+    let mut slots = Vec::with_capacity(1 + left as usize + right as usize);
+    slots.push(cols[main_idx].clone());
+    if left > 0 {
+        slots.extend(split_weighted(&cols[0].rect(), spacing, &vec![1.0; left as usize], true));
+    }
+    if right > 0 {
+        let col = &cols[cols.len() - 1];
+        slots.extend(split_weighted(&col.rect(), spacing, &vec![1.0; right as usize], true));
+    }
+    slots
+}
+
+/// A rectangular block of custom-grid cells merged into a single slot.
+/// `col`/`row` is the top-left cell; spans are counted in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellSpan {
+    pub col: u32,
+    pub row: u32,
+    pub col_span: u32,
+    pub row_span: u32,
+}
+
+impl CellSpan {
+    /// Span covering both cells (inclusive), in any drag direction.
+    pub fn from_corners(a: (u32, u32), b: (u32, u32)) -> Self {
+        Self {
+            col: a.0.min(b.0),
+            row: a.1.min(b.1),
+            col_span: a.0.abs_diff(b.0) + 1,
+            row_span: a.1.abs_diff(b.1) + 1,
         }
     }
 
-    // Cumulative y positions
-    let mut row_y = Vec::with_capacity(rows);
-    let mut cy = area.y;
-    for (i, &rh) in row_heights.iter().enumerate() {
-        row_y.push(cy);
-        if i + 1 < rows {
-            cy += rh + gap;
+    pub fn contains(&self, col: u32, row: u32) -> bool {
+        col >= self.col
+            && col < self.col + self.col_span
+            && row >= self.row
+            && row < self.row + self.row_span
+    }
+
+    pub fn overlaps(&self, other: &CellSpan) -> bool {
+        self.col < other.col + other.col_span
+            && other.col < self.col + self.col_span
+            && self.row < other.row + other.row_span
+            && other.row < self.row + self.row_span
+    }
+
+    fn cell_count(&self) -> u32 {
+        self.col_span * self.row_span
+    }
+}
+
+/// Column/row weights and merged cells for a custom grid, or per-slot
+/// weights for the other presets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridShape {
+    pub col_weights: Vec<f32>,
+    pub row_weights: Vec<f32>,
+    pub merges: Vec<CellSpan>,
+    /// Per-slot weights for non-grid presets (see `LayoutPreset::weight_count`).
+    pub weights: Vec<f32>,
+    /// Gaps and margins for this layout, replacing the configured defaults.
+    pub spacing: Option<Spacing>,
+}
+
+/// Slots for a preset, shaped by column/row weights and merges (grids) or
+/// per-slot weights (other presets) when a shape is given. The shape's own
+/// spacing, if any, replaces `spacing`.
+pub fn compute_shaped_slots(
+    preset: &LayoutPreset,
+    area: &Rect,
+    spacing: &Spacing,
+    shape: Option<&GridShape>,
+) -> Vec<Slot> {
+    match (shape, preset) {
+        (Some(shape), LayoutPreset::Grid { cols, rows }) => compute_weighted_grid(
+            *cols,
+            *rows,
+            area,
+            shape.spacing.as_ref().unwrap_or(spacing),
+            &shape.col_weights,
+            &shape.row_weights,
+            &shape.merges,
+        ),
+        (Some(shape), _) => preset.compute_weighted_slots(
+            area,
+            shape.spacing.as_ref().unwrap_or(spacing),
+            &shape.weights,
+        ),
+        (None, _) => preset.compute_slots(area, spacing),
+    }
+}
+
+/// Normalize user-supplied weights to fractions summing to 1.0. Returns an
+/// empty list (meaning "even sizes") if the count is wrong or any weight is
+/// not positive.
+pub fn normalize_weights(weights: &[f32], count: usize) -> Vec<f32> {
+    if weights.len() != count || weights.iter().any(|w| w.is_nan() || *w <= 0.0) {
+        return Vec::new();
+    }
+    let sum: f32 = weights.iter().sum();
+    weights.iter().map(|w| w / sum).collect()
+}
+
+/// Drop merges that fall outside the grid, cover a single cell, or overlap
+/// an earlier merge.
+pub fn sanitize_merges(cols: u32, rows: u32, merges: &[CellSpan]) -> Vec<CellSpan> {
+    let mut kept: Vec<CellSpan> = Vec::with_capacity(merges.len());
+    for m in merges {
+        let in_bounds = m.col_span > 0
+            && m.row_span > 0
+            && m.col + m.col_span <= cols
+            && m.row + m.row_span <= rows;
+        if in_bounds && m.cell_count() > 1 && !kept.iter().any(|k| k.overlaps(m)) {
+            kept.push(*m);
         }
     }
+    kept
+}
+
+/// Cell index (row-major) of the top-left cell behind each slot emitted by
+/// `compute_weighted_grid`. Cells swallowed by a merge produce no slot.
+pub fn grid_slot_cells(cols: u32, rows: u32, merges: &[CellSpan]) -> Vec<usize> {
+    let merges = sanitize_merges(cols, rows, merges);
+    let mut cells = Vec::with_capacity((cols * rows) as usize);
+    for r in 0..rows {
+        for c in 0..cols {
+            let covered = merges
+                .iter()
+                .any(|m| m.contains(c, r) && (m.col, m.row) != (c, r));
+            if !covered {
+                cells.push((r * cols + c) as usize);
+            }
+        }
+    }
+    cells
+}
+
+/// Compute grid slots with per-column and per-row weight fractions, inside
+/// the spacing's margins.
+/// Weights are normalized fractions that sum to 1.0; mismatched weight
+/// lists fall back to uniform. Merged cells produce one spanning slot at
+/// the position of their top-left cell.
+pub fn compute_weighted_grid(
+    cols: u32,
+    rows: u32,
+    area: &Rect,
+    spacing: &Spacing,
+    col_weights: &[f32],
+    row_weights: &[f32],
+    merges: &[CellSpan],
+) -> Vec<Slot> {
+    let area = &spacing.margin.inset(area);
+    let merges = sanitize_merges(cols, rows, merges);
+    let cols = cols as usize;
+    let rows = rows as usize;
+
+    let uniform_cols = vec![1.0 / cols as f32; cols];
+    let uniform_rows = vec![1.0 / rows as f32; rows];
+    let col_weights = if col_weights.len() == cols {
+        col_weights
+    } else {
+        &uniform_cols
+    };
+    let row_weights = if row_weights.len() == rows {
+        row_weights
+    } else {
+        &uniform_rows
+    };
+
+    let (col_x, col_widths): (Vec<i32>, Vec<i32>) =
+        distribute(area.x, area.w, spacing.gap_x, col_weights).into_iter().unzip();
+    let (row_y, row_heights): (Vec<i32>, Vec<i32>) =
+        distribute(area.y, area.h, spacing.gap_y, row_weights).into_iter().unzip();
 
     let mut slots = Vec::with_capacity(cols * rows);
     for r in 0..rows {
         for c in 0..cols {
+            let span = merges.iter().find(|m| m.contains(c as u32, r as u32));
+            let (last_c, last_r) = match span {
+                // Covered by a merge anchored elsewhere: no slot of its own
+                Some(m) if (m.col as usize, m.row as usize) != (c, r) => continue,
+                Some(m) => (c + m.col_span as usize - 1, r + m.row_span as usize - 1),
+                None => (c, r),
+            };
             slots.push(Slot {
                 x: col_x[c],
                 y: row_y[r],
-                w: col_widths[c],
-                h: row_heights[r],
+                w: col_x[last_c] + col_widths[last_c] - col_x[c],
+                h: row_y[last_r] + row_heights[last_r] - row_y[r],
             });
         }
     }
@@ -329,23 +976,67 @@ pub fn builtin_presets() -> Vec<(String, LayoutPreset)> {
         ("Top / Bottom".into(), LayoutPreset::TopBottom),
         (
             "Main + 2 Side".into(),
-            LayoutPreset::MainSide { side_count: 2 },
+            LayoutPreset::MainSide {
+                side_count: 2,
+                ratio: DEFAULT_MAIN_RATIO,
+                side: SidePosition::Right,
+            },
         ),
         (
             "Main + 3 Side".into(),
-            LayoutPreset::MainSide { side_count: 3 },
+            LayoutPreset::MainSide {
+                side_count: 3,
+                ratio: DEFAULT_MAIN_RATIO,
+                side: SidePosition::Right,
+            },
         ),
         (
             "Main + 4 Side".into(),
-            LayoutPreset::MainSide { side_count: 4 },
+            LayoutPreset::MainSide {
+                side_count: 4,
+                ratio: DEFAULT_MAIN_RATIO,
+                side: SidePosition::Right,
+            },
         ),
         (
             "Focus + 3 Side".into(),
-            LayoutPreset::Focus { side_count: 3 },
+            LayoutPreset::MainSide {
+                side_count: 3,
+                ratio: FOCUS_MAIN_RATIO,
+                side: SidePosition::Right,
+            },
         ),
         (
             "Focus + 4 Side".into(),
-            LayoutPreset::Focus { side_count: 4 },
+            LayoutPreset::MainSide {
+                side_count: 4,
+                ratio: FOCUS_MAIN_RATIO,
+                side: SidePosition::Right,
+            },
+        ),
+        ("BSP".into(), LayoutPreset::Bsp(None)),
+        (
+            "Spiral 6".into(),
+            LayoutPreset::Spiral {
+                count: 6,
+                ratio: DEFAULT_SPIRAL_RATIO,
+            },
+        ),
+        (
+            "Center + 1/1 Side".into(),
+            LayoutPreset::CenterMain {
+                left: 1,
+                right: 1,
+                ratio: DEFAULT_CENTER_RATIO,
+            },
+        ),
+        (
+            "Center + 2/2 Side".into(),
+            LayoutPreset::CenterMain {
+                left: 2,
+                right: 2,
+                ratio: DEFAULT_CENTER_RATIO,
+            },
         ),
         ("2 Columns".into(), LayoutPreset::Columns(2)),
         ("3 Columns".into(), LayoutPreset::Columns(3)),
@@ -354,3 +1045,249 @@ pub fn builtin_presets() -> Vec<(String, LayoutPreset)> {
         ("3 Rows".into(), LayoutPreset::Rows(3)),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &[&str] = &[
+        "h(0.6: main, v(a, b, c))",
+        "v(h(a, b), c, h(0.2: d, e, f))",
+        "h(a, v(b, h(c, v(d, e))))",
+    ];
+
+    /// Xorshift generator, so every run checks the same cases.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Uniform in `lo..=hi`.
+        fn range(&mut self, lo: i32, hi: i32) -> i32 {
+            lo + (self.next() % (hi - lo + 1) as u64) as i32
+        }
+
+        fn float(&mut self, lo: f32, hi: f32) -> f32 {
+            lo + (self.next() % 10_000) as f32 / 10_000.0 * (hi - lo)
+        }
+
+        fn weights(&mut self, n: usize) -> Vec<f32> {
+            (0..n).map(|_| self.float(0.2, 3.0)).collect()
+        }
+    }
+
+    struct Case {
+        preset: LayoutPreset,
+        shape: Option<GridShape>,
+        area: Rect,
+        spacing: Spacing,
+    }
+
+    impl Case {
+        fn slots(&self) -> Vec<Slot> {
+            compute_shaped_slots(&self.preset, &self.area, &self.spacing, self.shape.as_ref())
+        }
+
+        fn inner(&self) -> Rect {
+            self.spacing.margin.inset(&self.area)
+        }
+
+        fn expected_count(&self) -> usize {
+            match (&self.preset, &self.shape) {
+                (LayoutPreset::Grid { cols, rows }, Some(shape)) => {
+                    grid_slot_cells(*cols, *rows, &shape.merges).len()
+                }
+                (preset, _) => preset.slot_count(),
+            }
+        }
+    }
+
+    /// A random preset of every kind but freeform, with up to `max_count`
+    /// windows per stack and ratios in `ratios`.
+    fn random_preset(rng: &mut Rng, max_count: i32, ratios: (f32, f32)) -> LayoutPreset {
+        let n = rng.range(1, max_count) as u32;
+        let ratio = rng.float(ratios.0, ratios.1);
+        match rng.range(0, 9) {
+            0 => LayoutPreset::Grid {
+                cols: rng.range(1, max_count.min(8)) as u32,
+                rows: rng.range(1, max_count.min(8)) as u32,
+            },
+            1 => LayoutPreset::Columns(n),
+            2 => LayoutPreset::Rows(n),
+            3 => LayoutPreset::LeftRight,
+            4 => LayoutPreset::TopBottom,
+            5 => LayoutPreset::MainSide {
+                side_count: n,
+                ratio,
+                side: [
+                    SidePosition::Left,
+                    SidePosition::Right,
+                    SidePosition::Top,
+                    SidePosition::Bottom,
+                ][rng.range(0, 3) as usize],
+            },
+            6 => LayoutPreset::Bsp(Some(n)),
+            7 => LayoutPreset::Spiral { count: n, ratio },
+            8 => LayoutPreset::CenterMain {
+                left: rng.range(0, max_count) as u32,
+                right: rng.range(0, max_count) as u32,
+                ratio,
+            },
+            _ => {
+                let src = TEMPLATES[rng.range(0, TEMPLATES.len() as i32 - 1) as usize];
+                LayoutPreset::Template(template::parse(src).unwrap())
+            }
+        }
+    }
+
+    /// Weights and merges for half the cases, none for the rest.
+    fn random_shape(rng: &mut Rng, preset: &LayoutPreset) -> Option<GridShape> {
+        if rng.range(0, 1) == 0 {
+            return None;
+        }
+        let mut shape = GridShape {
+            weights: rng.weights(preset.weight_count()),
+            ..GridShape::default()
+        };
+        if let LayoutPreset::Grid { cols, rows } = *preset {
+            shape.col_weights = rng.weights(cols as usize);
+            shape.row_weights = rng.weights(rows as usize);
+            let cell = |rng: &mut Rng| {
+                (rng.range(0, cols as i32 - 1) as u32, rng.range(0, rows as i32 - 1) as u32)
+            };
+            for _ in 0..rng.range(0, 3) {
+                let (a, b) = (cell(rng), cell(rng));
+                shape.merges.push(CellSpan::from_corners(a, b));
+            }
+        }
+        Some(shape)
+    }
+
+    fn random_case(
+        rng: &mut Rng,
+        max_count: i32,
+        max_gap: i32,
+        min_size: (i32, i32),
+        ratios: (f32, f32),
+    ) -> Case {
+        let preset = random_preset(rng, max_count, ratios);
+        let shape = random_shape(rng, &preset);
+        let margin = rng.range(0, max_gap);
+        Case {
+            preset,
+            shape,
+            area: Rect {
+                x: rng.range(-2000, 2000),
+                y: rng.range(-500, 500),
+                w: rng.range(min_size.0, 4000),
+                h: rng.range(min_size.1, 2500),
+            },
+            spacing: Spacing {
+                gap_x: rng.range(0, max_gap),
+                gap_y: rng.range(0, max_gap),
+                margin: Margins {
+                    top: margin,
+                    right: rng.range(0, max_gap),
+                    bottom: margin,
+                    left: rng.range(0, max_gap),
+                },
+            },
+        }
+    }
+
+    fn overlap(a: &Slot, b: &Slot) -> bool {
+        a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+    }
+
+    fn assert_inside_and_disjoint(slots: &[Slot], area: &Rect, case: &str) {
+        for (i, s) in slots.iter().enumerate() {
+            assert!(s.w >= 0 && s.h >= 0, "{case}: slot {i} has negative size {s:?}");
+            let inside = s.x >= area.x
+                && s.y >= area.y
+                && s.x + s.w <= area.x + area.w
+                && s.y + s.h <= area.y + area.h;
+            assert!(inside, "{case}: slot {i} {s:?} outside {area:?}");
+            for (j, t) in slots.iter().enumerate().skip(i + 1) {
+                assert!(!overlap(s, t), "{case}: slots {i} {s:?} and {j} {t:?} overlap");
+            }
+        }
+    }
+
+    /// Every slot grown by the gap on its right and bottom (unless it is at
+    /// the area's edge) tiles `area` exactly, i.e. the slots cover it minus
+    /// the gaps.
+    fn assert_tiles(slots: &[Slot], area: &Rect, spacing: &Spacing, case: &str) {
+        let grown: Vec<Slot> = slots
+            .iter()
+            .map(|s| Slot {
+                w: s.w + if s.x + s.w < area.x + area.w { spacing.gap_x } else { 0 },
+                h: s.h + if s.y + s.h < area.y + area.h { spacing.gap_y } else { 0 },
+                ..s.clone()
+            })
+            .collect();
+        assert_inside_and_disjoint(&grown, area, case);
+        let covered: i64 = grown.iter().map(|s| s.w as i64 * s.h as i64).sum();
+        assert_eq!(covered, area.w as i64 * area.h as i64, "{case}: slots leave holes");
+    }
+
+    #[test]
+    fn distribute_tiles_exactly() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..5000 {
+            let start = rng.range(-3000, 3000);
+            let total = rng.range(0, 5000);
+            let gap = rng.range(0, 40);
+            let n = rng.range(1, 50) as usize;
+            let weights = rng.weights(n);
+            let spans = distribute(start, total, gap, &weights);
+            let case = format!("distribute({start}, {total}, {gap}, {weights:?})");
+            assert_eq!(spans.len(), n, "{case}");
+            assert_eq!(spans[0].0, start, "{case}");
+            let (last, size) = spans[n - 1];
+            assert_eq!(last + size, start + total, "{case}");
+            for pair in spans.windows(2) {
+                assert!(pair[0].1 >= 0, "{case}: negative span");
+                assert!(pair[1].0 >= pair[0].0 + pair[0].1, "{case}: spans overlap");
+            }
+        }
+    }
+
+    #[test]
+    fn slots_stay_inside_and_never_overlap() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..3000 {
+            let case = random_case(&mut rng, 40, 40, (1, 1), (0.1, 0.9));
+            let slots = case.slots();
+            let label = format!("{:?} in {:?}", case.preset, case.area);
+            assert_eq!(slots.len(), case.expected_count(), "{label}");
+            assert_inside_and_disjoint(&slots, &case.inner(), &label);
+        }
+    }
+
+    #[test]
+    fn slots_cover_the_area_without_gaps() {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        for _ in 0..3000 {
+            let mut case = random_case(&mut rng, 40, 0, (1, 1), (0.1, 0.9));
+            case.spacing = Spacing::default();
+            let label = format!("{:?} in {:?}", case.preset, case.area);
+            assert_tiles(&case.slots(), &case.inner(), &case.spacing, &label);
+        }
+    }
+
+    #[test]
+    fn slots_cover_the_area_minus_gaps() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        for _ in 0..3000 {
+            // Big enough that every gap fits, so none has to shrink
+            let case = random_case(&mut rng, 6, 16, (1000, 800), (0.25, 0.75));
+            let label = format!("{:?} with {:?} in {:?}", case.preset, case.spacing, case.area);
+            assert_tiles(&case.slots(), &case.inner(), &case.spacing, &label);
+        }
+    }
+}
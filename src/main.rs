@@ -3,23 +3,58 @@
 mod activity;
 mod app;
 mod arrange;
+mod assign;
+mod backend;
 mod config;
+mod constraints;
 mod gui;
+mod history;
+mod launcher;
 mod layout;
 mod monitor;
+mod pinning;
+mod rules;
+mod session;
+mod slot_memory;
+mod template;
 mod theme;
+#[cfg(windows)]
 mod tray;
+mod watch;
 mod windows;
 
 use clap::Parser;
+use std::sync::Arc;
 
 #[derive(Parser)]
 #[command(name = "powershellmanager")]
 #[command(about = "Universal window manager with smart activity-based sorting")]
 struct Cli {
-    /// Apply a layout and exit (e.g., "2x3", "columns:4", "left-right")
+    /// Apply a layout and exit (e.g., "2x3", "columns:4", "left-right", "h(0.6: main, v(a, b))")
     #[arg(long)]
     headless: Option<String>,
+    /// With --headless: print where windows would go without moving them
+    #[arg(long, requires = "headless")]
+    dry_run: bool,
+    /// Output format for --dry-run
+    #[arg(long, value_enum, default_value_t = PlanFormat::Table, requires = "dry_run")]
+    format: PlanFormat,
+    /// With --headless: order windows by stored activity score
+    #[arg(long, requires = "headless")]
+    smart_sort: bool,
+    /// With --headless: arrange the windows described in a scenario TOML file
+    /// (`[[monitor]]` and `[[window]]` tables) instead of the real desktop
+    #[arg(long, requires = "headless")]
+    simulate: Option<std::path::PathBuf>,
+    /// Start the missing apps of a launch profile, arrange them and exit
+    #[arg(long, conflicts_with = "headless")]
+    launch: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum PlanFormat {
+    Table,
+    Json,
 }
 
 fn main() {
@@ -29,55 +64,209 @@ fn main() {
 
     let cli = Cli::parse();
 
-    if let Some(layout_str) = cli.headless {
-        run_headless(&layout_str);
+    if let Some(layout_str) = cli.headless.clone() {
+        run_headless(&layout_str, &cli);
+        return;
+    }
+
+    if let Some(name) = &cli.launch {
+        run_launch(name);
         return;
     }
 
     run_gui();
 }
 
-fn run_headless(layout_str: &str) {
+fn run_launch(name: &str) {
+    let config = config::load();
+    let Some(profile) = config.profile.iter().find(|p| p.name.eq_ignore_ascii_case(name)) else {
+        eprintln!("Unknown launch profile: '{}'", name);
+        let names: Vec<&str> = config.profile.iter().map(|p| p.name.as_str()).collect();
+        if !names.is_empty() {
+            eprintln!("Profiles: {}", names.join(", "));
+        }
+        std::process::exit(1);
+    };
+
+    let backend = backend::native();
+    match launcher::run_profile(backend.as_ref(), profile, &config, 0) {
+        Ok(result) => {
+            println!(
+                "Launched {} apps, arranged {} windows into '{}'",
+                result.launched, result.arranged.arranged, profile.layout
+            );
+            for pin in &result.arranged.pinned {
+                println!("  {}", pin);
+            }
+            if result.missing > 0 {
+                println!("{} apps have no window", result.missing);
+            }
+            for err in &result.arranged.errors {
+                eprintln!("  Error: {}", err);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_headless(layout_str: &str, cli: &Cli) {
     let config = config::load();
 
     let preset = match layout::LayoutPreset::parse(layout_str) {
         Some(p) => p,
+        None if template::looks_like_template(layout_str) => {
+            if let Err(e) = template::parse(layout_str) {
+                eprintln!("{}", e.render(layout_str));
+            }
+            std::process::exit(1);
+        }
         None => {
             eprintln!("Unknown layout: '{}'", layout_str);
-            eprintln!("Examples: 2x3, columns:4, rows:3, left-right, top-bottom, main-side, focus:3");
+            eprintln!("Examples: 2x3, columns:4, rows:3, left-right, top-bottom, main-side, focus:3, bsp:5, spiral:0.6, center-main:2,2, \"h(0.6: main, v(a, b))\"");
             std::process::exit(1);
         }
     };
 
-    let filter = crate::windows::TargetFilter::from_str(&config.defaults.target);
+    let simulated = match &cli.simulate {
+        Some(path) => match backend::fake::FakeBackend::load(path) {
+            Ok(fake) => Some(Arc::new(fake)),
+            Err(e) => {
+                eprintln!("Failed to load scenario '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let backend: Arc<dyn backend::WindowBackend> = match &simulated {
+        Some(fake) => fake.clone(),
+        None => backend::native(),
+    };
+
+    // Slot memory holds real window handles, so simulations don't touch it
+    let persist_slots = config.defaults.persist_slot_memory && simulated.is_none();
+
+    let filter = config.target_filter();
     let disabled = std::collections::HashSet::new();
     let extra_exclude = config.categories.excluded_lower();
-    let result = arrange::arrange_masked(
-        &preset,
-        &filter,
-        &config.defaults.monitor,
-        config.defaults.gap,
-        &disabled,
-        None,
-        0, // no app_hwnd in headless
-        &extra_exclude,
-        false, // no smart sort in headless
-        None,
-        &config.pin,
+    let activity = cli.smart_sort.then(|| {
+        activity::ActivityTracker::new(config.defaults.decay_half_life_days, Arc::clone(&backend))
+    });
+    let plan = arrange::plan_arrangement(
+        backend.as_ref(),
+        &arrange::ArrangeRequest {
+            preset: &preset,
+            grid_shape: None,
+            filter: &filter,
+            monitor_spec: &config.defaults.monitor,
+            spacing: &config.defaults.spacing(),
+            disabled: &disabled,
+            app_hwnd: 0, // no app_hwnd in headless
+            extra_exclude: &extra_exclude,
+            smart_sort: cli.smart_sort,
+            activity: activity.as_ref(),
+            pin_rules: &config.pin,
+            layout_name: Some(layout_str),
+            slot_names: &config.slot_names(Some(layout_str), Some(&preset)),
+            placements: &[],
+            size_rules: &config.size_constraint,
+            persist_slots,
+        },
     );
 
+    if cli.dry_run {
+        match cli.format {
+            PlanFormat::Table => print_plan_table(&plan),
+            PlanFormat::Json => match serde_json::to_string_pretty(&plan) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Error: {}", e),
+            },
+        }
+        return;
+    }
+
+    let result = arrange::execute_plan(backend.as_ref(), &plan, persist_slots);
+
     println!(
         "Arranged {} windows into {} layout ({} slots)",
         result.arranged,
         preset.display_name(),
-        preset.slot_count()
+        preset.sized_for(plan.moves.len() + plan.skipped.len()).slot_count()
     );
+    for pin in &result.pinned {
+        println!("  {}", pin);
+    }
     if result.skipped > 0 {
         println!("Skipped {} windows (not enough slots)", result.skipped);
     }
     for err in &result.errors {
         eprintln!("Error: {}", err);
     }
+
+    if let Some(fake) = simulated {
+        for win in fake.windows() {
+            println!(
+                "  {:>6} {:>6} {:>6} {:>6}  {}{}",
+                win.x,
+                win.y,
+                win.w,
+                win.h,
+                win.process,
+                if win.title.is_empty() {
+                    String::new()
+                } else {
+                    format!(" \u{2014} {}", win.title)
+                }
+            );
+        }
+    }
+}
+
+fn print_plan_table(plan: &arrange::ArrangePlan) {
+    fn clip(s: &str, max: usize) -> String {
+        if s.chars().count() > max {
+            let cut: String = s.chars().take(max - 1).collect();
+            format!("{}…", cut)
+        } else {
+            s.to_string()
+        }
+    }
+
+    println!(
+        "{:>4}  {:>6} {:>6} {:>6} {:>6}  {:<20}  {:<40}  REASON",
+        "SLOT", "X", "Y", "W", "H", "PROCESS", "TITLE"
+    );
+    for mv in &plan.moves {
+        let t = &mv.target;
+        println!(
+            "{:>4}  {:>6} {:>6} {:>6} {:>6}  {:<20}  {:<40}  {}",
+            mv.slot,
+            t.x,
+            t.y,
+            t.w,
+            t.h,
+            clip(&mv.process_name, 20),
+            clip(&mv.title, 40),
+            mv.reason
+        );
+    }
+    for win in &plan.skipped {
+        println!(
+            "{:>4}  {:>6} {:>6} {:>6} {:>6}  {:<20}  {:<40}  skipped (no free slot)",
+            "-",
+            "",
+            "",
+            "",
+            "",
+            clip(&win.process_name, 20),
+            clip(&win.title, 40)
+        );
+    }
+    for err in &plan.errors {
+        eprintln!("Error: {}", err);
+    }
 }
 
 fn load_window_icon() -> Option<egui::IconData> {
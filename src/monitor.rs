@@ -1,9 +1,4 @@
-use windows::Win32::Foundation::{BOOL, LPARAM, RECT, TRUE};
-use windows::Win32::Graphics::Gdi::{
-    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFOEXW,
-};
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -13,67 +8,10 @@ pub struct Rect {
 
 #[derive(Debug, Clone)]
 pub struct MonitorInfo {
-    pub index: usize,
     pub is_primary: bool,
     pub work_area: Rect,
 }
 
-pub fn enumerate_monitors() -> Vec<MonitorInfo> {
-    struct EnumState {
-        monitors: Vec<(HMONITOR, Rect, bool)>,
-    }
-
-    unsafe extern "system" fn enum_callback(
-        hmon: HMONITOR,
-        _hdc: HDC,
-        _rect: *mut RECT,
-        lparam: LPARAM,
-    ) -> BOOL {
-        let state = &mut *(lparam.0 as *mut EnumState);
-
-        let mut info: MONITORINFOEXW = std::mem::zeroed();
-        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
-
-        if GetMonitorInfoW(hmon, &mut info.monitorInfo).as_bool() {
-            let wa = info.monitorInfo.rcWork;
-            let work_area = Rect {
-                x: wa.left,
-                y: wa.top,
-                w: wa.right - wa.left,
-                h: wa.bottom - wa.top,
-            };
-            let is_primary = (info.monitorInfo.dwFlags & 1) != 0; // MONITORINFOF_PRIMARY
-            state.monitors.push((hmon, work_area, is_primary));
-        }
-
-        TRUE
-    }
-
-    let mut state = EnumState {
-        monitors: Vec::with_capacity(4),
-    };
-
-    unsafe {
-        let _ = EnumDisplayMonitors(
-            HDC::default(),
-            None,
-            Some(enum_callback),
-            LPARAM(&mut state as *mut EnumState as isize),
-        );
-    }
-
-    state
-        .monitors
-        .into_iter()
-        .enumerate()
-        .map(|(i, (_hmon, work_area, is_primary))| MonitorInfo {
-            index: i,
-            is_primary,
-            work_area,
-        })
-        .collect()
-}
-
 pub fn resolve_monitor<'a>(monitors: &'a [MonitorInfo], spec: &str) -> &'a MonitorInfo {
     match spec {
         "primary" | "" => monitors
@@ -92,3 +30,17 @@ pub fn resolve_monitor<'a>(monitors: &'a [MonitorInfo], spec: &str) -> &'a Monit
         }
     }
 }
+
+/// Index of the monitor a window overlaps most.
+pub fn monitor_index(monitors: &[MonitorInfo], rect: &Rect) -> usize {
+    let overlap = |area: &Rect| {
+        let w = (rect.x + rect.w).min(area.x + area.w) - rect.x.max(area.x);
+        let h = (rect.y + rect.h).min(area.y + area.h) - rect.y.max(area.y);
+        w.max(0) as i64 * h.max(0) as i64
+    };
+    monitors
+        .iter()
+        .enumerate()
+        .max_by_key(|(i, m)| (overlap(&m.work_area), std::cmp::Reverse(*i)))
+        .map_or(0, |(i, _)| i)
+}
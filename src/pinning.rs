@@ -0,0 +1,80 @@
+use crate::config::PinRule;
+use crate::layout::LayoutPreset;
+use crate::windows::ManagedWindow;
+
+/// Where a pin rule put a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pin {
+    /// Index into the config's pin list.
+    pub rule: usize,
+    /// Index into the enabled slots.
+    pub slot: usize,
+}
+
+/// The pin of every window in `windows`, which are in priority order. Rules
+/// scoped to a different layout than `layout` are ignored; the rest go by
+/// priority, ties in config order, and each takes its matching windows into
+/// its first free slots until it runs out of slots or reaches `max_windows`.
+/// Slots marked in `taken` (one entry per enabled slot, as are `slot_names`)
+/// are skipped, and the slots pinned here are marked.
+pub fn resolve(
+    rules: &[PinRule],
+    layout: Option<&str>,
+    preset: &LayoutPreset,
+    slot_names: &[String],
+    windows: &[&ManagedWindow],
+    taken: &mut [bool],
+) -> Vec<Option<Pin>> {
+    let slot_count = taken.len();
+    let mut pins: Vec<Option<Pin>> = vec![None; windows.len()];
+
+    let mut order: Vec<usize> =
+        (0..rules.len()).filter(|&r| rules[r].applies_to(layout, preset)).collect();
+    order.sort_by_key(|&r| std::cmp::Reverse(rules[r].priority));
+    for r in order {
+        let rule = &rules[r];
+        let slots = rule.slot_indices(slot_names);
+        let max_windows = rule.max_windows.unwrap_or(slots.len());
+        let mut pinned = 0;
+        for (i, win) in windows.iter().enumerate() {
+            if pins[i].is_some() || !rule.matches(win) {
+                continue;
+            }
+            if !slots.iter().any(|&s| s < slot_count) {
+                log::warn!(
+                    "Pin rule #{}: the layout has no {} (it has {} slots)",
+                    r + 1,
+                    rule.slots_label().to_lowercase(),
+                    slot_count
+                );
+                break;
+            }
+            if pinned >= max_windows {
+                break;
+            }
+            let Some(&slot) = slots.iter().find(|&&s| s < slot_count && !taken[s]) else {
+                break;
+            };
+            taken[slot] = true;
+            pins[i] = Some(Pin { rule: r, slot });
+            pinned += 1;
+        }
+    }
+    pins
+}
+
+/// Whether a pin rule for `preset` holds `win` in enabled slot `slot`.
+pub fn is_pinned_to(
+    rules: &[PinRule],
+    layout: Option<&str>,
+    preset: &LayoutPreset,
+    slot_names: &[String],
+    win: &ManagedWindow,
+    slot: usize,
+) -> bool {
+    rules.iter().any(|rule| {
+        rule.applies_to(layout, preset)
+            && rule.matches(win)
+            && rule.slot_indices(slot_names).contains(&slot)
+    })
+}
@@ -0,0 +1,238 @@
+//! Window matching shared by pins, excludes and targeting. A [`Rule`] is a
+//! TOML table of conditions, e.g.
+//!
+//! ```toml
+//! [[exclude]]
+//! category = "chat"
+//! not = { title = "standup|1:1" }
+//! ```
+//!
+//! Every condition set in a table must hold. `any` holds if one of its rules
+//! matches, `all` if every one does, and `not` if its rule doesn't, so other
+//! combinations are written by nesting tables. An empty rule matches every
+//! window.
+
+use crate::windows::{AppCategory, ManagedWindow};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Process name, e.g. `pwsh.exe`.
+    #[serde(default)]
+    pub process: Option<String>,
+    /// Regex searched for in the window title.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Regex searched for in the window class (X11 `WM_CLASS`, sway app id).
+    #[serde(default)]
+    pub class: Option<String>,
+    /// Category name, e.g. `terminal` or `devtool`.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Glob on the executable path, ignoring case: `*` and `?` stay within
+    /// a directory, `**` spans directories, and `/` also matches `\`.
+    #[serde(default)]
+    pub exe: Option<String>,
+    /// Index of the monitor the window is mostly on.
+    #[serde(default)]
+    pub monitor: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all: Vec<Rule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<Rule>,
+    #[serde(default)]
+    pub not: Option<Box<Rule>>,
+}
+
+impl Rule {
+    /// Check if a window matches. Matching is case-insensitive throughout;
+    /// a rule with an error anywhere in it (see [`Rule::errors`]) never
+    /// matches, even where a broken part sits under `not`.
+    pub fn matches(&self, win: &ManagedWindow) -> bool {
+        self.is_valid() && self.holds(win)
+    }
+
+    fn holds(&self, win: &ManagedWindow) -> bool {
+        self.process
+            .as_ref()
+            .is_none_or(|p| win.process_name.eq_ignore_ascii_case(p))
+            && self.title.as_ref().is_none_or(|t| is_match(t, &win.title))
+            && self.class.as_ref().is_none_or(|c| is_match(c, &win.class_name))
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|c| win.category.display_name().eq_ignore_ascii_case(c))
+            && self
+                .exe
+                .as_ref()
+                .is_none_or(|g| is_match(&glob_to_regex(g), &win.exe_path.replace('\\', "/")))
+            && self.monitor.is_none_or(|m| win.monitor == m)
+            && self.all.iter().all(|r| r.holds(win))
+            && (self.any.is_empty() || self.any.iter().any(|r| r.holds(win)))
+            && self.not.as_ref().is_none_or(|r| !r.holds(win))
+    }
+
+    /// Like `errors().is_empty()`, but through the regex cache.
+    fn is_valid(&self) -> bool {
+        let patterns_ok = [&self.title, &self.class].into_iter().flatten().all(|p| compiles(p));
+        let category_ok = self.category.as_ref().is_none_or(|c| AppCategory::parse(c).is_some());
+        patterns_ok
+            && category_ok
+            && self.all.iter().chain(&self.any).chain(self.not.as_deref()).all(Rule::is_valid)
+    }
+
+    /// What would keep this rule from working as written: invalid regexes
+    /// and unknown categories, in this rule and the ones nested in it.
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.collect_errors(&mut errors);
+        errors
+    }
+
+    fn collect_errors(&self, errors: &mut Vec<String>) {
+        for (field, pattern) in [("title", &self.title), ("class", &self.class)] {
+            let Some(pattern) = pattern else {
+                continue;
+            };
+            if let Err(e) = compile(pattern) {
+                errors.push(format!("invalid {} regex: {}", field, e));
+            }
+        }
+        if let Some(category) = &self.category {
+            if AppCategory::parse(category).is_none() {
+                errors.push(format!("unknown category '{}'", category));
+            }
+        }
+        for rule in self.all.iter().chain(&self.any).chain(self.not.as_deref()) {
+            rule.collect_errors(errors);
+        }
+    }
+}
+
+/// Compiled patterns by source, so matching a rule doesn't recompile.
+fn cache() -> &'static Mutex<HashMap<String, Option<Regex>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+fn with_regex(pattern: &str, f: impl FnOnce(Option<&Regex>) -> bool) -> bool {
+    let Ok(mut cache) = cache().lock() else {
+        return false;
+    };
+    f(cache
+        .entry(pattern.to_string())
+        .or_insert_with(|| compile(pattern).ok())
+        .as_ref())
+}
+
+fn is_match(pattern: &str, text: &str) -> bool {
+    with_regex(pattern, |re| re.is_some_and(|re| re.is_match(text)))
+}
+
+fn compiles(pattern: &str) -> bool {
+    with_regex(pattern, |re| re.is_some())
+}
+
+/// Anchored, case-insensitive regex for a path glob, with `\` in the glob
+/// read as `/`.
+fn glob_to_regex(glob: &str) -> String {
+    let glob = glob.replace('\\', "/");
+    let mut out = String::from("(?i)^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                out.push_str(".*");
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out.push('$');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::Rect;
+    use crate::windows::categorize_process;
+
+    fn window(process: &str, title: &str) -> ManagedWindow {
+        ManagedWindow {
+            hwnd: 1,
+            title: title.to_string(),
+            process_name: process.to_string(),
+            class_name: String::new(),
+            exe_path: String::new(),
+            category: categorize_process(process),
+            rect: Rect { x: 0, y: 0, w: 800, h: 600 },
+            monitor: 0,
+            is_minimized: false,
+        }
+    }
+
+    fn rule(toml: &str) -> Rule {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn nested_conditions() {
+        let r = rule(
+            r#"
+            category = "terminal"
+            not = { title = "admin" }
+            any = [{ process = "pwsh.exe" }, { process = "windowsterminal.exe" }]
+            "#,
+        );
+        assert!(r.matches(&window("pwsh.exe", "~/src")));
+        assert!(!r.matches(&window("pwsh.exe", "Administrator: pwsh")));
+        assert!(!r.matches(&window("xterm", "~/src")));
+    }
+
+    #[test]
+    fn exe_globs_match_paths() {
+        let matches = |path: &str, glob: &str| {
+            let mut win = window("pwsh.exe", "~/src");
+            win.exe_path = path.into();
+            rule(&format!("exe = '{}'", glob)).matches(&win)
+        };
+        let pwsh = r"C:\Program Files\PowerShell\7\pwsh.exe";
+
+        assert!(matches(pwsh, r"C:\Program Files\PowerShell\*\pwsh.exe"));
+        assert!(matches(pwsh, "c:/program files/powershell/?/PWSH.EXE"));
+        assert!(matches(pwsh, "**/pwsh.exe"));
+        assert!(matches(pwsh, "C:/Program Files/**"));
+        // A single star stays within one directory
+        assert!(!matches(pwsh, "C:/Program Files/*/pwsh.exe"));
+        assert!(!matches(pwsh, "C:/*.exe"));
+        assert!(!matches(pwsh, "pwsh.exe"));
+
+        assert!(matches("/usr/bin/pwsh", "/usr/*/pwsh"));
+        assert!(!matches("/usr/local/bin/pwsh", "/usr/*/pwsh"));
+    }
+
+    #[test]
+    fn broken_rules_fail_closed() {
+        let win = window("pwsh.exe", "~/src");
+        for toml in [
+            r#"title = "(""#,
+            r#"not = { title = "(" }"#,
+            r#"not = { category = "nonsense" }"#,
+            r#"any = [{ process = "pwsh.exe" }, { class = "[" }]"#,
+        ] {
+            let r = rule(toml);
+            assert!(!r.errors().is_empty(), "{}", toml);
+            assert!(!r.matches(&win), "{}", toml);
+        }
+    }
+}
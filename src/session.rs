@@ -0,0 +1,349 @@
+use crate::backend::WindowBackend;
+use crate::config::{Session, SessionWindow};
+use crate::history;
+use crate::monitor::Rect;
+use crate::windows::{ManagedWindow, TargetFilter};
+
+pub struct RestoreResult {
+    pub restored: usize,
+    /// Saved windows with no matching window open.
+    pub missing: usize,
+}
+
+/// Record every managed window's placement, minimized state and stacking
+/// order under `name`.
+pub fn capture(
+    backend: &dyn WindowBackend,
+    name: &str,
+    filter: &TargetFilter,
+    app_hwnd: isize,
+    extra_exclude: &[String],
+) -> Session {
+    let monitors = backend.monitors();
+    let windows = backend.enumerate_windows(filter, app_hwnd, extra_exclude);
+
+    let mut saved: Vec<SessionWindow> = Vec::with_capacity(windows.len());
+    for win in &windows {
+        let ordinal = saved
+            .iter()
+            .filter(|s| s.matches(&win.process_name, &win.title))
+            .count();
+        let origin = monitors.get(win.monitor).map_or((0, 0), |m| (m.work_area.x, m.work_area.y));
+        saved.push(SessionWindow {
+            process: win.process_name.clone(),
+            title_contains: Some(win.title.clone()).filter(|t| !t.is_empty()),
+            ordinal,
+            monitor: win.monitor,
+            x: win.rect.x - origin.0,
+            y: win.rect.y - origin.1,
+            w: win.rect.w,
+            h: win.rect.h,
+            minimized: win.is_minimized,
+        });
+    }
+
+    Session {
+        name: name.to_string(),
+        window: saved,
+    }
+}
+
+/// Put the session's windows back where they were. Each saved window takes
+/// the open window with its process and title at the same ordinal, or else
+/// any unclaimed window of the same process. Undoable like an arrangement.
+pub fn restore(
+    backend: &dyn WindowBackend,
+    session: &Session,
+    filter: &TargetFilter,
+    app_hwnd: isize,
+    extra_exclude: &[String],
+) -> RestoreResult {
+    let monitors = backend.monitors();
+    if monitors.is_empty() {
+        return RestoreResult {
+            restored: 0,
+            missing: session.window.len(),
+        };
+    }
+    let windows = backend.enumerate_windows(filter, app_hwnd, extra_exclude);
+    let matched = match_windows(&session.window, &windows);
+
+    let hwnds: Vec<isize> = matched.iter().flatten().map(|&i| windows[i].hwnd).collect();
+    history::record(backend, &hwnds);
+
+    // Bottom of the stack first, so raising each one rebuilds the order.
+    // Where raising focuses, only the topmost is raised, once at the end.
+    let replay = !backend.raise_focuses();
+    let mut topmost = None;
+    let mut restored = 0;
+    for (saved, found) in session.window.iter().zip(&matched).rev() {
+        let Some(i) = *found else { continue };
+        let win = &windows[i];
+
+        // A minimized window keeps the spot it had before
+        if saved.minimized {
+            if !win.is_minimized {
+                backend.minimize(win.hwnd);
+            }
+            restored += 1;
+            continue;
+        }
+        if win.is_minimized {
+            backend.restore(win.hwnd);
+        }
+
+        let area = monitors.get(saved.monitor).unwrap_or(&monitors[0]).work_area;
+        let rect = Rect {
+            x: area.x + saved.x,
+            y: area.y + saved.y,
+            w: saved.w,
+            h: saved.h,
+        };
+        match backend.move_window(win.hwnd, &rect) {
+            Ok(()) => {
+                if replay {
+                    backend.raise(win.hwnd);
+                }
+                topmost = Some(win.hwnd);
+                restored += 1;
+            }
+            Err(e) => log::warn!("Failed to restore '{}': {}", win.title, e),
+        }
+    }
+    if let Some(hwnd) = topmost.filter(|_| !replay) {
+        backend.raise(hwnd);
+    }
+
+    RestoreResult {
+        restored,
+        missing: matched.iter().filter(|m| m.is_none()).count(),
+    }
+}
+
+/// Index into `windows` for every saved window.
+fn match_windows(saved: &[SessionWindow], windows: &[ManagedWindow]) -> Vec<Option<usize>> {
+    let mut claimed = vec![false; windows.len()];
+    let mut matched = vec![None; saved.len()];
+
+    // Same process and title at the same ordinal
+    for (s, m) in saved.iter().zip(matched.iter_mut()) {
+        let nth = windows
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| s.matches(&w.process_name, &w.title))
+            .nth(s.ordinal)
+            .map(|(i, _)| i);
+        if let Some(i) = nth.filter(|&i| !claimed[i]) {
+            claimed[i] = true;
+            *m = Some(i);
+        }
+    }
+
+    // Titles change (a terminal's working directory, a browser tab), so
+    // fall back to any window of the same process
+    for (s, m) in saved.iter().zip(matched.iter_mut()) {
+        if m.is_some() {
+            continue;
+        }
+        let any = (0..windows.len()).find(|&i| {
+            !claimed[i] && windows[i].process_name.eq_ignore_ascii_case(&s.process)
+        });
+        if let Some(i) = any {
+            claimed[i] = true;
+            *m = Some(i);
+        }
+    }
+
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, Scenario};
+
+    const DESKTOP: &str = r#"
+        [[monitor]]
+        x = 0
+        y = 0
+        w = 1200
+        h = 800
+        primary = true
+
+        [[window]]
+        hwnd = 1
+        process = "pwsh.exe"
+        title = "~/api"
+        x = 0
+        y = 0
+        w = 600
+        h = 800
+
+        [[window]]
+        hwnd = 2
+        process = "pwsh.exe"
+        title = "~/web"
+        x = 600
+        y = 0
+        w = 600
+        h = 800
+
+        [[window]]
+        hwnd = 3
+        process = "code.exe"
+        title = "main.rs"
+        x = 0
+        y = 0
+        w = 1200
+        h = 800
+    "#;
+
+    fn desktop() -> FakeBackend {
+        FakeBackend::new(toml::from_str::<Scenario>(DESKTOP).unwrap())
+    }
+
+    fn session(toml: &str) -> Session {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn matched(fake: &FakeBackend, session: &Session) -> Vec<Option<usize>> {
+        let windows = fake.enumerate_windows(&TargetFilter::Universal, 0, &[]);
+        match_windows(&session.window, &windows)
+    }
+
+    #[test]
+    fn windows_of_one_process_match_by_ordinal() {
+        let saved = session(
+            r#"
+            name = "work"
+            [[window]]
+            process = "PWSH.EXE"
+            title_contains = "~/"
+            ordinal = 1
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            title_contains = "~/"
+            x = 600
+            y = 0
+            w = 600
+            h = 800
+            "#,
+        );
+        assert_eq!(matched(&desktop(), &saved), vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn changed_titles_fall_back_to_the_process() {
+        let saved = session(
+            r#"
+            name = "work"
+            [[window]]
+            process = "pwsh.exe"
+            title_contains = "~/docs"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            title_contains = "~/web"
+            x = 600
+            y = 0
+            w = 600
+            h = 800
+            "#,
+        );
+        // The exact match claims its window before the fallback runs
+        assert_eq!(matched(&desktop(), &saved), vec![Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn saved_windows_without_a_live_one_stay_unmatched() {
+        let saved = session(
+            r#"
+            name = "work"
+            [[window]]
+            process = "firefox"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            ordinal = 2
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            "#,
+        );
+        assert_eq!(matched(&desktop(), &saved), vec![None, Some(1), Some(0), None, None]);
+    }
+
+    #[test]
+    fn restore_rebuilds_the_stacking_order() {
+        let _turn = history::fresh();
+        let fake = desktop();
+        let saved = session(
+            r#"
+            name = "work"
+            [[window]]
+            process = "code.exe"
+            x = 0
+            y = 0
+            w = 600
+            h = 400
+            [[window]]
+            process = "pwsh.exe"
+            title_contains = "web"
+            x = 600
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "firefox"
+            x = 0
+            y = 0
+            w = 600
+            h = 800
+            [[window]]
+            process = "pwsh.exe"
+            title_contains = "api"
+            x = 0
+            y = 400
+            w = 600
+            h = 400
+            "#,
+        );
+        let result = restore(&fake, &saved, &TargetFilter::Universal, 0, &[]);
+        assert_eq!((result.restored, result.missing), (3, 1));
+
+        let stack: Vec<isize> = fake.windows().iter().map(|w| w.hwnd).collect();
+        assert_eq!(stack, vec![3, 2, 1]);
+        let rect = |hwnd| fake.window_rect(hwnd).unwrap();
+        assert_eq!(rect(3), Rect { x: 0, y: 0, w: 600, h: 400 });
+        assert_eq!(rect(1), Rect { x: 0, y: 400, w: 600, h: 400 });
+    }
+}
@@ -0,0 +1,106 @@
+use crate::layout::LayoutPreset;
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+
+/// A window and the slot it was last arranged into.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotEntry {
+    pub hwnd: isize,
+    /// Guards against window handles reused by a different process.
+    pub process: String,
+    /// Index into the layout's full slot list (disabled cells included).
+    pub slot: usize,
+}
+
+/// The last arrangement, so re-applying the same layout keeps windows put.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SlotMemory {
+    #[serde(default)]
+    layout: String,
+    #[serde(default)]
+    entry: Vec<SlotEntry>,
+}
+
+#[derive(Default)]
+struct State {
+    memory: SlotMemory,
+    disk_loaded: bool,
+}
+
+fn state() -> &'static Mutex<State> {
+    static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(State::default()))
+}
+
+/// Identifies a layout for slot memory: the preset, its slot count and the
+/// monitor it goes on.
+pub fn layout_key(preset: &LayoutPreset, slot_count: usize, monitor_spec: &str) -> String {
+    let name = preset.spec().unwrap_or_else(|| preset.display_name());
+    format!("{}|{}|{}", name, slot_count, monitor_spec)
+}
+
+/// Where windows went the last time `layout` was applied. Empty if a
+/// different layout was applied since. With `persist`, the memory is read
+/// from disk the first time so it survives restarts.
+pub fn recall(layout: &str, persist: bool) -> Vec<SlotEntry> {
+    let Ok(mut state) = state().lock() else {
+        return Vec::new();
+    };
+    if persist && !state.disk_loaded {
+        state.disk_loaded = true;
+        if state.memory.entry.is_empty() {
+            state.memory = load();
+        }
+    }
+    if state.memory.layout == layout {
+        state.memory.entry.clone()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Replace the memory with the arrangement just made.
+pub fn remember(layout: &str, entries: Vec<SlotEntry>, persist: bool) {
+    let Ok(mut state) = state().lock() else {
+        return;
+    };
+    state.memory = SlotMemory {
+        layout: layout.to_string(),
+        entry: entries,
+    };
+    if persist {
+        save(&state.memory);
+    }
+}
+
+fn load() -> SlotMemory {
+    if let Some(path) = crate::config::slot_memory_path() {
+        if path.exists() {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                if let Ok(memory) = toml::from_str::<SlotMemory>(&content) {
+                    log::info!("Loaded slot memory from {}", path.display());
+                    return memory;
+                } else {
+                    log::warn!("Failed to parse slot memory");
+                }
+            }
+        }
+    }
+    SlotMemory::default()
+}
+
+fn save(memory: &SlotMemory) {
+    if let Some(path) = crate::config::slot_memory_path() {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match toml::to_string_pretty(memory) {
+            Ok(content) => {
+                if let Err(e) = std::fs::write(&path, &content) {
+                    log::warn!("Failed to save slot memory: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to serialize slot memory: {}", e),
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use crate::layout::{distribute, Slot, Spacing};
+use crate::monitor::Rect;
+use std::fmt;
+use std::ops::Range;
+
+/// A nested-split layout parsed from an expression like
+/// `h(0.6: main, v(a, b, c))`.
+///
+/// `h(...)` lays its children out side by side, `v(...)` stacks them.
+/// A child may be prefixed with a weight (`0.6: main`); children without one
+/// share whatever the weighted children leave over. Leaves are slot names,
+/// numbered in the order they appear. `_` is an anonymous slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutTemplate {
+    pub source: String,
+    pub root: TemplateNode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateNode {
+    Leaf(String),
+    Split {
+        dir: SplitDir,
+        children: Vec<(Option<f32>, TemplateNode)>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDir {
+    /// Children side by side, left to right.
+    Horizontal,
+    /// Children stacked, top to bottom.
+    Vertical,
+}
+
+/// A template parse error with the byte range of the offending input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl TemplateError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Multi-line report: the message, the source, and a caret line under the span.
+    pub fn render(&self, source: &str) -> String {
+        let start = source[..self.span.start.min(source.len())].chars().count();
+        let len = source
+            .get(self.span.clone())
+            .map(|s| s.chars().count())
+            .unwrap_or(0)
+            .max(1);
+        format!(
+            "{}\n  {}\n  {}{}",
+            self,
+            source,
+            " ".repeat(start),
+            "^".repeat(len)
+        )
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "layout template error at {}..{}: {}",
+            self.span.start, self.span.end, self.message
+        )
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// True if the string is meant as a template rather than a preset name,
+/// so callers can report template errors instead of "unknown layout".
+pub fn looks_like_template(s: &str) -> bool {
+    let s = s.trim_start().to_lowercase();
+    s.starts_with("h(") || s.starts_with("v(")
+}
+
+pub fn parse(source: &str) -> Result<LayoutTemplate, TemplateError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: source.len(),
+        leaf_spans: Vec::new(),
+    };
+    let root = parser.node()?;
+    if let Some(tok) = parser.peek() {
+        return Err(TemplateError::new(
+            format!("unexpected {} after the layout", tok.kind.describe()),
+            tok.span.clone(),
+        ));
+    }
+
+    let template = LayoutTemplate {
+        source: source.trim().to_string(),
+        root,
+    };
+    check_names(&parser.leaf_spans, &template.slot_names())?;
+    Ok(template)
+}
+
+impl LayoutTemplate {
+    pub fn slot_count(&self) -> usize {
+        self.slot_names().len()
+    }
+
+    /// Leaf names in slot order (`_` for anonymous slots).
+    pub fn slot_names(&self) -> Vec<String> {
+        fn walk(node: &TemplateNode, out: &mut Vec<String>) {
+            match node {
+                TemplateNode::Leaf(name) => out.push(name.clone()),
+                TemplateNode::Split { children, .. } => {
+                    for (_, child) in children {
+                        walk(child, out);
+                    }
+                }
+            }
+        }
+        let mut names = Vec::new();
+        walk(&self.root, &mut names);
+        names
+    }
+
+    /// Slots filling `area`, which the caller has already inset by the margins.
+    pub fn compute_slots(&self, area: &Rect, spacing: &Spacing) -> Vec<Slot> {
+        let mut slots = Vec::with_capacity(self.slot_count());
+        place(&self.root, *area, spacing, &mut slots);
+        slots
+    }
+}
+
+fn place(node: &TemplateNode, area: Rect, spacing: &Spacing, out: &mut Vec<Slot>) {
+    match node {
+        TemplateNode::Leaf(_) => out.push(Slot {
+            x: area.x,
+            y: area.y,
+            w: area.w,
+            h: area.h,
+        }),
+        TemplateNode::Split { dir, children } => {
+            let weights = resolve_weights(children);
+            let spans = match dir {
+                SplitDir::Horizontal => distribute(area.x, area.w, spacing.gap_x, &weights),
+                SplitDir::Vertical => distribute(area.y, area.h, spacing.gap_y, &weights),
+            };
+            for ((_, child), (pos, size)) in children.iter().zip(spans) {
+                let child_area = match dir {
+                    SplitDir::Horizontal => Rect {
+                        x: pos,
+                        y: area.y,
+                        w: size,
+                        h: area.h,
+                    },
+                    SplitDir::Vertical => Rect {
+                        x: area.x,
+                        y: pos,
+                        w: area.w,
+                        h: size,
+                    },
+                };
+                place(child, child_area, spacing, out);
+            }
+        }
+    }
+}
+
+/// Normalized weights for a split's children. Unweighted children share what
+/// the weighted ones leave (or get the average weight if nothing is left).
+fn resolve_weights(children: &[(Option<f32>, TemplateNode)]) -> Vec<f32> {
+    let given: f32 = children.iter().filter_map(|(w, _)| *w).sum();
+    let given_count = children.iter().filter(|(w, _)| w.is_some()).count();
+    let free_count = children.len() - given_count;
+
+    let fill = if free_count == 0 {
+        0.0
+    } else if given < 1.0 {
+        (1.0 - given) / free_count as f32
+    } else {
+        given / given_count as f32
+    };
+
+    let raw: Vec<f32> = children.iter().map(|(w, _)| w.unwrap_or(fill)).collect();
+    let total: f32 = raw.iter().sum();
+    raw.iter().map(|w| w / total).collect()
+}
+
+fn check_names(spans: &[Range<usize>], names: &[String]) -> Result<(), TemplateError> {
+    for (i, name) in names.iter().enumerate() {
+        if name == "_" {
+            continue;
+        }
+        if names[..i].contains(name) {
+            return Err(TemplateError::new(
+                format!("duplicate slot name '{}'", name),
+                spans[i].clone(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// --- Tokenizer ---
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(f32),
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+}
+
+impl TokenKind {
+    fn describe(&self) -> String {
+        match self {
+            Self::Ident(s) => format!("name '{}'", s),
+            Self::Number(n) => format!("number {}", n),
+            Self::LParen => "'('".into(),
+            Self::RParen => "')'".into(),
+            Self::Comma => "','".into(),
+            Self::Colon => "':'".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let single = match c {
+            '(' => Some(TokenKind::LParen),
+            ')' => Some(TokenKind::RParen),
+            ',' => Some(TokenKind::Comma),
+            ':' => Some(TokenKind::Colon),
+            _ => None,
+        };
+        if let Some(kind) = single {
+            chars.next();
+            tokens.push(Token {
+                kind,
+                span: start..start + 1,
+            });
+            continue;
+        }
+
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, d)) = chars.peek() {
+                if d.is_ascii_digit() || d == '.' {
+                    end = i + d.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let text = &source[start..end];
+            let n = text.parse::<f32>().map_err(|_| {
+                TemplateError::new(format!("invalid number '{}'", text), start..end)
+            })?;
+            tokens.push(Token {
+                kind: TokenKind::Number(n),
+                span: start..end,
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, d)) = chars.peek() {
+                if d.is_alphanumeric() || d == '_' || d == '-' {
+                    end = i + d.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(source[start..end].to_string()),
+                span: start..end,
+            });
+        } else {
+            return Err(TemplateError::new(
+                format!("unexpected character '{}'", c),
+                start..start + c.len_utf8(),
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+// --- Parser ---
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Byte length of the source, for errors at end of input.
+    end: usize,
+    /// Span of each leaf, in slot order.
+    leaf_spans: Vec<Range<usize>>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn eof_error(&self, expected: &str) -> TemplateError {
+        TemplateError::new(
+            format!("expected {}, found end of input", expected),
+            self.end..self.end,
+        )
+    }
+
+    fn node(&mut self) -> Result<TemplateNode, TemplateError> {
+        let tok = self
+            .next()
+            .ok_or_else(|| self.eof_error("a slot name or split"))?;
+        let name = match tok.kind {
+            TokenKind::Ident(name) => name,
+            other => {
+                return Err(TemplateError::new(
+                    format!("expected a slot name or split, found {}", other.describe()),
+                    tok.span,
+                ))
+            }
+        };
+
+        if !matches!(self.peek().map(|t| &t.kind), Some(TokenKind::LParen)) {
+            self.leaf_spans.push(tok.span);
+            return Ok(TemplateNode::Leaf(name));
+        }
+
+        let dir = match name.to_lowercase().as_str() {
+            "h" => SplitDir::Horizontal,
+            "v" => SplitDir::Vertical,
+            _ => {
+                return Err(TemplateError::new(
+                    format!("unknown split '{}', expected 'h' or 'v'", name),
+                    tok.span,
+                ))
+            }
+        };
+        let open = self.next().map(|t| t.span).unwrap_or(tok.span);
+
+        let mut children = Vec::new();
+        loop {
+            if let Some(t) = self.peek() {
+                if t.kind == TokenKind::RParen && children.is_empty() {
+                    return Err(TemplateError::new(
+                        "split needs at least one child",
+                        open.start..t.span.end,
+                    ));
+                }
+            }
+            children.push(self.item()?);
+
+            let sep = self.next().ok_or_else(|| self.eof_error("',' or ')'"))?;
+            match sep.kind {
+                TokenKind::Comma => continue,
+                TokenKind::RParen => break,
+                other => {
+                    return Err(TemplateError::new(
+                        format!("expected ',' or ')', found {}", other.describe()),
+                        sep.span,
+                    ))
+                }
+            }
+        }
+        Ok(TemplateNode::Split { dir, children })
+    }
+
+    /// `[weight ':'] node`
+    fn item(&mut self) -> Result<(Option<f32>, TemplateNode), TemplateError> {
+        let weight = match self.peek() {
+            Some(Token {
+                kind: TokenKind::Number(n),
+                span,
+            }) => {
+                let (n, span) = (*n, span.clone());
+                self.pos += 1;
+                if n <= 0.0 {
+                    return Err(TemplateError::new("weight must be greater than zero", span));
+                }
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Colon,
+                        ..
+                    }) => {}
+                    Some(t) => {
+                        return Err(TemplateError::new(
+                            format!("expected ':' after weight, found {}", t.kind.describe()),
+                            t.span,
+                        ))
+                    }
+                    None => return Err(self.eof_error("':' after weight")),
+                }
+                Some(n)
+            }
+            _ => None,
+        };
+        Ok((weight, self.node()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> (String, Range<usize>) {
+        let e = parse(source).unwrap_err();
+        (e.message, e.span)
+    }
+
+    #[test]
+    fn nested_splits_name_their_slots_in_order() {
+        let template = parse("  h(0.6: main, v(logs, _, _))").unwrap();
+        assert_eq!(template.source, "h(0.6: main, v(logs, _, _))");
+        assert_eq!(template.slot_names(), vec!["main", "logs", "_", "_"]);
+
+        let TemplateNode::Split { dir, children } = &template.root else {
+            panic!("expected a split, got {:?}", template.root);
+        };
+        assert_eq!(*dir, SplitDir::Horizontal);
+        assert_eq!(children[0], (Some(0.6), TemplateNode::Leaf("main".into())));
+        assert!(matches!(
+            &children[1],
+            (None, TemplateNode::Split { dir: SplitDir::Vertical, children }) if children.len() == 3
+        ));
+    }
+
+    #[test]
+    fn slot_names_must_be_unique() {
+        assert_eq!(error("h(a, v(b, a))"), ("duplicate slot name 'a'".into(), 10..11));
+    }
+
+    #[test]
+    fn malformed_templates_point_at_the_problem() {
+        assert_eq!(error("h()"), ("split needs at least one child".into(), 1..3));
+        assert_eq!(
+            error("h(0.5 a, b)"),
+            ("expected ':' after weight, found name 'a'".into(), 6..7)
+        );
+        assert_eq!(error("h(0: a, b)"), ("weight must be greater than zero".into(), 2..3));
+        assert_eq!(error("h(a, b, c"), ("expected ',' or ')', found end of input".into(), 9..9));
+        assert_eq!(error("x(a, b)"), ("unknown split 'x', expected 'h' or 'v'".into(), 0..1));
+        assert_eq!(error("h(a, -1: b)"), ("unexpected character '-'".into(), 5..6));
+    }
+
+    #[test]
+    fn carets_line_up_under_non_ascii_names() {
+        let source = "h(café, café)";
+        let e = parse(source).unwrap_err();
+        assert_eq!(e.span, 9..14);
+        assert_eq!(
+            e.render(source),
+            "layout template error at 9..14: duplicate slot name 'café'\n  \
+             h(café, café)\n          ^^^^"
+        );
+    }
+}
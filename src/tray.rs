@@ -1,5 +1,5 @@
-use crate::config::Config;
-use crate::layout::{LayoutPreset, builtin_presets};
+use crate::config::{Config, LaunchProfile, Session};
+use crate::layout::{GridShape, LayoutPreset, builtin_presets};
 use tray_icon::menu::{Menu, MenuEvent, MenuId, MenuItem, Submenu};
 use tray_icon::{Icon, TrayIconBuilder, TrayIconEvent};
 
@@ -11,15 +11,23 @@ pub struct TrayIcon {
 /// Menu IDs extracted from tray setup — Send + Clone, safe to move to a thread.
 pub struct TrayMenuIds {
     pub open_id: MenuId,
+    pub undo_id: MenuId,
+    pub redo_id: MenuId,
     pub quit_id: MenuId,
-    pub layout_items: Vec<(MenuId, String, LayoutPreset, Option<(Vec<f32>, Vec<f32>)>)>,
+    pub layout_items: Vec<(MenuId, String, LayoutPreset, Option<GridShape>)>,
+    pub session_items: Vec<(MenuId, Session)>,
+    pub profile_items: Vec<(MenuId, LaunchProfile)>,
 }
 
 #[derive(Debug)]
 pub enum TrayAction {
     None,
     ShowGui,
-    ApplyLayout(String, LayoutPreset, Option<(Vec<f32>, Vec<f32>)>),
+    ApplyLayout(String, LayoutPreset, Option<GridShape>),
+    RestoreSession(Session),
+    LaunchProfile(LaunchProfile),
+    Undo,
+    Redo,
     Quit,
 }
 
@@ -49,25 +57,75 @@ pub fn create_tray(config: &Config) -> Option<(TrayIcon, TrayMenuIds)> {
             let item = MenuItem::new(&layout_def.name, true, None);
             let id = item.id().clone();
             let _ = layouts_submenu.append(&item);
-            layout_items.push((id, layout_def.name.clone(), preset, None));
+            let shape = layout_def.to_shape(&preset, &config.defaults);
+            layout_items.push((id, layout_def.name.clone(), preset, shape));
         }
     }
 
     if !config.saved_grid.is_empty() {
         let _ = layouts_submenu.append(&tray_icon::menu::PredefinedMenuItem::separator());
         for sg in &config.saved_grid {
-            let label = format!("{} ({}x{})", sg.name, sg.cols, sg.rows);
+            let Some(preset) = sg.to_preset() else {
+                continue;
+            };
+            let label = match &preset {
+                LayoutPreset::Grid { cols, rows } => format!("{} ({}x{})", sg.name, cols, rows),
+                other => format!("{} ({})", sg.name, other.display_name()),
+            };
             let item = MenuItem::new(&label, true, None);
             let id = item.id().clone();
             let _ = layouts_submenu.append(&item);
-            let weights = Some((sg.col_weights.clone(), sg.row_weights.clone()));
-            let preset = LayoutPreset::Grid { cols: sg.cols, rows: sg.rows };
-            layout_items.push((id, sg.name.clone(), preset, weights));
+            let shape = Some(sg.to_shape(&preset));
+            layout_items.push((id, sg.name.clone(), preset, shape));
+        }
+    }
+
+    if !config.free_layout.is_empty() {
+        let _ = layouts_submenu.append(&tray_icon::menu::PredefinedMenuItem::separator());
+        for fl in &config.free_layout {
+            let label = format!("{} (free)", fl.name);
+            let item = MenuItem::new(&label, true, None);
+            let id = item.id().clone();
+            let _ = layouts_submenu.append(&item);
+            let preset = LayoutPreset::Free(fl.rects.clone());
+            layout_items.push((id, fl.name.clone(), preset, None));
         }
     }
 
     let _ = menu.append(&layouts_submenu);
 
+    let mut session_items = Vec::new();
+    if !config.session.is_empty() {
+        let sessions_submenu = Submenu::new("Sessions", true);
+        for session in &config.session {
+            let item = MenuItem::new(&session.name, true, None);
+            let id = item.id().clone();
+            let _ = sessions_submenu.append(&item);
+            session_items.push((id, session.clone()));
+        }
+        let _ = menu.append(&sessions_submenu);
+    }
+
+    let mut profile_items = Vec::new();
+    if !config.profile.is_empty() {
+        let profiles_submenu = Submenu::new("Launch", true);
+        for profile in &config.profile {
+            let item = MenuItem::new(&profile.name, true, None);
+            let id = item.id().clone();
+            let _ = profiles_submenu.append(&item);
+            profile_items.push((id, profile.clone()));
+        }
+        let _ = menu.append(&profiles_submenu);
+    }
+
+    let undo_item = MenuItem::new("Undo Arrangement", true, None);
+    let undo_id = undo_item.id().clone();
+    let _ = menu.append(&undo_item);
+
+    let redo_item = MenuItem::new("Redo Arrangement", true, None);
+    let redo_id = redo_item.id().clone();
+    let _ = menu.append(&redo_item);
+
     let separator = tray_icon::menu::PredefinedMenuItem::separator();
     let _ = menu.append(&separator);
 
@@ -86,7 +144,15 @@ pub fn create_tray(config: &Config) -> Option<(TrayIcon, TrayMenuIds)> {
 
     Some((
         TrayIcon { _tray: tray },
-        TrayMenuIds { open_id, quit_id, layout_items },
+        TrayMenuIds {
+            open_id,
+            undo_id,
+            redo_id,
+            quit_id,
+            layout_items,
+            session_items,
+            profile_items,
+        },
     ))
 }
 
@@ -108,12 +174,28 @@ impl TrayMenuIds {
             if event.id == self.open_id {
                 return TrayAction::ShowGui;
             }
+            if event.id == self.undo_id {
+                return TrayAction::Undo;
+            }
+            if event.id == self.redo_id {
+                return TrayAction::Redo;
+            }
             if event.id == self.quit_id {
                 return TrayAction::Quit;
             }
-            for (id, name, preset, weights) in &self.layout_items {
+            for (id, name, preset, shape) in &self.layout_items {
+                if event.id == *id {
+                    return TrayAction::ApplyLayout(name.clone(), preset.clone(), shape.clone());
+                }
+            }
+            for (id, session) in &self.session_items {
+                if event.id == *id {
+                    return TrayAction::RestoreSession(session.clone());
+                }
+            }
+            for (id, profile) in &self.profile_items {
                 if event.id == *id {
-                    return TrayAction::ApplyLayout(name.clone(), preset.clone(), weights.clone());
+                    return TrayAction::LaunchProfile(profile.clone());
                 }
             }
         }
@@ -0,0 +1,474 @@
+use crate::arrange::{self, ArrangeRequest};
+use crate::backend::WindowBackend;
+use crate::config::{PinRule, SizeConstraint};
+use crate::constraints::limits_for;
+use crate::layout::{GridShape, LayoutPreset, Slot, Spacing};
+use crate::monitor::Rect;
+use crate::pinning;
+use crate::slot_memory::{self, SlotEntry};
+use crate::windows::{ManagedWindow, TargetFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What happens to the other windows when an arranged window closes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosePolicy {
+    /// Leave the slot empty for the next new window.
+    #[default]
+    LeaveGap,
+    /// Move every later window one slot forward.
+    ShiftUp,
+    /// Arrange everything again.
+    Relayout,
+}
+
+impl ClosePolicy {
+    pub const ALL: [ClosePolicy; 3] = [Self::LeaveGap, Self::ShiftUp, Self::Relayout];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::LeaveGap => "Leave gap",
+            Self::ShiftUp => "Shift up",
+            Self::Relayout => "Re-run layout",
+        }
+    }
+}
+
+/// The layout kept live, owned so the watcher thread can hold on to it.
+#[derive(Debug, Clone)]
+pub struct WatchTarget {
+    pub preset: LayoutPreset,
+    pub grid_shape: Option<GridShape>,
+    pub filter: TargetFilter,
+    pub monitor_spec: String,
+    pub spacing: Spacing,
+    pub disabled: HashSet<usize>,
+    pub app_hwnd: isize,
+    pub extra_exclude: Vec<String>,
+    pub pin_rules: Vec<PinRule>,
+    pub layout_name: Option<String>,
+    pub slot_names: Vec<String>,
+    pub size_rules: Vec<SizeConstraint>,
+    pub persist_slots: bool,
+    pub close_policy: ClosePolicy,
+}
+
+impl WatchTarget {
+    fn request(&self) -> ArrangeRequest<'_> {
+        ArrangeRequest {
+            preset: &self.preset,
+            grid_shape: self.grid_shape.as_ref(),
+            filter: &self.filter,
+            monitor_spec: &self.monitor_spec,
+            spacing: &self.spacing,
+            disabled: &self.disabled,
+            app_hwnd: self.app_hwnd,
+            extra_exclude: &self.extra_exclude,
+            smart_sort: false,
+            activity: None,
+            pin_rules: &self.pin_rules,
+            layout_name: self.layout_name.as_deref(),
+            slot_names: &self.slot_names,
+            placements: &[],
+            size_rules: &self.size_rules,
+            persist_slots: self.persist_slots,
+        }
+    }
+
+    /// Slot names of the enabled slots, as pin rules index them.
+    fn enabled_names(&self, slots: &[(usize, Slot)]) -> Vec<String> {
+        slots
+            .iter()
+            .map(|(i, _)| self.slot_names.get(*i).cloned().unwrap_or_default())
+            .collect()
+    }
+}
+
+/// Background thread that keeps a layout live while watch mode is on:
+/// windows that open go to their pinned slot or else the first free one,
+/// and windows that close are handled by the target's [`ClosePolicy`].
+/// Works by diffing window lists, so every backend supports it. Watcher
+/// moves aren't recorded for undo, so background re-tiles can't push the
+/// user's own arrangements out of the history or clear redo.
+pub struct Watcher {
+    target: Arc<Mutex<Option<WatchTarget>>>,
+}
+
+impl Watcher {
+    pub fn new(backend: Arc<dyn WindowBackend>) -> Self {
+        let target = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&target);
+        std::thread::Builder::new()
+            .name("watcher".into())
+            .spawn(move || {
+                watch_loop(shared, backend);
+            })
+            .expect("failed to spawn watcher thread");
+        Self { target }
+    }
+
+    /// Set the layout to keep live, or `None` to stop watching.
+    pub fn set_target(&self, target: Option<WatchTarget>) {
+        if let Ok(mut guard) = self.target.lock() {
+            *guard = target;
+        }
+    }
+}
+
+fn watch_loop(target: Arc<Mutex<Option<WatchTarget>>>, backend: Arc<dyn WindowBackend>) {
+    let mut state = WatchState::default();
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+
+        let Some(target) = target.lock().ok().and_then(|t| t.clone()) else {
+            state = WatchState::default();
+            continue;
+        };
+        state.poll(backend.as_ref(), &target);
+    }
+}
+
+/// What the watcher keeps between polls.
+#[derive(Default)]
+struct WatchState {
+    /// Slot memory key of the layout being watched.
+    watching: Option<String>,
+    /// Windows seen so far, except minimized ones that haven't been placed
+    /// yet: they count as new once they're restored.
+    known: HashSet<isize>,
+}
+
+impl WatchState {
+    fn poll(&mut self, backend: &dyn WindowBackend, target: &WatchTarget) {
+        let request = target.request();
+        let Some((layout, slots)) = arrange::enabled_slots(backend, &request) else {
+            return;
+        };
+        let windows =
+            backend.enumerate_windows(&target.filter, target.app_hwnd, &target.extra_exclude);
+
+        // Switching to watch mode or to another layout arranges everything once
+        if self.watching.as_deref() != Some(layout.as_str()) {
+            let arranged = arrange_all(backend, &request);
+            log::info!("Watch: arranged {} windows into the live layout", arranged);
+            self.watching = Some(layout);
+            self.known = windows.iter().map(|w| w.hwnd).collect();
+            return;
+        }
+
+        let current: HashSet<isize> = windows.iter().map(|w| w.hwnd).collect();
+        let opened: Vec<&ManagedWindow> = windows
+            .iter()
+            .filter(|w| !self.known.contains(&w.hwnd) && !w.is_minimized)
+            .collect();
+        let closed = self.known.iter().any(|hwnd| !current.contains(hwnd));
+        if opened.is_empty() && !closed {
+            return;
+        }
+
+        let remembered = slot_memory::recall(&layout, target.persist_slots);
+        let closed_arranged = remembered.iter().any(|e| !current.contains(&e.hwnd));
+        let mut entries: Vec<SlotEntry> = remembered
+            .into_iter()
+            .filter(|e| {
+                windows.iter().any(|w| {
+                    w.hwnd == e.hwnd && w.process_name.eq_ignore_ascii_case(&e.process)
+                })
+            })
+            .collect();
+
+        if closed_arranged {
+            match target.close_policy {
+                ClosePolicy::LeaveGap => {}
+                ClosePolicy::ShiftUp => shift_up(backend, target, &windows, &slots, &mut entries),
+                ClosePolicy::Relayout => {
+                    let arranged = arrange_all(backend, &request);
+                    log::info!("Watch: re-arranged {} windows after a close", arranged);
+                    self.known = current;
+                    return;
+                }
+            }
+        }
+
+        place_opened(backend, target, opened, &slots, &mut entries);
+        slot_memory::remember(&layout, entries, target.persist_slots);
+        self.known = windows
+            .iter()
+            .filter(|w| self.known.contains(&w.hwnd) || !w.is_minimized)
+            .map(|w| w.hwnd)
+            .collect();
+    }
+}
+
+/// Arrange every window, like headless mode: without an undo snapshot.
+fn arrange_all(backend: &dyn WindowBackend, request: &ArrangeRequest) -> usize {
+    let plan = arrange::plan_arrangement(backend, request);
+    let result = arrange::execute_plan(backend, &plan, request.persist_slots);
+    result.log_pins();
+    result.arranged
+}
+
+/// Place windows that opened since the last poll: pinned ones into their
+/// pinned slots first, the rest into the first free slots.
+fn place_opened(
+    backend: &dyn WindowBackend,
+    target: &WatchTarget,
+    opened: Vec<&ManagedWindow>,
+    slots: &[(usize, Slot)],
+    entries: &mut Vec<SlotEntry>,
+) {
+    let mut taken: Vec<bool> =
+        slots.iter().map(|(i, _)| entries.iter().any(|e| e.slot == *i)).collect();
+    let pins = pinning::resolve(
+        &target.pin_rules,
+        target.layout_name.as_deref(),
+        &target.preset,
+        &target.enabled_names(slots),
+        &opened,
+        &mut taken,
+    );
+    for (win, pin) in opened.into_iter().zip(pins) {
+        let position = match pin {
+            Some(pin) => pin.slot,
+            None => {
+                let Some(free) = taken.iter().position(|t| !t) else {
+                    continue;
+                };
+                taken[free] = true;
+                free
+            }
+        };
+        let (index, slot) = &slots[position];
+        if place(backend, target, win, slot) {
+            log::info!("Watch: placed '{}' in slot {}", win.title, index);
+            entries.push(SlotEntry {
+                hwnd: win.hwnd,
+                process: win.process_name.clone(),
+                slot: *index,
+            });
+        } else {
+            taken[position] = false;
+        }
+    }
+}
+
+/// Close the gaps: the arranged windows move to the first enabled slots,
+/// keeping their order. Windows in a slot a pin rule gives them stay put.
+fn shift_up(
+    backend: &dyn WindowBackend,
+    target: &WatchTarget,
+    windows: &[ManagedWindow],
+    slots: &[(usize, Slot)],
+    entries: &mut [SlotEntry],
+) {
+    let names = target.enabled_names(slots);
+    let position = |slot: usize| slots.iter().position(|(i, _)| *i == slot);
+    let pinned = |entry: &SlotEntry| {
+        let win = windows.iter().find(|w| w.hwnd == entry.hwnd);
+        match (win, position(entry.slot)) {
+            (Some(win), Some(p)) => pinning::is_pinned_to(
+                &target.pin_rules,
+                target.layout_name.as_deref(),
+                &target.preset,
+                &names,
+                win,
+                p,
+            ),
+            _ => false,
+        }
+    };
+
+    let held: HashSet<usize> = entries.iter().filter(|e| pinned(e)).map(|e| e.slot).collect();
+    let mut movable: Vec<&mut SlotEntry> =
+        entries.iter_mut().filter(|e| !held.contains(&e.slot)).collect();
+    movable.sort_by_key(|e| position(e.slot).unwrap_or(usize::MAX));
+    let free = slots.iter().filter(|(i, _)| !held.contains(i));
+    for (entry, (index, slot)) in movable.into_iter().zip(free) {
+        if entry.slot == *index {
+            continue;
+        }
+        let Some(win) = windows.iter().find(|w| w.hwnd == entry.hwnd) else {
+            continue;
+        };
+        if place(backend, target, win, slot) {
+            entry.slot = *index;
+        }
+    }
+}
+
+fn place(
+    backend: &dyn WindowBackend,
+    target: &WatchTarget,
+    win: &ManagedWindow,
+    slot: &Slot,
+) -> bool {
+    let fitted = limits_for(win, &target.size_rules).fit_into(slot);
+    let rect = Rect {
+        x: fitted.x,
+        y: fitted.y,
+        w: fitted.w,
+        h: fitted.h,
+    };
+    match backend.move_window(win.hwnd, &rect) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("Watch: failed to place '{}': {}", win.title, e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, Scenario};
+    use crate::config::Config;
+
+    const DESKTOP: &str = r#"
+        [[monitor]]
+        x = 0
+        y = 0
+        w = 1200
+        h = 800
+        primary = true
+
+        [[window]]
+        hwnd = 1
+        process = "pwsh.exe"
+        x = 0
+        y = 0
+        w = 300
+        h = 800
+
+        [[window]]
+        hwnd = 2
+        process = "code.exe"
+        x = 300
+        y = 0
+        w = 300
+        h = 800
+
+        [[window]]
+        hwnd = 3
+        process = "slack.exe"
+        x = 600
+        y = 0
+        w = 300
+        h = 800
+    "#;
+
+    fn target(pins: &str) -> WatchTarget {
+        let config: Config = toml::from_str(pins).unwrap();
+        WatchTarget {
+            preset: LayoutPreset::parse("columns:4").unwrap(),
+            grid_shape: None,
+            filter: TargetFilter::Universal,
+            monitor_spec: String::new(),
+            spacing: Spacing::default(),
+            disabled: HashSet::new(),
+            app_hwnd: 0,
+            extra_exclude: Vec::new(),
+            pin_rules: config.pin,
+            layout_name: Some("columns:4".into()),
+            slot_names: Vec::new(),
+            size_rules: Vec::new(),
+            persist_slots: false,
+            close_policy: ClosePolicy::ShiftUp,
+        }
+    }
+
+    fn setup(pins: &str) -> (FakeBackend, WatchTarget, Vec<ManagedWindow>, Vec<(usize, Slot)>) {
+        let fake = FakeBackend::new(toml::from_str::<Scenario>(DESKTOP).unwrap());
+        let target = target(pins);
+        let (_, slots) = arrange::enabled_slots(&fake, &target.request()).unwrap();
+        let windows = fake.enumerate_windows(&target.filter, 0, &[]);
+        (fake, target, windows, slots)
+    }
+
+    fn entry(hwnd: isize, process: &str, slot: usize) -> SlotEntry {
+        SlotEntry {
+            hwnd,
+            process: process.into(),
+            slot,
+        }
+    }
+
+    fn slot_of(entries: &[SlotEntry], hwnd: isize) -> Option<usize> {
+        entries.iter().find(|e| e.hwnd == hwnd).map(|e| e.slot)
+    }
+
+    #[test]
+    fn opened_windows_take_their_pinned_slot() {
+        let pins = r#"
+            [[pin]]
+            process = "code.exe"
+            slot = 3
+        "#;
+        let (fake, target, windows, slots) = setup(pins);
+        let mut entries = vec![entry(1, "pwsh.exe", 0)];
+        place_opened(&fake, &target, windows[1..].iter().collect(), &slots, &mut entries);
+        assert_eq!(slot_of(&entries, 2), Some(3));
+        assert_eq!(slot_of(&entries, 3), Some(1));
+        let code = fake.windows().into_iter().find(|w| w.hwnd == 2).unwrap();
+        assert_eq!((code.x, code.w), (slots[3].1.x, slots[3].1.w));
+
+        let (fake, target, windows, slots) = setup("");
+        let mut entries = vec![entry(1, "pwsh.exe", 0)];
+        place_opened(&fake, &target, windows[1..].iter().collect(), &slots, &mut entries);
+        assert_eq!(slot_of(&entries, 2), Some(1));
+        assert_eq!(slot_of(&entries, 3), Some(2));
+    }
+
+    #[test]
+    fn shift_up_leaves_pinned_windows() {
+        let pins = r#"
+            [[pin]]
+            process = "code.exe"
+            slot = 2
+        "#;
+        let (fake, target, windows, slots) = setup(pins);
+        // The window in slot 0 closed
+        let mut entries =
+            vec![entry(1, "pwsh.exe", 1), entry(2, "code.exe", 2), entry(3, "slack.exe", 3)];
+        shift_up(&fake, &target, &windows, &slots, &mut entries);
+        assert_eq!(slot_of(&entries, 1), Some(0));
+        assert_eq!(slot_of(&entries, 2), Some(2));
+        assert_eq!(slot_of(&entries, 3), Some(1));
+        let code = fake.windows().into_iter().find(|w| w.hwnd == 2).unwrap();
+        assert_eq!(code.x, 300, "pinned windows aren't moved");
+    }
+
+    #[test]
+    fn windows_opened_minimized_are_placed_once_restored() {
+        let (fake, target, _, slots) = setup("");
+        let mut state = WatchState::default();
+        state.poll(&fake, &target);
+
+        let window = r#"
+            hwnd = 4
+            process = "xterm"
+            x = 50
+            y = 50
+            w = 100
+            h = 100
+            minimized = true
+        "#;
+        fake.open(toml::from_str(window).unwrap());
+        state.poll(&fake, &target);
+        let rect = || fake.window_rect(4).unwrap();
+        assert_eq!((rect().x, rect().w), (50, 100), "minimized windows wait");
+
+        fake.restore(4);
+        state.poll(&fake, &target);
+        let rect = rect();
+        assert!(
+            slots.iter().any(|(_, s)| (s.x, s.y, s.w, s.h) == (rect.x, rect.y, rect.w, rect.h)),
+            "{rect:?} isn't a slot"
+        );
+    }
+}
@@ -1,16 +1,5 @@
 use crate::monitor::Rect;
-use windows::Win32::Foundation::{BOOL, CloseHandle, HWND, LPARAM, TRUE};
-use windows::Win32::System::ProcessStatus::K32GetModuleFileNameExW;
-use windows::Win32::System::Threading::{
-    OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
-};
-use windows::Win32::Foundation::HMODULE;
-use windows::Win32::UI::WindowsAndMessaging::{
-    BringWindowToTop, EnumWindows, GetClassNameW, GetWindowRect, GetWindowTextW,
-    GetWindowThreadProcessId, IsIconic, IsWindowVisible, GWL_EXSTYLE, GetWindowLongPtrW,
-    SW_HIDE, SW_MINIMIZE, SW_RESTORE, SW_SHOW, SetForegroundWindow, ShowWindow,
-    WS_EX_TOOLWINDOW,
-};
+use crate::rules::Rule;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum AppCategory {
@@ -53,6 +42,23 @@ impl AppCategory {
             Self::Other    => "Other",
         }
     }
+
+    /// Category by display name, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Self::Terminal,
+            Self::Browser,
+            Self::Editor,
+            Self::Chat,
+            Self::Media,
+            Self::Game,
+            Self::DevTool,
+            Self::System,
+            Self::Other,
+        ]
+        .into_iter()
+        .find(|c| c.display_name().eq_ignore_ascii_case(name))
+    }
 }
 
 pub fn categorize_process(name: &str) -> AppCategory {
@@ -63,18 +69,24 @@ pub fn categorize_process(name: &str) -> AppCategory {
         | "hyper.exe" | "mintty.exe" | "conhost.exe"
         | "conemu64.exe" | "conemu.exe" | "tabby.exe"
         | "terminus.exe" | "kitty.exe" | "rio.exe"
-        | "warp.exe" => AppCategory::Terminal,
+        | "warp.exe"
+        // Linux terminals (X11 process names have no extension)
+        | "xterm" | "gnome-terminal-server" | "konsole" | "alacritty"
+        | "kitty" | "foot" | "wezterm-gui" | "xfce4-terminal"
+        | "tilix" | "terminator" | "urxvt" | "st" => AppCategory::Terminal,
 
         // Browsers
         "chrome.exe" | "firefox.exe" | "msedge.exe"
         | "brave.exe" | "vivaldi.exe" | "opera.exe"
-        | "arc.exe" | "waterfox.exe" | "librewolf.exe" => AppCategory::Browser,
+        | "arc.exe" | "waterfox.exe" | "librewolf.exe"
+        | "firefox" | "firefox-bin" | "chrome" | "chromium" | "brave" => AppCategory::Browser,
 
         // Editors / IDEs
         "code.exe" | "devenv.exe" | "rider64.exe"
         | "idea64.exe" | "sublime_text.exe" | "notepad++.exe"
         | "notepad.exe" | "zed.exe" | "cursor.exe"
-        | "windsurf.exe" => AppCategory::Editor,
+        | "windsurf.exe"
+        | "code" | "zed" | "gedit" | "kate" | "sublime_text" => AppCategory::Editor,
 
         // Chat / Communication
         "discord.exe" | "slack.exe" | "teams.exe"
@@ -112,8 +124,14 @@ pub struct ManagedWindow {
     pub hwnd: isize,
     pub title: String,
     pub process_name: String,
+    /// Window class, empty if unknown.
+    pub class_name: String,
+    /// Full path of the executable, empty if unknown.
+    pub exe_path: String,
     pub category: AppCategory,
     pub rect: Rect,
+    /// Index of the monitor the window is mostly on.
+    pub monitor: usize,
     pub is_minimized: bool,
 }
 
@@ -122,6 +140,12 @@ pub enum TargetFilter {
     Terminals,
     Universal,
     Custom(Vec<String>),
+    /// Windows of `base` that match `include` (if set) and none of `exclude`.
+    Rules {
+        base: Box<TargetFilter>,
+        include: Option<Rule>,
+        exclude: Vec<Rule>,
+    },
 }
 
 impl TargetFilter {
@@ -144,16 +168,8 @@ impl TargetFilter {
         }
     }
 
-    pub fn display_name(&self) -> &str {
-        match self {
-            Self::Terminals => "Terminals",
-            Self::Universal => "Universal",
-            Self::Custom(_) => "Custom",
-        }
-    }
-
-    fn matches(&self, process_name: &str) -> bool {
-        let lower = process_name.to_lowercase();
+    fn matches(&self, win: &ManagedWindow) -> bool {
+        let lower = win.process_name.to_lowercase();
         match self {
             Self::Terminals => {
                 matches!(lower.as_str(),
@@ -164,10 +180,27 @@ impl TargetFilter {
                     | "conhost.exe" | "conemu64.exe" | "conemu.exe"
                     | "tabby.exe" | "terminus.exe"
                     | "kitty.exe" | "rio.exe" | "warp.exe"
+                    | "xterm" | "gnome-terminal-server" | "konsole" | "alacritty"
+                    | "kitty" | "foot" | "wezterm-gui" | "xfce4-terminal"
+                    | "tilix" | "terminator" | "urxvt" | "st"
                 )
             }
             Self::Universal => true, // Accept all — filtering done elsewhere
-            Self::Custom(names) => names.iter().any(|n| lower == *n),
+            Self::Custom(names) => names.contains(&lower),
+            Self::Rules { base, include, exclude } => {
+                base.matches(win)
+                    && include.as_ref().is_none_or(|r| r.matches(win))
+                    && !exclude.iter().any(|r| r.matches(win))
+            }
+        }
+    }
+
+    /// Whether system windows need filtering out, as every process passes.
+    fn is_universal(&self) -> bool {
+        match self {
+            Self::Universal => true,
+            Self::Rules { base, .. } => base.is_universal(),
+            _ => false,
         }
     }
 }
@@ -214,241 +247,33 @@ const EXCLUDED_PROCESSES: &[&str] = &[
     "windowsinternal.composableshell.experiences.textinput.inputapp.exe",
 ];
 
-pub fn find_windows(filter: &TargetFilter, app_hwnd: isize, extra_exclude: &[String]) -> Vec<ManagedWindow> {
-    struct EnumState {
-        filter: TargetFilter,
-        app_hwnd: isize,
-        extra_exclude: Vec<String>,
-        results: Vec<ManagedWindow>,
-    }
-
-    unsafe extern "system" fn enum_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
-        let state = &mut *(lparam.0 as *mut EnumState);
-
-        // Skip own window
-        if hwnd.0 as isize == state.app_hwnd {
-            return TRUE;
-        }
-
-        if !IsWindowVisible(hwnd).as_bool() {
-            return TRUE;
-        }
-
-        // Skip tool windows
-        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
-        if (ex_style as u32) & WS_EX_TOOLWINDOW.0 != 0 {
-            return TRUE;
-        }
-
-        // Get window rect
-        let mut rect = windows::Win32::Foundation::RECT::default();
-        if GetWindowRect(hwnd, &mut rect).is_err() {
-            return TRUE;
-        }
-        let w = rect.right - rect.left;
-        let h = rect.bottom - rect.top;
-        if w <= 0 || h <= 0 {
-            return TRUE;
-        }
-
-        // Get process name
-        let mut pid = 0u32;
-        GetWindowThreadProcessId(hwnd, Some(&mut pid));
-        if pid == 0 {
-            return TRUE;
-        }
-
-        let process_name = get_process_name(pid).unwrap_or_default();
-        if process_name.is_empty() {
-            return TRUE;
-        }
-
-        let lower = process_name.to_lowercase();
-
-        // Check user-configured exclusions
-        if state.extra_exclude.iter().any(|ex| lower == *ex) {
-            return TRUE;
-        }
+/// Whether a window belongs to the managed set: not excluded by the user,
+/// not a system window in Universal mode, and accepted by the filter.
+pub fn should_manage(filter: &TargetFilter, win: &ManagedWindow, extra_exclude: &[String]) -> bool {
+    let lower = win.process_name.to_lowercase();
+    let class_name = win.class_name.as_str();
 
-        // Universal mode: exclude system windows
-        if state.filter == TargetFilter::Universal {
-            // Check excluded processes
-            if EXCLUDED_PROCESSES.iter().any(|&p| lower == p) {
-                return TRUE;
-            }
-
-            // Check excluded window classes
-            let mut class_buf = [0u16; 256];
-            let class_len = GetClassNameW(hwnd, &mut class_buf);
-            if class_len > 0 {
-                let class_name = String::from_utf16_lossy(&class_buf[..class_len as usize]);
-                if EXCLUDED_CLASSES.iter().any(|&c| c == class_name) {
-                    return TRUE;
-                }
-            }
-
-            // Special case: explorer.exe windows that aren't File Explorer
-            // Only allow explorer.exe if it has the CabinetWClass (File Explorer window)
-            if lower == "explorer.exe" {
-                let mut class_buf2 = [0u16; 256];
-                let class_len2 = GetClassNameW(hwnd, &mut class_buf2);
-                if class_len2 > 0 {
-                    let class_name = String::from_utf16_lossy(&class_buf2[..class_len2 as usize]);
-                    if class_name != "CabinetWClass" {
-                        return TRUE;
-                    }
-                } else {
-                    return TRUE;
-                }
-            }
-        }
-
-        // Check filter match (for Terminals/Custom modes)
-        if !state.filter.matches(&process_name) {
-            return TRUE;
-        }
-
-        // Get window title
-        let mut buf = [0u16; 256];
-        let len = GetWindowTextW(hwnd, &mut buf);
-        let title = if len > 0 {
-            String::from_utf16_lossy(&buf[..len as usize])
-        } else {
-            String::new()
-        };
-
-        let is_minimized = IsIconic(hwnd).as_bool();
-        let category = categorize_process(&process_name);
-
-        state.results.push(ManagedWindow {
-            hwnd: hwnd.0 as isize,
-            title,
-            process_name,
-            category,
-            rect: Rect {
-                x: rect.left,
-                y: rect.top,
-                w,
-                h,
-            },
-            is_minimized,
-        });
-
-        TRUE
+    // Check user-configured exclusions
+    if extra_exclude.contains(&lower) {
+        return false;
     }
 
-    let mut state = EnumState {
-        filter: filter.clone(),
-        app_hwnd,
-        extra_exclude: extra_exclude.to_vec(),
-        results: Vec::with_capacity(32),
-    };
-
-    unsafe {
-        let _ = EnumWindows(
-            Some(enum_callback),
-            LPARAM(&mut state as *mut EnumState as isize),
-        );
-    }
-
-    state.results
-}
-
-pub fn focus_window(hwnd: isize) {
-    unsafe {
-        let h = HWND(hwnd as *mut _);
-        if IsIconic(h).as_bool() {
-            let _ = ShowWindow(h, SW_RESTORE);
+    // Universal mode: exclude system windows
+    if filter.is_universal() {
+        if EXCLUDED_PROCESSES.iter().any(|&p| lower == p) {
+            return false;
         }
-        let _ = SetForegroundWindow(h);
-    }
-}
-
-pub fn minimize_window(hwnd: isize) {
-    unsafe {
-        let _ = ShowWindow(HWND(hwnd as *mut _), SW_MINIMIZE);
-    }
-}
-
-pub fn restore_window(hwnd: isize) {
-    unsafe {
-        let h = HWND(hwnd as *mut _);
-        let _ = ShowWindow(h, SW_RESTORE);
-        let _ = SetForegroundWindow(h);
-    }
-}
-
-/// Show and restore the app window via direct Win32 calls.
-/// Works even when eframe's update loop is paused (hidden window).
-pub fn show_app_window(hwnd: isize) {
-    unsafe {
-        let h = HWND(hwnd as *mut _);
-        let _ = ShowWindow(h, SW_SHOW);
-        let _ = ShowWindow(h, SW_RESTORE);
-        let _ = BringWindowToTop(h);
-        let _ = SetForegroundWindow(h);
-    }
-}
-
-/// Hide the app window via direct Win32 call.
-pub fn hide_app_window(hwnd: isize) {
-    unsafe {
-        let _ = ShowWindow(HWND(hwnd as *mut _), SW_HIDE);
-    }
-}
-
-/// Get the HWND of the current foreground window.
-pub fn get_foreground_window() -> Option<isize> {
-    unsafe {
-        let hwnd = windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow();
-        if hwnd.0.is_null() {
-            None
-        } else {
-            Some(hwnd.0 as isize)
-        }
-    }
-}
-
-/// Get the process name for a given window handle.
-pub fn get_process_name_for_hwnd(hwnd: isize) -> Option<String> {
-    unsafe {
-        let mut pid = 0u32;
-        GetWindowThreadProcessId(HWND(hwnd as *mut _), Some(&mut pid));
-        if pid == 0 {
-            return None;
+        if EXCLUDED_CLASSES.contains(&class_name) {
+            return false;
         }
-        get_process_name(pid)
-    }
-}
 
-/// Get the title of a window by handle.
-pub fn get_window_title(hwnd: isize) -> String {
-    unsafe {
-        let mut buf = [0u16; 256];
-        let len = GetWindowTextW(HWND(hwnd as *mut _), &mut buf);
-        if len > 0 {
-            String::from_utf16_lossy(&buf[..len as usize])
-        } else {
-            String::new()
+        // Special case: explorer.exe windows that aren't File Explorer
+        // Only allow explorer.exe if it has the CabinetWClass (File Explorer window)
+        if lower == "explorer.exe" && class_name != "CabinetWClass" {
+            return false;
         }
     }
-}
 
-fn get_process_name(pid: u32) -> Option<String> {
-    unsafe {
-        let handle = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid).ok()?;
-        let mut buf = [0u16; 260];
-        let len = K32GetModuleFileNameExW(handle, HMODULE::default(), &mut buf);
-        let _ = CloseHandle(handle);
-
-        if len == 0 {
-            return None;
-        }
-
-        let full_path = String::from_utf16_lossy(&buf[..len as usize]);
-        full_path
-            .rsplit('\\')
-            .next()
-            .map(|s| s.to_string())
-    }
+    // Check filter match (for Terminals/Custom modes and rules)
+    filter.matches(win)
 }